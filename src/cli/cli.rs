@@ -12,11 +12,17 @@ pub mod validators {
 }
 
 use clap::{Arg, App, ArgMatches};
+use n_lang::code_generation::python_client::PythonParamStyle;
 use std::path::PathBuf;
 
 pub const PROJECT_DIR: &'static str = "Project's dir";
 pub const OUTPUT_TS_FILE: &'static str = "Output TypeScript file";
 pub const OUTPUT_SQL_FILE: &'static str = "Output T-SQL file";
+pub const OUTPUT_RUST_FILE: &'static str = "Output Rust file";
+pub const OUTPUT_PYTHON_FILE: &'static str = "Output Python file";
+pub const PYTHON_PARAM_STYLE: &'static str = "Python client's parameter style";
+pub const OUTPUT_JSON_SCHEMA_FILE: &'static str = "Output JSON Schema file";
+pub const OUTPUT_OPEN_API_FILE: &'static str = "Output OpenAPI file";
 
 pub fn build_cli_app<'a, 'b>() -> App<'a, 'b> {
     App::new("N-lang compiler")
@@ -42,6 +48,38 @@ pub fn build_cli_app<'a, 'b>() -> App<'a, 'b> {
                 .required(true)
                 .index(3)
         )
+        .arg(
+            Arg::with_name(OUTPUT_RUST_FILE)
+                .help("Destination location for result Rust client file")
+                .long("rust")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name(OUTPUT_PYTHON_FILE)
+                .help("Destination location for result Python client file")
+                .long("python")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name(PYTHON_PARAM_STYLE)
+                .help("DB-API parameter style used by result Python client file")
+                .long("python-paramstyle")
+                .takes_value(true)
+                .possible_values(&["qmark", "format"])
+                .default_value("qmark")
+        )
+        .arg(
+            Arg::with_name(OUTPUT_JSON_SCHEMA_FILE)
+                .help("Destination location for JSON Schema of exported data types")
+                .long("json-schema")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name(OUTPUT_OPEN_API_FILE)
+                .help("Destination location for OpenAPI document of exported functions")
+                .long("openapi")
+                .takes_value(true)
+        )
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
@@ -49,6 +87,11 @@ pub struct CLIConfig {
     pub projects_dir: PathBuf,
     pub output_typescript_file: PathBuf,
     pub output_tsql_file: PathBuf,
+    pub output_rust_file: Option<PathBuf>,
+    pub output_python_file: Option<PathBuf>,
+    pub python_param_style: PythonParamStyle,
+    pub output_json_schema_file: Option<PathBuf>,
+    pub output_open_api_file: Option<PathBuf>,
 }
 
 fn extract_required_param<'a>(matches: &'a ArgMatches, param: &str) -> &'a str {
@@ -64,5 +107,11 @@ pub fn match_cli_config() -> CLIConfig {
         projects_dir: PathBuf::from(extract_required_param(&matches, PROJECT_DIR)),
         output_typescript_file: PathBuf::from(extract_required_param(&matches, OUTPUT_TS_FILE)),
         output_tsql_file: PathBuf::from(extract_required_param(&matches, OUTPUT_SQL_FILE)),
+        output_rust_file: matches.value_of(OUTPUT_RUST_FILE).map(PathBuf::from),
+        output_python_file: matches.value_of(OUTPUT_PYTHON_FILE).map(PathBuf::from),
+        python_param_style: PythonParamStyle::from_name(extract_required_param(&matches, PYTHON_PARAM_STYLE))
+            .expect("Parameter style's name should be already validated"),
+        output_json_schema_file: matches.value_of(OUTPUT_JSON_SCHEMA_FILE).map(PathBuf::from),
+        output_open_api_file: matches.value_of(OUTPUT_OPEN_API_FILE).map(PathBuf::from),
     }
 }
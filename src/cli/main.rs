@@ -22,9 +22,27 @@ fn write(filename: &Path, content: &str) -> Result<(), Box<Error>> {
 
 fn do_it() -> Result<(), Box<Error>> {
     let config = cli::match_cli_config();
-    let (db, rpc) = resolve::resolve_dir(&config.projects_dir)?;
+    let (db, rpc, warnings) = resolve::resolve_dir(&config.projects_dir)?;
+    for warning in warnings {
+        println!("Warning: {}", warning);
+    }
     write(&config.output_tsql_file, &db.generate_string()?)?;
     write(&config.output_typescript_file, &rpc.generate_string()?)?;
+    if let Some(output_rust_file) = &config.output_rust_file {
+        write(output_rust_file, &rpc.generate_rust_string()?)?;
+    }
+    if let Some(output_python_file) = &config.output_python_file {
+        write(output_python_file, &rpc.generate_python_string_with_param_style(config.python_param_style)?)?;
+    }
+    if let Some(output_json_schema_file) = &config.output_json_schema_file {
+        write(output_json_schema_file, &rpc.generate_json_schema_string()?)?;
+    }
+    if let Some(output_open_api_file) = &config.output_open_api_file {
+        let title = config.projects_dir.file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("N-lang project");
+        write(output_open_api_file, &rpc.generate_open_api_string(title, "0.1.0")?)?;
+    }
     Ok(())
 }
 
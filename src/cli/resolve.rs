@@ -8,6 +8,7 @@ use n_lang::{
         Resolve,
     },
     project_analysis::{
+        SemanticError,
         SemanticErrors,
         HashMapSource,
         ProjectContext,
@@ -19,7 +20,7 @@ use std::{
 };
 use stdlib::build_ms_sql_std_lib;
 
-pub fn resolve_dir(path: &Path) -> Result<(DatabaseProject, RPCModule), Box<Error>> {
+pub fn resolve_dir(path: &Path) -> Result<(DatabaseProject, RPCModule, Vec<SemanticError>), Box<Error>> {
     let sources = HashMapSource::for_dir(path)?;
     let project_context = ProjectContext::new(SyncRef::new(build_ms_sql_std_lib()));
     for (module_path, _) in sources.texts() {
@@ -27,8 +28,12 @@ pub fn resolve_dir(path: &Path) -> Result<(DatabaseProject, RPCModule), Box<Erro
     }
     let project = project_context.resolve(&sources)
         .map_err(|errors| SemanticErrors::from(errors))?;
+    let warnings = project.values()
+        .flat_map(|module| module.read().warnings().clone())
+        .collect();
     Ok((
         DatabaseProject::new(&project),
         RPCModule::top(&project),
+        warnings,
     ))
 }
@@ -1,6 +1,7 @@
 use n_lang::{
     project_analysis::{
         StdLib,
+        StdLibArgument,
         StdLibBinaryOperation,
         StdLibFunction,
         StdLibPrefixUnaryOperation,
@@ -20,6 +21,11 @@ const INT_TYPE: DataType = DataType::Primitive(PrimitiveDataType::Number(NumberT
     unsigned: false,
     zerofill: false,
 }));
+const BIG_INT_TYPE: DataType = DataType::Primitive(PrimitiveDataType::Number(NumberType::Integer {
+    size: 64,
+    unsigned: false,
+    zerofill: false,
+}));
 
 #[inline]
 fn reg_boolean(
@@ -170,6 +176,39 @@ fn reg_arithmetic(
     );
 }
 
+#[inline]
+fn reg_window_functions(target: &mut StdLib) {
+    target.reg_function(
+        StdLibFunction::new("row_number".to_string())
+            .returns(BIG_INT_TYPE)
+            .ordered()
+    );
+
+    target.reg_function(
+        StdLibFunction::new("rank".to_string())
+            .returns(BIG_INT_TYPE)
+            .ordered()
+    );
+
+    target.reg_function(
+        StdLibFunction::new("dense_rank".to_string())
+            .returns(BIG_INT_TYPE)
+            .ordered()
+    );
+
+    target.reg_function(
+        StdLibFunction::new("lag".to_string())
+            .gets_arguments(vec![
+                StdLibArgument::Generic,
+                StdLibArgument::Typed(BIG_INT_TYPE),
+                StdLibArgument::Generic,
+            ])
+            .optional_from(1)
+            .returns_generic()
+            .ordered()
+    );
+}
+
 #[inline]
 fn reg_int_arithmetic(
     target: &mut StdLib,
@@ -228,5 +267,6 @@ pub fn build_ms_sql_std_lib() -> StdLib {
     reg_int_arithmetic(&mut stdlib, 32);
     reg_int_arithmetic(&mut stdlib, 64);
     reg_float_arithmetic(&mut stdlib);
+    reg_window_functions(&mut stdlib);
     stdlib
 }
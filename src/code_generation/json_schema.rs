@@ -0,0 +1,374 @@
+use helpers::{
+    Path,
+    PathBuf,
+    SimpleFormatter,
+};
+use language::{
+    CompoundDataType,
+    DataType,
+    DateTimeType,
+    FunctionDefinition,
+    NumberType,
+    PrimitiveDataType,
+    StringType,
+};
+use std::fmt::{
+    self,
+    Write,
+};
+use super::RPCModule;
+
+const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+const OPEN_API_VERSION: &str = "3.1.0";
+
+/// Минимальное представление JSON-документа для вывода схем
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    #[inline]
+    pub fn string(value: impl ToString) -> Self {
+        Json::String(value.to_string())
+    }
+    #[inline]
+    pub fn number(value: impl ToString) -> Self {
+        Json::Number(value.to_string())
+    }
+    pub fn fmt_string(f: &mut impl Write, value: &str) -> fmt::Result {
+        f.write_char('"')?;
+        for c in value.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+    pub fn fmt(&self, f: &mut SimpleFormatter) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(value) => f.write_str(value),
+            Json::String(value) => Json::fmt_string(f, value),
+            Json::Array(items) => {
+                if items.is_empty() {
+                    return f.write_str("[]");
+                }
+                writeln!(f, "[")?;
+                {
+                    let mut sub_f = f.sub_block();
+                    let mut items = items.iter().peekable();
+                    while let Some(item) = items.next() {
+                        item.fmt(&mut sub_f)?;
+                        if items.peek().is_some() {
+                            sub_f.write_char(',')?;
+                        }
+                        writeln!(sub_f, "")?;
+                    }
+                }
+                write!(f, "]")
+            }
+            Json::Object(properties) => {
+                if properties.is_empty() {
+                    return f.write_str("{}");
+                }
+                writeln!(f, "{{")?;
+                {
+                    let mut sub_f = f.sub_block();
+                    let mut properties = properties.iter().peekable();
+                    while let Some((name, value)) = properties.next() {
+                        Json::fmt_string(&mut sub_f, name)?;
+                        sub_f.write_str(": ")?;
+                        value.fmt(&mut sub_f)?;
+                        if properties.peek().is_some() {
+                            sub_f.write_char(',')?;
+                        }
+                        writeln!(sub_f, "")?;
+                    }
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+    pub fn generate_string(&self) -> Result<String, fmt::Error> {
+        let mut result = String::new();
+        {
+            let mut formatter = SimpleFormatter::new(&mut result, 2);
+            self.fmt(&mut formatter)?;
+            writeln!(formatter, "")?;
+        }
+        Ok(result)
+    }
+}
+
+fn integer_schema(minimum: i128, maximum: u128) -> Json {
+    Json::Object(vec![
+        ("type".to_string(), Json::string("integer")),
+        ("minimum".to_string(), Json::number(minimum)),
+        ("maximum".to_string(), Json::number(maximum)),
+    ])
+}
+
+fn string_schema(format: &str) -> Json {
+    Json::Object(vec![
+        ("type".to_string(), Json::string("string")),
+        ("format".to_string(), Json::string(format)),
+    ])
+}
+
+pub fn primitive_schema(primitive: &PrimitiveDataType) -> Json {
+    let mut schema = Vec::new();
+    match primitive {
+        PrimitiveDataType::Null => {
+            schema.push(("type".to_string(), Json::string("null")));
+        }
+        PrimitiveDataType::Number(NumberType::Bit { size }) => match size {
+            Some(0) => schema.push(("type".to_string(), Json::string("null"))),
+            Some(1) | None => schema.push(("type".to_string(), Json::string("boolean"))),
+            Some(size) => return integer_schema(0, (1u128 << (*size).min(64)) - 1),
+        },
+        PrimitiveDataType::Number(NumberType::Boolean) => {
+            schema.push(("type".to_string(), Json::string("boolean")));
+        }
+        PrimitiveDataType::Number(NumberType::Integer { size, unsigned, .. }) => {
+            let size = u32::from(*size).min(64);
+            return if *unsigned {
+                integer_schema(0, (1u128 << size) - 1)
+            } else {
+                integer_schema(-(1i128 << (size - 1)), (1u128 << (size - 1)) - 1)
+            };
+        }
+        PrimitiveDataType::Number(NumberType::Decimal { size, unsigned, .. }) => {
+            schema.push(("type".to_string(), Json::string("number")));
+            // decimal(p, s) хранит не более p - s цифр в целой части
+            if let Some((precision, scale)) = size {
+                let digits = precision.saturating_sub(scale.unwrap_or(0));
+                let bound = format!("1{}", "0".repeat(digits as usize));
+                if !*unsigned {
+                    schema.push(("exclusiveMinimum".to_string(), Json::number(format!("-{}", bound))));
+                }
+                schema.push(("exclusiveMaximum".to_string(), Json::number(bound)));
+            }
+            if *unsigned {
+                schema.push(("minimum".to_string(), Json::number(0)));
+            }
+        }
+        PrimitiveDataType::Number(NumberType::Float { .. }) => {
+            schema.push(("type".to_string(), Json::string("number")));
+        }
+        PrimitiveDataType::DateTime(DateTimeType::Date) => return string_schema("date"),
+        PrimitiveDataType::DateTime(DateTimeType::Time { .. }) => return string_schema("time"),
+        PrimitiveDataType::DateTime(_) => return string_schema("date-time"),
+        PrimitiveDataType::Year(_) => {
+            schema.push(("type".to_string(), Json::string("integer")));
+        }
+        PrimitiveDataType::String(StringType::Varchar { size, .. }) => {
+            schema.push(("type".to_string(), Json::string("string")));
+            if let Some(size) = size {
+                schema.push(("maxLength".to_string(), Json::number(size)));
+            }
+        }
+        PrimitiveDataType::String(StringType::Text { .. }) |
+        PrimitiveDataType::String(StringType::Json) => {
+            schema.push(("type".to_string(), Json::string("string")));
+        }
+    }
+    Json::Object(schema)
+}
+
+/// Строит схему типа данных. Ссылки на именованные типы указывают в `refs_prefix`.
+pub fn data_type_schema(data_type: &DataType, refs_prefix: &str) -> Json {
+    match data_type {
+        DataType::Array(sub_type) => Json::Object(vec![
+            ("type".to_string(), Json::string("array")),
+            ("items".to_string(), data_type_schema(sub_type, refs_prefix)),
+        ]),
+        DataType::Compound(CompoundDataType::Structure(fields)) => {
+            let properties = fields.iter()
+                .map(|(name, field)| (name.clone(), data_type_schema(&field.field_type, refs_prefix)))
+                .collect();
+            let required = fields.keys()
+                .map(Json::string)
+                .collect();
+            Json::Object(vec![
+                ("type".to_string(), Json::string("object")),
+                ("properties".to_string(), Json::Object(properties)),
+                ("required".to_string(), Json::Array(required)),
+                ("additionalProperties".to_string(), Json::Bool(false)),
+            ])
+        }
+        DataType::Compound(CompoundDataType::Tuple(fields)) => {
+            let items = fields.iter()
+                .map(|field| data_type_schema(&field.field_type, refs_prefix))
+                .collect();
+            Json::Object(vec![
+                ("type".to_string(), Json::string("array")),
+                ("prefixItems".to_string(), Json::Array(items)),
+                ("items".to_string(), Json::Bool(false)),
+                ("minItems".to_string(), Json::number(fields.len())),
+            ])
+        }
+        DataType::Enum(enum_type) => {
+            let (value_type, values) = if enum_type.is_text {
+                ("string", enum_type.variants.iter().map(Json::string).collect())
+            } else {
+                ("integer", (0..enum_type.variants.len()).map(Json::number).collect())
+            };
+            Json::Object(vec![
+                ("type".to_string(), Json::string(value_type)),
+                ("enum".to_string(), Json::Array(values)),
+            ])
+        }
+        DataType::Primitive(primitive) => primitive_schema(primitive),
+        DataType::Reference(item) => {
+            let item_guard = item.read();
+            let path = item_guard.get_path().as_path().into_new_buf(".");
+            Json::Object(vec![
+                ("$ref".to_string(), Json::string(format!("{}{}", refs_prefix, path))),
+            ])
+        }
+        DataType::Void => Json::Object(vec![
+            ("type".to_string(), Json::string("null")),
+        ]),
+    }
+}
+
+fn function_operation(function: &FunctionDefinition, module_path: Path, refs_prefix: &str) -> Json {
+    let mut properties = Vec::with_capacity(function.arguments.len());
+    for (argument_name, argument) in &function.arguments {
+        let argument_guard = argument.read();
+        let argument_data_type = argument_guard.data_type()
+            .expect("Variable's data-type should not be unknown at generate-time");
+        properties.push((argument_name.clone(), data_type_schema(argument_data_type, refs_prefix)));
+    }
+    let required = function.arguments.keys()
+        .map(Json::string)
+        .collect();
+    let request_schema = Json::Object(vec![
+        ("type".to_string(), Json::string("object")),
+        ("properties".to_string(), Json::Object(properties)),
+        ("required".to_string(), Json::Array(required)),
+        ("additionalProperties".to_string(), Json::Bool(false)),
+    ]);
+
+    let description = format!("Result of {}::{}", module_path, function.name);
+    let response = if function.result == DataType::Void {
+        ("204".to_string(), Json::Object(vec![
+            ("description".to_string(), Json::string(description)),
+        ]))
+    } else {
+        ("200".to_string(), Json::Object(vec![
+            ("description".to_string(), Json::string(description)),
+            ("content".to_string(), Json::Object(vec![
+                ("application/json".to_string(), Json::Object(vec![
+                    ("schema".to_string(), data_type_schema(&function.result, refs_prefix)),
+                ])),
+            ])),
+        ]))
+    };
+
+    Json::Object(vec![
+        ("operationId".to_string(), Json::string(format!(
+            "{}.{}",
+            module_path.into_new_buf("."),
+            function.name,
+        ))),
+        ("requestBody".to_string(), Json::Object(vec![
+            ("required".to_string(), Json::Bool(true)),
+            ("content".to_string(), Json::Object(vec![
+                ("application/json".to_string(), Json::Object(vec![
+                    ("schema".to_string(), request_schema),
+                ])),
+            ])),
+        ])),
+        ("responses".to_string(), Json::Object(vec![response])),
+    ])
+}
+
+impl RPCModule {
+    fn collect_schemas(&self, path: Path, refs_prefix: &str, target: &mut Vec<(String, Json)>) {
+        for (module_name, module) in self.sub_modules.iter() {
+            module.collect_schemas(
+                PathBuf::from_paths(
+                    path,
+                    Path::new(module_name.as_str(), "::"),
+                ).as_path(),
+                refs_prefix,
+                target,
+            );
+        }
+        for (name, data_type) in self.data_types.iter() {
+            let mut data_type_path = path.into_new_buf(".");
+            data_type_path.push(name);
+            target.push((data_type_path.data, data_type_schema(data_type, refs_prefix)));
+        }
+    }
+    fn collect_operations(&self, path: Path, refs_prefix: &str, target: &mut Vec<(String, Json)>) {
+        for (module_name, module) in self.sub_modules.iter() {
+            module.collect_operations(
+                PathBuf::from_paths(
+                    path,
+                    Path::new(module_name.as_str(), "::"),
+                ).as_path(),
+                refs_prefix,
+                target,
+            );
+        }
+        for (name, function) in self.functions.iter() {
+            let mut operation_path = path.into_new_buf("/");
+            operation_path.push(name);
+            target.push((
+                format!("/{}", operation_path),
+                Json::Object(vec![
+                    ("post".to_string(), function_operation(function, path, refs_prefix)),
+                ]),
+            ));
+        }
+    }
+    pub fn json_schema(&self) -> Json {
+        let mut definitions = Vec::new();
+        self.collect_schemas(Path::new("", "::"), "#/$defs/", &mut definitions);
+        Json::Object(vec![
+            ("$schema".to_string(), Json::string(JSON_SCHEMA_DIALECT)),
+            ("$defs".to_string(), Json::Object(definitions)),
+        ])
+    }
+    pub fn open_api(&self, title: &str, version: &str) -> Json {
+        let refs_prefix = "#/components/schemas/";
+        let mut schemas = Vec::new();
+        self.collect_schemas(Path::new("", "::"), refs_prefix, &mut schemas);
+        let mut paths = Vec::new();
+        self.collect_operations(Path::new("", "::"), refs_prefix, &mut paths);
+        Json::Object(vec![
+            ("openapi".to_string(), Json::string(OPEN_API_VERSION)),
+            ("info".to_string(), Json::Object(vec![
+                ("title".to_string(), Json::string(title)),
+                ("version".to_string(), Json::string(version)),
+            ])),
+            ("paths".to_string(), Json::Object(paths)),
+            ("components".to_string(), Json::Object(vec![
+                ("schemas".to_string(), Json::Object(schemas)),
+            ])),
+        ])
+    }
+    #[inline]
+    pub fn generate_json_schema_string(&self) -> Result<String, fmt::Error> {
+        self.json_schema().generate_string()
+    }
+    #[inline]
+    pub fn generate_open_api_string(&self, title: &str, version: &str) -> Result<String, fmt::Error> {
+        self.open_api(title, version).generate_string()
+    }
+}
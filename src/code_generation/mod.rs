@@ -11,7 +11,9 @@ use helpers::{
 };
 use indexmap::IndexMap;
 use language::{
+    ConstDefinition,
     DataType,
+    ErrorDefinition,
     FunctionDefinition,
     TableDefinition,
 };
@@ -23,10 +25,24 @@ use std::{
     },
 };
 
+pub mod json_schema;
+pub mod python_client;
+pub mod rust_client;
+
+/// Классы ошибок клиента и соответствующие им номера ошибок MS SQL
+const SQL_ERROR_CLASSES: &[(&str, &[u32])] = &[
+    ("UniqueViolationError", &[2601, 2627]),
+    ("ForeignKeyViolationError", &[547]),
+    ("DeadlockError", &[1205]),
+    ("RaisedError", &[50000]),
+];
+
 #[derive(Debug, Clone)]
 pub struct RPCModule {
     data_types: Map<String, DataType>,
+    constants: Map<String, ConstDefinition>,
     functions: Map<String, FunctionDefinition>,
+    errors: Map<String, ErrorDefinition>,
     sub_modules: Map<String, RPCModule>,
 }
 
@@ -42,7 +58,9 @@ impl RPCModule {
         sub_modules.sort();
         RPCModule {
             data_types: Map::new(),
+            constants: Map::new(),
             functions: Map::new(),
+            errors: Map::new(),
             sub_modules,
         }
     }
@@ -50,7 +68,9 @@ impl RPCModule {
         let source_guard = source.read();
 
         let mut data_types = Map::new();
+        let mut constants = Map::new();
         let mut functions = Map::new();
+        let mut errors = Map::new();
         let mut sub_modules = Map::new();
 
         for (item_name, item) in source_guard.items() {
@@ -62,6 +82,10 @@ impl RPCModule {
                 functions.insert(item_name.as_str(), function.clone());
             } else if let Some(table) = item_guard.get_table() {
                 sub_modules.insert(item_name.as_str(), RPCModule::for_table(table));
+            } else if let Some(error) = item_guard.get_error() {
+                errors.insert(item_name.as_str(), error.clone());
+            } else if let Some(constant) = item_guard.get_constant() {
+                constants.insert(item_name.as_str(), constant.clone());
             }
         }
 
@@ -77,11 +101,15 @@ impl RPCModule {
                 }
         }
         data_types.sort();
+        constants.sort();
         functions.sort();
+        errors.sort();
         sub_modules.sort();
         RPCModule {
             data_types,
+            constants,
             functions,
+            errors,
             sub_modules,
         }
     }
@@ -91,7 +119,9 @@ impl RPCModule {
         data_types.insert("primary_key", table.primary_key.clone());
         RPCModule {
             data_types,
+            constants: Map::new(),
             functions: Map::new(),
+            errors: Map::new(),
             sub_modules: Map::new(),
         }
     }
@@ -110,11 +140,122 @@ impl RPCModule {
         for (name, data_type) in self.data_types.iter() {
             data_type.fmt_export(f, &name)?;
         }
+        for (_name, constant) in self.constants.iter() {
+            constant.fmt_export(f)?;
+        }
+        for (name, error) in self.errors.iter() {
+            writeln!(f, "export class {} extends NLangError {{ static readonly number = {} }}", name, error.number)?;
+        }
         for (_name, function) in self.functions.iter() {
             function.fmt_export(f, path)?;
         }
         Ok(())
     }
+    /// Собирает объявленные в N ошибки вместе с полными именами их классов
+    fn collect_errors<'a>(&'a self, prefix: &str, result: &mut Vec<(String, &'a ErrorDefinition)>) {
+        for (name, error) in self.errors.iter() {
+            result.push((format!("{}{}", prefix, name), error));
+        }
+        for (module_name, module) in self.sub_modules.iter() {
+            module.collect_errors(&format!("{}{}.", prefix, module_name), result);
+        }
+    }
+    pub fn fmt_errors(&self, f: &mut SimpleFormatter) -> fmt::Result {
+        writeln!(f, "export class NLangError extends Error {{")?;
+        {
+            let mut class_f = f.sub_block();
+            writeln!(class_f, "constructor(message: string, public readonly cause?: any) {{")?;
+            {
+                let mut sub_f = class_f.sub_block();
+                writeln!(sub_f, "super(message)")?;
+                writeln!(sub_f, "Object.setPrototypeOf(this, new.target.prototype)")?;
+                writeln!(sub_f, "this.name = new.target.name")?;
+            }
+            writeln!(class_f, "}}")?;
+            // Исключения, не пришедшие от сервера, пробрасываются как есть
+            writeln!(class_f, "static wrap(error: any): any {{")?;
+            {
+                let mut sub_f = class_f.sub_block();
+                writeln!(sub_f, "if (error instanceof NLangError || !(error && typeof error.number === 'number')) {{")?;
+                writeln!(sub_f.sub_block(), "return error")?;
+                writeln!(sub_f, "}}")?;
+                writeln!(sub_f, "const message = error.message || String(error)")?;
+                writeln!(sub_f, "switch (error.number) {{")?;
+                {
+                    let mut switch_f = sub_f.sub_block();
+                    for (class_name, numbers) in SQL_ERROR_CLASSES {
+                        for number in numbers.iter() {
+                            writeln!(switch_f, "case {}:", number)?;
+                        }
+                        writeln!(switch_f.sub_block(), "return new {}(message, error)", class_name)?;
+                    }
+                    // Номера объявленных ошибок передаются только оператором THROW
+                    let mut user_errors = Vec::new();
+                    self.collect_errors("", &mut user_errors);
+                    for (class_name, error) in user_errors {
+                        writeln!(switch_f, "case {}:", error.number)?;
+                        writeln!(switch_f.sub_block(), "return new {}(message, error)", class_name)?;
+                    }
+                    writeln!(switch_f, "default:")?;
+                    writeln!(switch_f.sub_block(), "return new NLangError(message, error)")?;
+                }
+                writeln!(sub_f, "}}")?;
+            }
+            writeln!(class_f, "}}")?;
+        }
+        writeln!(f, "}}")?;
+        for (class_name, _) in SQL_ERROR_CLASSES {
+            writeln!(f, "export class {} extends NLangError {{}}", class_name)?;
+        }
+        writeln!(f, "")
+    }
+    pub fn fmt_helpers(&self, f: &mut SimpleFormatter) -> fmt::Result {
+        // Вложенные массивы приходят JSON-строкой
+        writeln!(f, "function _jsonArray(value: any): any[] {{")?;
+        {
+            let mut body_f = f.sub_block();
+            writeln!(body_f, "if (value === null || value === undefined) {{")?;
+            writeln!(body_f.sub_block(), "return []")?;
+            writeln!(body_f, "}}")?;
+            writeln!(body_f, "return JSON.parse(value)")?;
+        }
+        writeln!(f, "}}")?;
+        writeln!(f, "")
+    }
+    pub fn fmt_transactions(&self, f: &mut SimpleFormatter) -> fmt::Result {
+        // Процедуры можно вызывать как на пуле соединений, так и внутри транзакции
+        writeln!(f, "export type Connection = _mssql.ConnectionPool | _mssql.Transaction")?;
+        writeln!(f, "")?;
+        writeln!(f, "export async function withTransaction<T>(")?;
+        {
+            let mut args_f = f.sub_block();
+            writeln!(args_f, "_pool: _mssql.ConnectionPool,")?;
+            writeln!(args_f, "_body: (tx: _mssql.Transaction) => Promise<T>,")?;
+        }
+        writeln!(f, "): Promise<T> {{")?;
+        {
+            let mut body_f = f.sub_block();
+            writeln!(body_f, "const _tx = new _mssql.Transaction(_pool)")?;
+            writeln!(body_f, "await _tx.begin()")?;
+            writeln!(body_f, "try {{")?;
+            {
+                let mut try_f = body_f.sub_block();
+                writeln!(try_f, "const _result = await _body(_tx)")?;
+                writeln!(try_f, "await _tx.commit()")?;
+                writeln!(try_f, "return _result")?;
+            }
+            writeln!(body_f, "}} catch (_error) {{")?;
+            {
+                let mut catch_f = body_f.sub_block();
+                // Сервер мог уже откатить транзакцию сам (например, при взаимной блокировке)
+                writeln!(catch_f, "await _tx.rollback().catch(() => undefined)")?;
+                writeln!(catch_f, "throw NLangError.wrap(_error)")?;
+            }
+            writeln!(body_f, "}}")?;
+        }
+        writeln!(f, "}}")?;
+        writeln!(f, "")
+    }
     pub fn generate_string(&self) -> Result<String, fmt::Error> {
         let mut result = String::new();
         {
@@ -124,6 +265,9 @@ impl RPCModule {
             writeln!(formatter, "import * as _mssql from 'mssql'")?;
             writeln!(formatter, "")?;
 
+            self.fmt_helpers(&mut formatter)?;
+            self.fmt_transactions(&mut formatter)?;
+            self.fmt_errors(&mut formatter)?;
             self.fmt(&mut formatter, Path::new("", "::"))?;
         }
         Ok(result)
@@ -0,0 +1,514 @@
+use helpers::{
+    Path,
+    PathBuf,
+    SimpleFormatter,
+    TSQL,
+    TSQLParameters,
+};
+use language::{
+    CompoundDataType,
+    DataType,
+    DateTimeType,
+    FunctionDefinition,
+    PrimitiveDataType,
+};
+use std::fmt::{
+    self,
+    Write,
+};
+use super::RPCModule;
+
+/// Общая часть Python-клиента, не зависящая от проекта
+const PYTHON_PRELUDE: &str = r#"import json
+from dataclasses import dataclass
+from datetime import date, datetime, time
+from decimal import Decimal
+from enum import Enum, IntEnum
+from typing import Any, Dict, List, Tuple
+
+
+def _query(connection, sql, parameters):
+    cursor = connection.cursor()
+    try:
+        cursor.execute(sql, parameters)
+        while cursor.description is None:
+            if not cursor.nextset():
+                return []
+        columns = [column[0] for column in cursor.description]
+        return [dict(zip(columns, row)) for row in cursor.fetchall()]
+    finally:
+        cursor.close()
+
+
+def _query_sets(connection, sql, parameters):
+    cursor = connection.cursor()
+    try:
+        cursor.execute(sql, parameters)
+        sets = []
+        while True:
+            if cursor.description is not None:
+                columns = [column[0] for column in cursor.description]
+                sets.append([dict(zip(columns, row)) for row in cursor.fetchall()])
+            if not cursor.nextset():
+                return sets
+    finally:
+        cursor.close()
+
+
+def _json_array(value):
+    if value is None:
+        return []
+    return json.loads(value) if isinstance(value, str) else value
+"#;
+
+/// Стиль подстановки параметров DB-API драйвера, под который генерируется Python-клиент
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PythonParamStyle {
+    /// `?` (pyodbc)
+    QMark,
+    /// `%s` (pymssql)
+    Format,
+}
+
+impl PythonParamStyle {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "qmark" => Some(PythonParamStyle::QMark),
+            "format" => Some(PythonParamStyle::Format),
+            _ => None,
+        }
+    }
+    fn name(&self) -> &'static str {
+        match self {
+            PythonParamStyle::QMark => "qmark",
+            PythonParamStyle::Format => "format",
+        }
+    }
+    fn placeholder(&self) -> &'static str {
+        match self {
+            PythonParamStyle::QMark => "?",
+            PythonParamStyle::Format => "%s",
+        }
+    }
+}
+
+impl Default for PythonParamStyle {
+    #[inline]
+    fn default() -> Self {
+        PythonParamStyle::QMark
+    }
+}
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+    "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with",
+    "yield",
+];
+
+fn python_identifier(name: &str) -> String {
+    if PYTHON_KEYWORDS.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn python_type(data_type: &DataType) -> Result<String, fmt::Error> {
+    let result = match data_type {
+        DataType::Array(sub_type) => format!("List[{}]", python_type(sub_type)?),
+        DataType::Compound(CompoundDataType::Structure(_)) => "Dict[str, Any]".to_string(),
+        DataType::Compound(CompoundDataType::Tuple(fields)) => {
+            let mut result = String::from("Tuple[");
+            for (i, field) in fields.iter().enumerate() {
+                if i > 0 {
+                    result.push_str(", ");
+                }
+                result.push_str(&python_type(&field.field_type)?);
+            }
+            result.push(']');
+            result
+        }
+        DataType::Enum(enum_type) => {
+            let mut result = String::new();
+            enum_type.storage_type().fmt_python(&mut result)?;
+            result
+        }
+        DataType::Primitive(primitive) => {
+            let mut result = String::new();
+            primitive.fmt_python(&mut result)?;
+            result
+        }
+        // Ссылки на классы всегда указываются строкой, так как классы могут быть ещё не объявлены
+        DataType::Reference(item) => {
+            let item_guard = item.read();
+            format!("'{}'", item_guard.get_path().as_path().into_new_buf("."))
+        }
+        DataType::Void => "None".to_string(),
+    };
+    Ok(result)
+}
+
+/// Собирает выражения доступа к примитивам значения в порядке `DataType::primitives`
+fn python_accessors(data_type: &DataType, expr: String, target: &mut Vec<String>) {
+    match data_type {
+        DataType::Compound(CompoundDataType::Structure(fields)) => {
+            for (field_name, field) in fields.iter() {
+                python_accessors(&field.field_type, format!("{}['{}']", expr, field_name), target);
+            }
+        }
+        DataType::Compound(CompoundDataType::Tuple(fields)) => {
+            for (i, field) in fields.iter().enumerate() {
+                python_accessors(&field.field_type, format!("{}[{}]", expr, i), target);
+            }
+        }
+        DataType::Reference(item) => {
+            let item_guard = item.read();
+            let body = &item_guard.get_data_type()
+                .expect("Wrong references are not allowed at generate-time")
+                .body;
+            if let DataType::Compound(CompoundDataType::Structure(fields)) = body {
+                for (field_name, field) in fields.iter() {
+                    python_accessors(
+                        &field.field_type,
+                        format!("{}.{}", expr, python_identifier(field_name)),
+                        target,
+                    );
+                }
+            } else {
+                python_accessors(body, expr, target);
+            }
+        }
+        DataType::Array(_) |
+        DataType::Enum(_) |
+        DataType::Primitive(_) |
+        DataType::Void => target.push(expr),
+    }
+}
+
+fn fmt_python_result_bind(
+    f: &mut SimpleFormatter,
+    data_type: &DataType,
+    variable: &str,
+    prefix: Path,
+    is_json: bool,
+) -> fmt::Result {
+    match data_type {
+        // Вложенные массивы приходят JSON-строкой
+        DataType::Array(sub_type) => {
+            writeln!(f, "[")?;
+            {
+                let mut sub_f = f.sub_block();
+                fmt_python_result_bind(&mut sub_f, sub_type, "item", Path::new("", "#"), true)?;
+                writeln!(sub_f, "")?;
+                writeln!(sub_f, "for item in _json_array({}['{}'])", variable, prefix)?;
+            }
+            write!(f, "]")
+        }
+        DataType::Compound(CompoundDataType::Tuple(fields)) => {
+            writeln!(f, "(")?;
+            {
+                let mut sub_f = f.sub_block();
+                for (i, field) in fields.iter().enumerate() {
+                    let mut field_prefix = prefix.into_buf();
+                    field_prefix.push_fmt(format_args!("component{}", i))?;
+                    fmt_python_result_bind(&mut sub_f, &field.field_type, variable, field_prefix.as_path(), is_json)?;
+                    writeln!(sub_f, ",")?;
+                }
+            }
+            write!(f, ")")
+        }
+        DataType::Compound(CompoundDataType::Structure(fields)) => {
+            writeln!(f, "{{")?;
+            {
+                let mut sub_f = f.sub_block();
+                for (name, field) in fields.iter() {
+                    let mut field_prefix = prefix.into_buf();
+                    field_prefix.push(&*name);
+                    write!(sub_f, "'{}': ", name)?;
+                    fmt_python_result_bind(&mut sub_f, &field.field_type, variable, field_prefix.as_path(), is_json)?;
+                    writeln!(sub_f, ",")?;
+                }
+            }
+            write!(f, "}}")
+        }
+        DataType::Primitive(PrimitiveDataType::DateTime(date_time_type)) if is_json => {
+            let class = match date_time_type {
+                DateTimeType::Date => "date",
+                DateTimeType::Time { .. } => "time",
+                _ => "datetime",
+            };
+            write!(f, "{}.fromisoformat({}['{}'])", class, variable, prefix)
+        }
+        DataType::Enum(_) |
+        DataType::Primitive(_) => {
+            write!(f, "{}['{}']", variable, prefix)
+        }
+        DataType::Reference(item) => {
+            let item_guard = item.read();
+            let body = &item_guard.get_data_type()
+                .expect("Wrong references are not allowed at generate-time")
+                .body;
+            let fields = match body {
+                DataType::Compound(CompoundDataType::Structure(fields)) => fields,
+                DataType::Enum(_) => {
+                    let class = item_guard.get_path().as_path().into_new_buf(".");
+                    return write!(f, "{}({}['{}'])", class, variable, prefix);
+                }
+                body => return fmt_python_result_bind(f, body, variable, prefix, is_json),
+            };
+            // Именованные структуры собираются в dataclass-ы
+            writeln!(f, "{}(", item_guard.get_path().as_path().into_new_buf("."))?;
+            {
+                let mut sub_f = f.sub_block();
+                for (name, field) in fields.iter() {
+                    let mut field_prefix = prefix.into_buf();
+                    field_prefix.push(&*name);
+                    write!(sub_f, "{}=", python_identifier(name))?;
+                    fmt_python_result_bind(&mut sub_f, &field.field_type, variable, field_prefix.as_path(), is_json)?;
+                    writeln!(sub_f, ",")?;
+                }
+            }
+            write!(f, ")")
+        }
+        DataType::Void => f.write_str("None"),
+    }
+}
+
+fn fmt_python_function(
+    f: &mut SimpleFormatter,
+    function: &FunctionDefinition,
+    module_path: Path,
+    param_style: PythonParamStyle,
+    is_class_body: bool,
+) -> fmt::Result {
+    if is_class_body {
+        writeln!(f, "@staticmethod")?;
+    }
+    write!(f, "def {}(connection", python_identifier(&function.name))?;
+    let mut values = Vec::new();
+    for (argument_name, argument) in &function.arguments {
+        let argument_guard = argument.read();
+        let argument_data_type = argument_guard.data_type()
+            .expect("Variable's data-type should not be unknown at generate-time");
+        write!(f, ", {}: {}", python_identifier(argument_name), python_type(argument_data_type)?)?;
+        python_accessors(argument_data_type, python_identifier(argument_name), &mut values);
+    }
+    writeln!(f, ") -> {}:", python_type(&function.result)?)?;
+
+    let mut body_f = f.sub_block();
+    let arguments = function.argument_primitives();
+    let placeholder = param_style.placeholder();
+
+    if let Some(record_sets) = function.result.as_record_sets() {
+        let parameters = arguments.iter()
+            .map(|primitive| format!("@{} = {}", primitive.path, placeholder))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            body_f,
+            "sets = _query_sets(connection, 'SET NOCOUNT ON; EXECUTE dbo.[{}::{}] {}', [",
+            module_path,
+            function.name,
+            parameters,
+        )?;
+        for value in values.iter() {
+            writeln!(body_f.sub_block(), "{},", value)?;
+        }
+        writeln!(body_f, "])")?;
+        writeln!(body_f, "return (")?;
+        {
+            let mut tuple_f = body_f.sub_block();
+            for (i, item_type) in record_sets.iter().enumerate() {
+                writeln!(tuple_f, "[")?;
+                {
+                    let mut list_f = tuple_f.sub_block();
+                    fmt_python_result_bind(&mut list_f, item_type, "record", Path::new("", "#"), false)?;
+                    writeln!(list_f, "")?;
+                    writeln!(list_f, "for record in sets[{}]", i)?;
+                }
+                writeln!(tuple_f, "],")?;
+            }
+        }
+        return writeln!(body_f, ")");
+    }
+
+    let query = if function.is_lite_weight {
+        let placeholders = vec![placeholder; arguments.len()].join(", ");
+        if function.result.as_primitive().is_some() {
+            format!("SELECT dbo.[{}::{}]({}) AS result", module_path, function.name, placeholders)
+        } else {
+            format!("SELECT * FROM dbo.[{}::{}]({})", module_path, function.name, placeholders)
+        }
+    } else {
+        // DB-API не поддерживает OUTPUT-параметры, поэтому они возвращаются отдельной выборкой
+        let result_variable_name = function.result_var_name.as_ref()
+            .expect("Procedures should know their return-value name.");
+        let mut prefix = PathBuf::new("#");
+        prefix.push(result_variable_name);
+        let outputs = function.result.primitives(prefix);
+
+        let mut declarations = Vec::with_capacity(outputs.len());
+        let mut parameters = Vec::with_capacity(arguments.len() + outputs.len());
+        let mut selections = Vec::with_capacity(outputs.len());
+        for primitive in arguments.iter() {
+            parameters.push(format!("@{} = {}", primitive.path, placeholder));
+        }
+        for (i, primitive) in outputs.iter().enumerate() {
+            declarations.push(format!(
+                "@_{} {}",
+                i,
+                TSQL(&primitive.field_type, TSQLParameters::new(module_path)),
+            ));
+            parameters.push(format!("@{} = @_{} OUTPUT", primitive.path, i));
+            selections.push(format!("@_{} AS [{}]", i, primitive.path));
+        }
+        let mut query = String::from("SET NOCOUNT ON; ");
+        // Процедура без результата не объявляет переменных и ничего не выбирает
+        if !declarations.is_empty() {
+            write!(query, "DECLARE {}; ", declarations.join(", "))?;
+        }
+        write!(query, "EXECUTE dbo.[{}::{}] {}", module_path, function.name, parameters.join(", "))?;
+        if !selections.is_empty() {
+            write!(query, "; SELECT {}", selections.join(", "))?;
+        }
+        query
+    };
+
+    writeln!(body_f, "records = _query(connection, '{}', [", query)?;
+    for value in values.iter() {
+        writeln!(body_f.sub_block(), "{},", value)?;
+    }
+    writeln!(body_f, "])")?;
+
+    if function.is_lite_weight {
+        if function.result.as_primitive().is_some() {
+            return writeln!(body_f, "return records[0]['result']");
+        }
+        if let Some(item_type) = function.result.as_array() {
+            writeln!(body_f, "return [")?;
+            {
+                let mut list_f = body_f.sub_block();
+                fmt_python_result_bind(&mut list_f, item_type, "record", Path::new("", "#"), false)?;
+                writeln!(list_f, "")?;
+                writeln!(list_f, "for record in records")?;
+            }
+            return writeln!(body_f, "]");
+        }
+        writeln!(body_f, "record = records[0]")?;
+        write!(body_f, "return ")?;
+        fmt_python_result_bind(&mut body_f, &function.result, "record", Path::new("", "#"), false)?;
+        return writeln!(body_f, "");
+    }
+
+    let result_variable_name = function.result_var_name.as_ref()
+        .expect("Procedures should know their return-value name.");
+    // Без выходных параметров выборки нет, а результат собирается без обращения к ней
+    if !function.result.primitives(PathBuf::new("#")).is_empty() {
+        writeln!(body_f, "record = records[0]")?;
+    }
+    write!(body_f, "return ")?;
+    fmt_python_result_bind(
+        &mut body_f,
+        &function.result,
+        "record",
+        Path::new(result_variable_name, "#"),
+        false,
+    )?;
+    writeln!(body_f, "")
+}
+
+impl RPCModule {
+    fn fmt_python(
+        &self,
+        f: &mut SimpleFormatter,
+        path: Path,
+        param_style: PythonParamStyle,
+        is_class_body: bool,
+    ) -> fmt::Result {
+        let mut is_empty = true;
+        for (module_name, module) in self.sub_modules.iter() {
+            writeln!(f, "class {}:", module_name)?;
+            module.fmt_python(
+                &mut f.sub_block(),
+                PathBuf::from_paths(
+                    path,
+                    Path::new(module_name.as_str(), "::"),
+                ).as_path(),
+                param_style,
+                true,
+            )?;
+            writeln!(f, "")?;
+            is_empty = false;
+        }
+        for (name, data_type) in self.data_types.iter() {
+            match data_type {
+                DataType::Compound(CompoundDataType::Structure(fields)) => {
+                    writeln!(f, "@dataclass")?;
+                    writeln!(f, "class {}:", name)?;
+                    let mut class_f = f.sub_block();
+                    if fields.is_empty() {
+                        writeln!(class_f, "pass")?;
+                    }
+                    for (field_name, field) in fields.iter() {
+                        writeln!(
+                            class_f,
+                            "{}: {}",
+                            python_identifier(field_name),
+                            python_type(&field.field_type)?,
+                        )?;
+                    }
+                }
+                DataType::Enum(enum_type) => {
+                    if enum_type.is_text {
+                        writeln!(f, "class {}(str, Enum):", name)?;
+                    } else {
+                        writeln!(f, "class {}(IntEnum):", name)?;
+                    }
+                    let mut class_f = f.sub_block();
+                    for (i, variant) in enum_type.variants.iter().enumerate() {
+                        if enum_type.is_text {
+                            writeln!(class_f, "{} = '{}'", python_identifier(variant), variant)?;
+                        } else {
+                            writeln!(class_f, "{} = {}", python_identifier(variant), i)?;
+                        }
+                    }
+                }
+                data_type => {
+                    writeln!(f, "{} = {}", name, python_type(data_type)?)?;
+                }
+            }
+            writeln!(f, "")?;
+            is_empty = false;
+        }
+        for (_name, function) in self.functions.iter() {
+            fmt_python_function(f, function, path, param_style, is_class_body)?;
+            writeln!(f, "")?;
+            is_empty = false;
+        }
+        if is_empty {
+            writeln!(f, "pass")?;
+        }
+        Ok(())
+    }
+    pub fn generate_python_string(&self) -> Result<String, fmt::Error> {
+        self.generate_python_string_with_param_style(PythonParamStyle::default())
+    }
+    pub fn generate_python_string_with_param_style(
+        &self,
+        param_style: PythonParamStyle,
+    ) -> Result<String, fmt::Error> {
+        let mut result = String::new();
+        {
+            let mut formatter = SimpleFormatter::new(&mut result, 4);
+            formatter.write_str(PYTHON_PRELUDE)?;
+            writeln!(formatter, "")?;
+            writeln!(formatter, "#: Placeholder style the client was generated for")?;
+            writeln!(formatter, "paramstyle = '{}'", param_style.name())?;
+            writeln!(formatter, "")?;
+            writeln!(formatter, "")?;
+            self.fmt_python(&mut formatter, Path::new("", "::"), param_style, false)?;
+        }
+        Ok(result)
+    }
+}
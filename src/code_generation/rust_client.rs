@@ -0,0 +1,678 @@
+use helpers::{
+    class_style,
+    NameUniquer,
+    Path,
+    PathBuf,
+    SimpleFormatter,
+    TSQL,
+    TSQLParameters,
+};
+use indexmap::IndexMap;
+use language::{
+    CompoundDataType,
+    DataType,
+    EnumDataType,
+    Field,
+    FunctionDefinition,
+};
+use std::{
+    fmt::{
+        self,
+        Write,
+    },
+    sync::Arc,
+};
+use super::RPCModule;
+
+/// Общая часть Rust-клиента, не зависящая от проекта
+const RUST_PRELUDE: &str = r##"/// Value of a column or a query parameter
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+/// Row of a result (or a set of query parameters) keyed by `#`-joined primitive paths
+pub type Row = Vec<(String, Value)>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnError {
+    pub path: String,
+    pub message: &'static str,
+}
+
+#[derive(Debug)]
+pub enum ClientError<E> {
+    Executor(E),
+    Column(ColumnError),
+    EmptyResult,
+}
+
+impl<E> From<ColumnError> for ClientError<E> {
+    fn from(error: ColumnError) -> Self {
+        ClientError::Column(error)
+    }
+}
+
+/// Database driver used by generated functions
+#[allow(async_fn_in_trait)]
+pub trait Executor {
+    type Error;
+    /// Runs T-SQL query, parameters are bound by their names (`@name`)
+    async fn query(&mut self, query: &str, inputs: Row) -> Result<Vec<Row>, Self::Error>;
+    /// Calls stored procedure and returns values of its OUTPUT parameters given as (name, T-SQL type)
+    async fn execute(&mut self, procedure: &str, inputs: Row, outputs: &[(&str, &str)]) -> Result<Row, Self::Error>;
+    /// Calls stored procedure and returns all record sets selected by it
+    async fn execute_sets(&mut self, procedure: &str, inputs: Row) -> Result<Vec<Vec<Row>>, Self::Error>;
+}
+
+pub trait FromRow: Sized {
+    fn from_row(row: &Row, path: &str) -> Result<Self, ColumnError>;
+}
+
+pub trait ToRow {
+    fn to_row(&self, path: &str, row: &mut Row) -> Result<(), ColumnError>;
+}
+
+pub fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}#{}", prefix, name)
+    }
+}
+
+pub fn column<'a>(row: &'a Row, path: &str) -> Result<&'a Value, ColumnError> {
+    row.iter()
+        .find(|(name, _)| name == path)
+        .map(|(_, value)| value)
+        .ok_or_else(|| ColumnError { path: path.to_string(), message: "column is missing" })
+}
+
+fn unexpected<T>(path: &str) -> Result<T, ColumnError> {
+    Err(ColumnError { path: path.to_string(), message: "column has unexpected type" })
+}
+
+fn out_of_range<T>(path: &str) -> Result<T, ColumnError> {
+    Err(ColumnError { path: path.to_string(), message: "value is out of range" })
+}
+
+impl FromRow for () {
+    fn from_row(_row: &Row, _path: &str) -> Result<Self, ColumnError> {
+        Ok(())
+    }
+}
+
+impl ToRow for () {
+    fn to_row(&self, path: &str, row: &mut Row) -> Result<(), ColumnError> {
+        row.push((path.to_string(), Value::Null));
+        Ok(())
+    }
+}
+
+impl FromRow for bool {
+    fn from_row(row: &Row, path: &str) -> Result<Self, ColumnError> {
+        match column(row, path)? {
+            Value::Bool(value) => Ok(*value),
+            Value::Int(value) => Ok(*value != 0),
+            _ => unexpected(path),
+        }
+    }
+}
+
+impl ToRow for bool {
+    fn to_row(&self, path: &str, row: &mut Row) -> Result<(), ColumnError> {
+        row.push((path.to_string(), Value::Bool(*self)));
+        Ok(())
+    }
+}
+
+impl FromRow for String {
+    fn from_row(row: &Row, path: &str) -> Result<Self, ColumnError> {
+        match column(row, path)? {
+            Value::Text(value) => Ok(value.clone()),
+            _ => unexpected(path),
+        }
+    }
+}
+
+impl ToRow for String {
+    fn to_row(&self, path: &str, row: &mut Row) -> Result<(), ColumnError> {
+        row.push((path.to_string(), Value::Text(self.clone())));
+        Ok(())
+    }
+}
+
+macro_rules! integer_row {
+    ($($t:ty),*) => {$(
+        impl FromRow for $t {
+            fn from_row(row: &Row, path: &str) -> Result<Self, ColumnError> {
+                match column(row, path)? {
+                    Value::Int(value) => <$t>::try_from(*value).or_else(|_| out_of_range(path)),
+                    Value::Bool(value) => Ok(<$t>::from(*value)),
+                    _ => unexpected(path),
+                }
+            }
+        }
+
+        impl ToRow for $t {
+            fn to_row(&self, path: &str, row: &mut Row) -> Result<(), ColumnError> {
+                let value = i64::try_from(*self).or_else(|_| out_of_range(path))?;
+                row.push((path.to_string(), Value::Int(value)));
+                Ok(())
+            }
+        }
+    )*};
+}
+
+integer_row!(u8, i8, u16, i16, u32, i32, u64, i64);
+
+impl FromRow for f64 {
+    fn from_row(row: &Row, path: &str) -> Result<Self, ColumnError> {
+        match column(row, path)? {
+            Value::Float(value) => Ok(*value),
+            Value::Int(value) => i32::try_from(*value).map(f64::from).or_else(|_| out_of_range(path)),
+            _ => unexpected(path),
+        }
+    }
+}
+
+impl ToRow for f64 {
+    fn to_row(&self, path: &str, row: &mut Row) -> Result<(), ColumnError> {
+        row.push((path.to_string(), Value::Float(*self)));
+        Ok(())
+    }
+}
+
+/// There is no `TryFrom<f64>` for `f32`: values outside of its range are rejected, the rest are rounded
+impl FromRow for f32 {
+    fn from_row(row: &Row, path: &str) -> Result<Self, ColumnError> {
+        let value = f64::from_row(row, path)?;
+        if value.is_finite() && value.abs() > f64::from(f32::MAX) {
+            return out_of_range(path);
+        }
+        Ok(value as f32)
+    }
+}
+
+impl ToRow for f32 {
+    fn to_row(&self, path: &str, row: &mut Row) -> Result<(), ColumnError> {
+        f64::from(*self).to_row(path, row)
+    }
+}
+
+/// Nested arrays are transferred as a JSON array of rows (`FOR JSON PATH`)
+impl<T: FromRow> FromRow for Vec<T> {
+    fn from_row(row: &Row, path: &str) -> Result<Self, ColumnError> {
+        let malformed = || ColumnError { path: path.to_string(), message: "column has malformed JSON" };
+        let text = match column(row, path)? {
+            Value::Null => return Ok(Vec::new()),
+            Value::Text(text) => text,
+            _ => return unexpected(path),
+        };
+        let items: Vec<serde_json::Map<String, serde_json::Value>> = serde_json::from_str(text)
+            .map_err(|_| malformed())?;
+        items.into_iter()
+            .map(|item| {
+                let item_row = item.into_iter()
+                    .map(|(name, value)| Ok((name, from_json(value).ok_or_else(malformed)?)))
+                    .collect::<Result<Row, ColumnError>>()?;
+                T::from_row(&item_row, "")
+            })
+            .collect()
+    }
+}
+
+impl<T: ToRow> ToRow for Vec<T> {
+    fn to_row(&self, path: &str, row: &mut Row) -> Result<(), ColumnError> {
+        let items = self.iter()
+            .map(|item| {
+                let mut item_row = Row::new();
+                item.to_row("", &mut item_row)?;
+                Ok(item_row.into_iter().map(|(name, value)| (name, to_json(value))).collect())
+            })
+            .collect::<Result<Vec<serde_json::Map<String, serde_json::Value>>, ColumnError>>()?;
+        row.push((path.to_string(), Value::Text(serde_json::Value::from(items).to_string())));
+        Ok(())
+    }
+}
+
+/// Columns of a nested array row are primitives
+fn from_json(value: serde_json::Value) -> Option<Value> {
+    Some(match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(value) => Value::Bool(value),
+        serde_json::Value::Number(number) => match number.as_i64() {
+            Some(value) => Value::Int(value),
+            None => Value::Float(number.as_f64()?),
+        },
+        serde_json::Value::String(value) => Value::Text(value),
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => return None,
+    })
+}
+
+fn to_json(value: Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(value) => serde_json::Value::Bool(value),
+        Value::Int(value) => serde_json::Value::from(value),
+        Value::Float(value) => serde_json::Value::from(value),
+        Value::Text(value) => serde_json::Value::String(value),
+    }
+}
+
+macro_rules! tuple_row {
+    ($($i:tt $t:ident),*) => {
+        impl<$($t: FromRow),*> FromRow for ($($t,)*) {
+            fn from_row(row: &Row, path: &str) -> Result<Self, ColumnError> {
+                Ok(($($t::from_row(row, &join(path, concat!("component", $i)))?,)*))
+            }
+        }
+
+        impl<$($t: ToRow),*> ToRow for ($($t,)*) {
+            fn to_row(&self, path: &str, row: &mut Row) -> Result<(), ColumnError> {
+                $(self.$i.to_row(&join(path, concat!("component", $i)), row)?;)*
+                Ok(())
+            }
+        }
+    };
+}
+
+tuple_row!(0 A);
+tuple_row!(0 A, 1 B);
+tuple_row!(0 A, 1 B, 2 C);
+tuple_row!(0 A, 1 B, 2 C, 3 D);
+tuple_row!(0 A, 1 B, 2 C, 3 D, 4 E);
+tuple_row!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+tuple_row!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G);
+tuple_row!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H);
+"##;
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe",
+    "use", "where", "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv",
+    "typeof", "unsized", "virtual", "yield", "try",
+];
+
+fn rust_identifier(name: &str) -> String {
+    if RUST_KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Типы одного Rust-модуля. Анонимные структуры N получают в Rust собственные имена.
+struct RustTypes {
+    supers: String,
+    names: NameUniquer,
+    anonymous: Vec<(String, Arc<IndexMap<String, Field>>)>,
+}
+
+impl RustTypes {
+    fn type_name(&mut self, data_type: &DataType, hint: &str) -> Result<String, fmt::Error> {
+        let result = match data_type {
+            DataType::Array(sub_type) => format!("Vec<{}>", self.type_name(sub_type, hint)?),
+            DataType::Compound(CompoundDataType::Structure(fields)) => {
+                let name = self.names.add_name(class_style(hint));
+                self.anonymous.push((name.clone(), fields.clone()));
+                name
+            }
+            DataType::Compound(CompoundDataType::Tuple(fields)) => {
+                let mut result = String::from("(");
+                for (i, field) in fields.iter().enumerate() {
+                    let component_hint = format!("{}_component{}", hint, i);
+                    write!(result, "{}, ", self.type_name(&field.field_type, &component_hint)?)?;
+                }
+                result.push(')');
+                result
+            }
+            DataType::Enum(enum_type) => {
+                let mut result = String::new();
+                enum_type.storage_type().fmt_rust(&mut result)?;
+                result
+            }
+            DataType::Primitive(primitive) => {
+                let mut result = String::new();
+                primitive.fmt_rust(&mut result)?;
+                result
+            }
+            DataType::Reference(item) => {
+                let item_guard = item.read();
+                format!("{}{}", self.supers, item_guard.get_path())
+            }
+            DataType::Void => "()".to_string(),
+        };
+        Ok(result)
+    }
+    fn fmt_struct(
+        &mut self,
+        f: &mut SimpleFormatter,
+        name: &str,
+        fields: &IndexMap<String, Field>,
+    ) -> fmt::Result {
+        writeln!(f, "#[derive(Debug, Clone, PartialEq)]")?;
+        writeln!(f, "pub struct {} {{", name)?;
+        for (field_name, field) in fields.iter() {
+            let field_type = self.type_name(&field.field_type, &format!("{}_{}", name, field_name))?;
+            writeln!(f.sub_block(), "pub {}: {},", rust_identifier(field_name), field_type)?;
+        }
+        writeln!(f, "}}")?;
+        writeln!(f, "")?;
+
+        writeln!(f, "impl FromRow for {} {{", name)?;
+        {
+            let mut impl_f = f.sub_block();
+            writeln!(impl_f, "fn from_row(row: &Row, path: &str) -> Result<Self, ColumnError> {{")?;
+            {
+                let mut fn_f = impl_f.sub_block();
+                writeln!(fn_f, "Ok({} {{", name)?;
+                for (field_name, _) in fields.iter() {
+                    writeln!(
+                        fn_f.sub_block(),
+                        "{}: FromRow::from_row(row, &join(path, \"{}\"))?,",
+                        rust_identifier(field_name),
+                        field_name,
+                    )?;
+                }
+                writeln!(fn_f, "}})")?;
+            }
+            writeln!(impl_f, "}}")?;
+        }
+        writeln!(f, "}}")?;
+        writeln!(f, "")?;
+
+        writeln!(f, "impl ToRow for {} {{", name)?;
+        {
+            let mut impl_f = f.sub_block();
+            writeln!(impl_f, "fn to_row(&self, path: &str, row: &mut Row) -> Result<(), ColumnError> {{")?;
+            for (field_name, _) in fields.iter() {
+                writeln!(
+                    impl_f.sub_block(),
+                    "self.{}.to_row(&join(path, \"{}\"), row)?;",
+                    rust_identifier(field_name),
+                    field_name,
+                )?;
+            }
+            writeln!(impl_f.sub_block(), "Ok(())")?;
+            writeln!(impl_f, "}}")?;
+        }
+        writeln!(f, "}}")?;
+        writeln!(f, "")
+    }
+    fn fmt_enum(
+        &mut self,
+        f: &mut SimpleFormatter,
+        name: &str,
+        enum_type: &EnumDataType,
+    ) -> fmt::Result {
+        writeln!(f, "#[derive(Debug, Clone, Copy, PartialEq, Eq)]")?;
+        writeln!(f, "pub enum {} {{", name)?;
+        for variant in enum_type.variants.iter() {
+            writeln!(f.sub_block(), "{},", rust_identifier(variant))?;
+        }
+        writeln!(f, "}}")?;
+        writeln!(f, "")?;
+
+        // Значение варианта в колонке: его номер или имя
+        let values: Vec<String> = enum_type.variants.iter()
+            .enumerate()
+            .map(|(i, variant)| if enum_type.is_text {
+                format!("\"{}\"", variant)
+            } else {
+                i.to_string()
+            })
+            .collect();
+
+        writeln!(f, "impl FromRow for {} {{", name)?;
+        {
+            let mut impl_f = f.sub_block();
+            writeln!(impl_f, "fn from_row(row: &Row, path: &str) -> Result<Self, ColumnError> {{")?;
+            {
+                let mut fn_f = impl_f.sub_block();
+                if enum_type.is_text {
+                    writeln!(fn_f, "let value = match column(row, path)? {{")?;
+                    writeln!(fn_f.sub_block(), "Value::Text(value) => value.as_str(),")?;
+                } else {
+                    writeln!(fn_f, "let value = match column(row, path)? {{")?;
+                    writeln!(fn_f.sub_block(), "Value::Int(value) => *value,")?;
+                }
+                writeln!(fn_f.sub_block(), "_ => return unexpected(path),")?;
+                writeln!(fn_f, "}};")?;
+                writeln!(fn_f, "match value {{")?;
+                for (variant, value) in enum_type.variants.iter().zip(values.iter()) {
+                    writeln!(fn_f.sub_block(), "{} => Ok({}::{}),", value, name, rust_identifier(variant))?;
+                }
+                writeln!(fn_f.sub_block(), "_ => unexpected(path),")?;
+                writeln!(fn_f, "}}")?;
+            }
+            writeln!(impl_f, "}}")?;
+        }
+        writeln!(f, "}}")?;
+        writeln!(f, "")?;
+
+        writeln!(f, "impl ToRow for {} {{", name)?;
+        {
+            let mut impl_f = f.sub_block();
+            writeln!(impl_f, "fn to_row(&self, path: &str, row: &mut Row) -> Result<(), ColumnError> {{")?;
+            {
+                let mut fn_f = impl_f.sub_block();
+                writeln!(fn_f, "let value = match self {{")?;
+                for (variant, value) in enum_type.variants.iter().zip(values.iter()) {
+                    let value = if enum_type.is_text {
+                        format!("Value::Text({}.to_string())", value)
+                    } else {
+                        format!("Value::Int({})", value)
+                    };
+                    writeln!(fn_f.sub_block(), "{}::{} => {},", name, rust_identifier(variant), value)?;
+                }
+                writeln!(fn_f, "}};")?;
+                writeln!(fn_f, "row.push((path.to_string(), value));")?;
+                writeln!(fn_f, "Ok(())")?;
+            }
+            writeln!(impl_f, "}}")?;
+        }
+        writeln!(f, "}}")?;
+        writeln!(f, "")
+    }
+    fn fmt_function(
+        &mut self,
+        f: &mut SimpleFormatter,
+        function: &FunctionDefinition,
+        module_path: Path,
+    ) -> fmt::Result {
+        let result_type = self.type_name(&function.result, &format!("{}_result", function.name))?;
+
+        writeln!(f, "pub async fn {}<E: Executor>(", rust_identifier(&function.name))?;
+        {
+            let mut args_f = f.sub_block();
+            writeln!(args_f, "_executor: &mut E,")?;
+            for (argument_name, argument) in &function.arguments {
+                let argument_guard = argument.read();
+                let argument_data_type = argument_guard.data_type()
+                    .expect("Variable's data-type should not be unknown at generate-time");
+                let argument_type = self.type_name(
+                    argument_data_type,
+                    &format!("{}_{}", function.name, argument_name),
+                )?;
+                writeln!(args_f, "{}: {},", rust_identifier(argument_name), argument_type)?;
+            }
+        }
+        writeln!(f, ") -> Result<{}, ClientError<E::Error>> {{", result_type)?;
+
+        {
+            let mut body_f = f.sub_block();
+
+            // Binding arguments into the query
+            writeln!(body_f, "let mut _inputs = Row::new();")?;
+            for (argument_name, _) in &function.arguments {
+                writeln!(
+                    body_f,
+                    "ToRow::to_row(&{}, \"{}\", &mut _inputs)?;",
+                    rust_identifier(argument_name),
+                    argument_name,
+                )?;
+            }
+
+            if let Some(record_sets) = function.result.as_record_sets() {
+                writeln!(
+                    body_f,
+                    "let mut _sets = _executor.execute_sets(\"dbo.[{module}::{name}]\", _inputs).await.map_err(ClientError::Executor)?.into_iter();",
+                    module = module_path,
+                    name = function.name,
+                )?;
+                writeln!(body_f, "Ok((")?;
+                for _ in record_sets.iter() {
+                    let mut set_f = body_f.sub_block();
+                    writeln!(set_f, "_sets.next().ok_or(ClientError::EmptyResult)?.iter()")?;
+                    writeln!(set_f.sub_block(), ".map(|_row| FromRow::from_row(_row, \"\"))")?;
+                    writeln!(set_f.sub_block(), ".collect::<Result<_, ColumnError>>()?,")?;
+                }
+                writeln!(body_f, "))")?;
+            } else if !function.is_lite_weight {
+                let result_variable_name = function.result_var_name.as_ref()
+                    .expect("Procedures should know their return-value name.");
+                let mut prefix = PathBuf::new("#");
+                prefix.push(result_variable_name);
+
+                writeln!(
+                    body_f,
+                    "let _output = _executor.execute(\"dbo.[{module}::{name}]\", _inputs, &[",
+                    module = module_path,
+                    name = function.name,
+                )?;
+                for primitive in function.result.primitives(prefix) {
+                    writeln!(
+                        body_f.sub_block(),
+                        "(\"{}\", \"{}\"),",
+                        primitive.path,
+                        TSQL(&primitive.field_type, TSQLParameters::new(module_path)),
+                    )?;
+                }
+                writeln!(body_f, "]).await.map_err(ClientError::Executor)?;")?;
+                writeln!(body_f, "Ok(FromRow::from_row(&_output, \"{}\")?)", result_variable_name)?;
+            } else {
+                let arguments = function.argument_primitives()
+                    .into_iter()
+                    .map(|primitive| format!("@{}", primitive.path))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                if function.result.as_primitive().is_some() {
+                    writeln!(
+                        body_f,
+                        "let _rows = _executor.query(\"SELECT dbo.[{module}::{name}]({args}) as result\", _inputs).await.map_err(ClientError::Executor)?;",
+                        module = module_path,
+                        name = function.name,
+                        args = arguments,
+                    )?;
+                    writeln!(body_f, "let _row = _rows.first().ok_or(ClientError::EmptyResult)?;")?;
+                    writeln!(body_f, "Ok(FromRow::from_row(_row, \"result\")?)")?;
+                } else {
+                    writeln!(
+                        body_f,
+                        "let _rows = _executor.query(\"SELECT * FROM dbo.[{module}::{name}]({args})\", _inputs).await.map_err(ClientError::Executor)?;",
+                        module = module_path,
+                        name = function.name,
+                        args = arguments,
+                    )?;
+                    if function.result.as_array().is_some() {
+                        writeln!(body_f, "let _result: Result<_, ColumnError> = _rows.iter()")?;
+                        writeln!(body_f.sub_block(), ".map(|_row| FromRow::from_row(_row, \"\"))")?;
+                        writeln!(body_f.sub_block(), ".collect();")?;
+                        writeln!(body_f, "Ok(_result?)")?;
+                    } else {
+                        writeln!(body_f, "let _row = _rows.first().ok_or(ClientError::EmptyResult)?;")?;
+                        writeln!(body_f, "Ok(FromRow::from_row(_row, \"\")?)")?;
+                    }
+                }
+            }
+        }
+
+        writeln!(f, "}}")?;
+        writeln!(f, "")
+    }
+}
+
+impl RPCModule {
+    fn fmt_rust(&self, f: &mut SimpleFormatter, path: Path, depth: usize) -> fmt::Result {
+        let mut types = RustTypes {
+            supers: "super::".repeat(depth),
+            names: NameUniquer::new(),
+            anonymous: Vec::new(),
+        };
+        for (module_name, _) in self.sub_modules.iter() {
+            types.names.add_name(module_name.clone());
+        }
+        for (name, _) in self.data_types.iter() {
+            types.names.add_name(name.clone());
+        }
+
+        if depth > 0 {
+            writeln!(f, "#[allow(unused_imports)]")?;
+            writeln!(
+                f,
+                "use {}{{column, join, unexpected, ClientError, ColumnError, Executor, FromRow, Row, ToRow, Value}};",
+                types.supers,
+            )?;
+            writeln!(f, "")?;
+        }
+
+        for (module_name, module) in self.sub_modules.iter() {
+            writeln!(f, "pub mod {} {{", module_name)?;
+            module.fmt_rust(
+                &mut f.sub_block(),
+                PathBuf::from_paths(
+                    path,
+                    Path::new(module_name.as_str(), "::"),
+                ).as_path(),
+                depth + 1,
+            )?;
+            writeln!(f, "}}")?;
+            writeln!(f, "")?;
+        }
+        for (name, data_type) in self.data_types.iter() {
+            match data_type {
+                DataType::Compound(CompoundDataType::Structure(fields)) => {
+                    types.fmt_struct(f, name, fields)?;
+                }
+                DataType::Enum(enum_type) => {
+                    types.fmt_enum(f, name, enum_type)?;
+                }
+                data_type => {
+                    let type_name = types.type_name(data_type, name)?;
+                    writeln!(f, "pub type {} = {};", name, type_name)?;
+                    writeln!(f, "")?;
+                }
+            }
+        }
+        for (_name, function) in self.functions.iter() {
+            types.fmt_function(f, function, path)?;
+        }
+        // Анонимные структуры могут порождать новые анонимные структуры
+        while !types.anonymous.is_empty() {
+            let (name, fields) = types.anonymous.remove(0);
+            types.fmt_struct(f, &name, &fields)?;
+        }
+        Ok(())
+    }
+    pub fn generate_rust_string(&self) -> Result<String, fmt::Error> {
+        let mut result = String::new();
+        {
+            let mut formatter = SimpleFormatter::new(&mut result, 4);
+            // Вложенные массивы передаются JSON-ом, поэтому клиенту нужен крейт serde_json
+            writeln!(formatter, "// Requires the serde_json crate")?;
+            writeln!(formatter, "#![allow(dead_code, non_camel_case_types, non_snake_case, unused_variables)]")?;
+            writeln!(formatter, "")?;
+            formatter.write_str(RUST_PRELUDE)?;
+            writeln!(formatter, "")?;
+            self.fmt_rust(&mut formatter, Path::new("", "::"), 0)?;
+        }
+        Ok(result)
+    }
+}
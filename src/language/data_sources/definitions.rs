@@ -6,9 +6,12 @@ use helpers::{
 };
 use language::{
     AssignmentTarget,
+    BinaryOperator,
+    CommonTable,
     DataType,
     Expression,
     ExpressionAST,
+    ExpressionBody,
     ItemPath,
     Selection,
     SelectionAST,
@@ -31,8 +34,114 @@ use std::fmt::{
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum JoinType {
     Cross,
+    Inner,
     Left,
     Right,
+    Full,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinConditionAST<'source> {
+    Expression(ExpressionAST<'source>),
+    Using(Vec<ItemPath>),
+}
+
+impl<'source> JoinConditionAST<'source> {
+    fn resolve(
+        &self,
+        scope: &SyncRef<FunctionVariableScope>,
+        left: &DataSource,
+        right: &DataSource,
+    ) -> Result<JoinCondition, Vec<SemanticError>> {
+        match self {
+            JoinConditionAST::Expression(expr) => Ok(JoinCondition::Expression(expr.resolve(scope)?)),
+            JoinConditionAST::Using(columns) => {
+                let mut result = Vec::with_capacity(columns.len());
+                let mut errors = Vec::new();
+                for column in columns.iter() {
+                    match JoinCondition::using_column(scope, column, left, right) {
+                        Ok(expr) => result.push(expr),
+                        Err(error) => errors.push(error),
+                    }
+                }
+                if !errors.is_empty() {
+                    return Err(errors);
+                }
+                Ok(JoinCondition::Using(result))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinCondition {
+    Expression(Expression),
+    /// Равенства одноимённых полей левого и правого источников
+    Using(Vec<Expression>),
+}
+
+impl JoinCondition {
+    /// Находит единственный источник, содержащий данное поле
+    fn find_column(source: &DataSource, column: &ItemPath) -> Result<Expression, SemanticError> {
+        let mut vars = Vec::new();
+        source.entity_variables(&mut vars);
+        let mut result = None;
+        for var in vars {
+            let data_type = match var.property_type(column.pos, column.path.as_path()) {
+                Ok(data_type) => data_type,
+                Err(_) => continue,
+            };
+            if result.is_some() {
+                return Err(SemanticError::ambiguous_column(column.pos, column.path.to_string()));
+            }
+            let var_type = var.data_type(column.pos)?;
+            result = Some(Expression {
+                body: ExpressionBody::PropertyAccess(
+                    Box::new(Expression::variable_access(var, column.pos, var_type)),
+                    column.clone(),
+                ),
+                pos: column.pos,
+                data_type,
+            });
+        }
+        result.ok_or_else(|| SemanticError::wrong_property(column.pos, column.path.to_string()))
+    }
+    fn using_column(
+        scope: &SyncRef<FunctionVariableScope>,
+        column: &ItemPath,
+        left: &DataSource,
+        right: &DataSource,
+    ) -> Result<Expression, SemanticError> {
+        let left = JoinCondition::find_column(left, column)?;
+        let right = JoinCondition::find_column(right, column)?;
+        let data_type = scope.project()
+            .resolve_binary_operation(column.pos, BinaryOperator::Equals, &left.data_type, &right.data_type)?
+            .output
+            .clone();
+        Ok(Expression {
+            body: ExpressionBody::BinaryOperation(Box::new(left), BinaryOperator::Equals, Box::new(right)),
+            pos: column.pos,
+            data_type,
+        })
+    }
+    pub fn fmt(
+        &self,
+        f: &mut impl fmt::Write,
+        context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        match self {
+            JoinCondition::Expression(expr) => expr.fmt(f, context),
+            JoinCondition::Using(equalities) => {
+                for (i, equality) in equalities.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" AND ")?;
+                    }
+                    equality.fmt(f, context)?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -43,7 +152,7 @@ pub enum DataSourceAST<'source> {
     },
     Join {
         join_type: JoinType,
-        condition: Option<ExpressionAST<'source>>,
+        condition: Option<JoinConditionAST<'source>>,
         left: Box<DataSourceAST<'source>>,
         right: Box<DataSourceAST<'source>>,
     },
@@ -76,7 +185,17 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for DataSourceAST<'source>
                             new_var.make_read_only();
                         }
                         new_var.mark_as_automatic();
-                        return Ok(DataSource::Variable { var });
+                        return Ok(DataSource::Variable { var, entity: new_var });
+                    }
+                    if let Some(table) = scope.get_common_table(name) {
+                        let new_var_name = match alias {
+                            Some(alias) => alias.text(),
+                            None => name,
+                        };
+                        let entity_type = table.read().entity.clone();
+                        let var = scope.new_variable(pos, new_var_name.to_string(), Some(entity_type))?;
+                        var.mark_as_automatic();
+                        return Ok(DataSource::CommonTable { table, var });
                     }
                 }
                 match scope.module().get_item(name.path.as_path(), &mut Vec::new()) {
@@ -114,7 +233,19 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for DataSourceAST<'source>
             DataSourceAST::Join { join_type, condition, left, right } => {
                 let left = left.resolve(scope)?;
                 let right = right.resolve(scope)?;
-                let condition = condition.resolve(scope)?;
+                match join_type {
+                    JoinType::Left => right.mark_as_nullable(),
+                    JoinType::Right => left.mark_as_nullable(),
+                    JoinType::Full => {
+                        left.mark_as_nullable();
+                        right.mark_as_nullable();
+                    }
+                    JoinType::Cross | JoinType::Inner => {}
+                }
+                let condition = match condition {
+                    Some(condition) => Some(condition.resolve(scope, &left, &right)?),
+                    None => None,
+                };
                 Ok(DataSource::Join { join_type: *join_type, condition, left, right })
             }
             DataSourceAST::Selection { query, alias } => {
@@ -136,14 +267,19 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for DataSourceAST<'source>
 pub enum DataSource {
     Variable {
         var: SyncRef<FunctionVariable>,
+        entity: SyncRef<FunctionVariable>,
     },
     Table {
         item: SyncRef<Item>,
         var: SyncRef<FunctionVariable>,
     },
+    CommonTable {
+        table: SyncRef<CommonTable>,
+        var: SyncRef<FunctionVariable>,
+    },
     Join {
         join_type: JoinType,
-        condition: Option<Expression>,
+        condition: Option<JoinCondition>,
         left: Box<DataSource>,
         right: Box<DataSource>,
     },
@@ -157,24 +293,28 @@ pub enum DataSource {
 impl DataSource {
     pub fn is_allows_updates(&self) -> bool {
         match self {
-            DataSource::Variable { var } => !var.is_read_only(),
+            DataSource::Variable { var, entity: _ } => !var.is_read_only(),
             DataSource::Table { item: _, var: _ } => true,
+            DataSource::CommonTable { table: _, var: _ } => false,
             DataSource::Join { join_type: _, condition: _, left, right } => left.is_allows_updates() && right.is_allows_updates(),
             DataSource::Selection { query: _, alias: _, var: _ } => false,
         }
     }
     pub fn is_allows_inserts(&self) -> bool {
         match self {
-            DataSource::Variable { var } => !var.is_read_only(),
+            DataSource::Variable { var, entity: _ } => !var.is_read_only(),
             DataSource::Table { item: _, var: _ } => true,
+            DataSource::CommonTable { table: _, var: _ } => false,
             DataSource::Join { join_type: _, condition: _, left: _, right: _ } => false,
             DataSource::Selection { query: _, alias: _, var: _ } => false,
         }
     }
     pub fn get_target_for_insert(&self, pos: ItemPosition) -> Result<SyncRef<FunctionVariable>, SemanticError> {
         match self {
-            DataSource::Variable { var } => Ok(var.clone()),
+            DataSource::Variable { var, entity: _ } => Ok(var.clone()),
             DataSource::Table { item: _, var } => Ok(var.clone()),
+            DataSource::CommonTable { table: _, var: _ } =>
+                return Err(SemanticError::not_allowed_inside(pos, "insertion", "common table expression")),
             DataSource::Join { join_type: _, condition: _, left: _, right: _ } =>
                 return Err(SemanticError::not_allowed_inside(pos, "insertion", "JOIN of data sources")),
             DataSource::Selection { query: _, alias: _, var: _ } =>
@@ -183,26 +323,51 @@ impl DataSource {
     }
     pub fn is_allows_deletes(&self) -> bool {
         match self {
-            DataSource::Variable { var } => !var.is_read_only(),
+            DataSource::Variable { var, entity: _ } => !var.is_read_only(),
             DataSource::Table { item: _, var: _ } => true,
+            DataSource::CommonTable { table: _, var: _ } => false,
             DataSource::Join { join_type: _, condition: _, left: _, right: _ } => false,
             DataSource::Selection { query: _, alias: _, var: _ } => false,
         }
     }
     pub fn is_target_belongs_to_source(&self, target: &AssignmentTarget) -> bool {
         let inner_var = match self {
-            DataSource::Variable { var } => var,
+            DataSource::Variable { var, entity: _ } => var,
             DataSource::Table { item: _, var } => var,
+            DataSource::CommonTable { table: _, var } => var,
             DataSource::Join { join_type: _, condition: _, left, right } =>
                 return left.is_target_belongs_to_source(target) || right.is_target_belongs_to_source(target),
             DataSource::Selection { query: _, alias: _, var } => var,
         };
         inner_var.is_same_ref(&target.var)
     }
+    /// Собирает переменные, через которые доступны строки источника
+    pub fn entity_variables(&self, target: &mut Vec<SyncRef<FunctionVariable>>) {
+        match self {
+            DataSource::Variable { var: _, entity } => target.push(entity.clone()),
+            DataSource::Table { item: _, var } |
+            DataSource::CommonTable { table: _, var } => target.push(var.clone()),
+            DataSource::Join { join_type: _, condition: _, left, right } => {
+                left.entity_variables(target);
+                right.entity_variables(target);
+            }
+            DataSource::Selection { query: _, alias: _, var } => target.push(var.clone()),
+        }
+    }
+    /// Отмечает источник как стоящий на внешней стороне соединения
+    pub fn mark_as_nullable(&self) {
+        let mut vars = Vec::new();
+        self.entity_variables(&mut vars);
+        for var in vars {
+            var.mark_as_nullable();
+        }
+    }
     pub fn is_local(&self) -> bool {
         match self {
-            DataSource::Variable { var: _ } => true,
+            DataSource::Variable { var: _, entity: _ } => true,
             DataSource::Table { item: _, var: _ } => false,
+            // Выражение вычисляется в табличную переменную
+            DataSource::CommonTable { table: _, var: _ } => true,
             DataSource::Join { join_type: _, condition: _, left, right } =>
                 left.is_local() && right.is_local(),
             DataSource::Selection { query, alias: _, var: _ } => query.source.is_local(),
@@ -215,7 +380,7 @@ impl DataSource {
         aliases: bool,
     ) -> fmt::Result {
         match self {
-            DataSource::Variable { var } => {
+            DataSource::Variable { var, entity: _ } => {
                 let var_guard = var.read();
                 if aliases {
                     f.write_line(format_args!("@{name} AS [{name}]", name = var_guard.name()))
@@ -234,21 +399,48 @@ impl DataSource {
                     f.write_line(format_args!("[{}]", item_guard.get_path()))
                 }
             }
+            DataSource::CommonTable { table, var } => {
+                let table_guard = table.read();
+                if let Some(query) = context.get_inline_common_table(table) {
+                    f.write_line("(")?;
+                    {
+                        let mut sub_f = f.sub_block();
+                        for line in query.lines() {
+                            sub_f.write_line(line)?;
+                        }
+                    }
+                    let mut line = f.line()?;
+                    if aliases {
+                        write!(line, ") AS [{}] (", var.read().name())?;
+                    } else {
+                        write!(line, ") AS [{}] (", table_guard.name)?;
+                    }
+                    table_guard.fmt_columns(&mut line)?;
+                    return line.write_char(')');
+                }
+                if aliases {
+                    let var_guard = var.read();
+                    f.write_line(format_args!("[{}] AS [{}]", table_guard.name, var_guard.name()))
+                } else {
+                    f.write_line(format_args!("[{}]", table_guard.name))
+                }
+            }
             DataSource::Join { join_type, condition, left, right } => {
                 left.fmt(f.clone(), context, aliases)?;
-                {
+                f.write_line(match join_type {
+                    JoinType::Cross => "CROSS JOIN",
+                    JoinType::Inner => "INNER JOIN",
+                    JoinType::Left => "LEFT JOIN",
+                    JoinType::Right => "RIGHT JOIN",
+                    JoinType::Full => "FULL JOIN",
+                })?;
+                right.fmt(f.clone(), context, aliases)?;
+                if let Some(condition) = condition {
                     let mut line = f.line()?;
-                    line.write_str(match join_type {
-                        JoinType::Cross => "CROSS JOIN",
-                        JoinType::Left => "LEFT JOIN",
-                        JoinType::Right => "RIGHT JOIN",
-                    })?;
-                    if let Some(condition) = condition {
-                        line.write_str(" ON ")?;
-                        condition.fmt(&mut line, context)?;
-                    }
+                    line.write_str("ON ")?;
+                    condition.fmt(&mut line, context)?;
                 }
-                right.fmt(f, context, aliases)
+                Ok(())
             }
             DataSource::Selection { query, alias, var: _ } => {
                 query.fmt(f.clone(), context)?;
@@ -16,19 +16,20 @@
         | "using" "(" comma_list(property_path) ")"
 
     join_tail =
-        | "natural" "left" ["outer"] "join" join_source  
-        | "left" ["outer"] "join" join_source [join_condition]  
-        | "natural" "right" ["outer"] "join" join_source  
-        | "right" ["outer"] "join" join_source [join_condition]  
-        | "natural" "full" ["outer"] "join" join_source  
-        | "full" ["outer"] "join" join_source [join_condition]
-        | "inner" "join" join_source [join_condition]
+        | "left" ["outer"] "join" join_source join_condition
+        | "right" ["outer"] "join" join_source join_condition
+        | "full" ["outer"] "join" join_source join_condition
+        | "inner" "join" join_source join_condition
         | ["cross"] "join" join_source
         | "," join_source
-    
+
     data_source = join_source join_tail*
     ```
 
+    Поля, перечисленные в `using`, ищутся в источниках по обе стороны соединения.
+    Источники на внешней стороне соединений `left`, `right` и `full` могут не иметь строки,
+    поэтому их поля в результатах выборки допускают `null`.
+
     Правила `property_path` и `module_path` определены в модуле `language::others`.
 
     Правила `comma_list`, `keyword`, `not_keyword_identifier` и `symbols` определены в модуле `parser_basics`.
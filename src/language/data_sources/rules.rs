@@ -1,7 +1,7 @@
 use language::{
     expression,
-    ExpressionAST,
     module_path,
+    property_path,
     selection,
 };
 use lexeme_scanner::Token;
@@ -9,15 +9,23 @@ use parser_basics::{
     keyword,
     not_keyword_identifier,
     ParserResult,
+    rounded_comma_list,
     symbols,
 };
 use super::*;
 
-parser_rule!(join_condition(i) -> ExpressionAST<'source> {
-    do_parse!(i,
-        apply!(keyword, "on") >>
-        x: expression >>
-        (x)
+parser_rule!(join_condition(i) -> JoinConditionAST<'source> {
+    alt!(i,
+        do_parse!(
+            apply!(keyword, "on") >>
+            x: expression >>
+            (JoinConditionAST::Expression(x))
+        )
+        | do_parse!(
+            apply!(keyword, "using") >>
+            columns: apply!(rounded_comma_list, property_path) >>
+            (JoinConditionAST::Using(columns))
+        )
     )
 });
 
@@ -49,7 +57,7 @@ parser_rule!(join_source(i) -> DataSourceAST<'source> {
     )
 });
 
-type JoinTail<'source> = (JoinType, Option<ExpressionAST<'source>>, DataSourceAST<'source>);
+type JoinTail<'source> = (JoinType, Option<JoinConditionAST<'source>>, DataSourceAST<'source>);
 parser_rule!(join_tail(i) -> JoinTail<'source> {
     alt!(i,
         do_parse!(
@@ -57,23 +65,31 @@ parser_rule!(join_tail(i) -> JoinTail<'source> {
             opt!(apply!(keyword, "outer")) >>
             apply!(keyword, "join") >>
             source: join_source >>
-            condition: opt!(join_condition) >>
-            ((JoinType::Left, condition, source))
+            condition: join_condition >>
+            ((JoinType::Left, Some(condition), source))
         )
         | do_parse!(
             apply!(keyword, "right") >>
             opt!(apply!(keyword, "outer")) >>
             apply!(keyword, "join") >>
             source: join_source >>
-            condition: opt!(join_condition) >>
-            ((JoinType::Right, condition, source))
+            condition: join_condition >>
+            ((JoinType::Right, Some(condition), source))
+        )
+        | do_parse!(
+            apply!(keyword, "full") >>
+            opt!(apply!(keyword, "outer")) >>
+            apply!(keyword, "join") >>
+            source: join_source >>
+            condition: join_condition >>
+            ((JoinType::Full, Some(condition), source))
         )
         | do_parse!(
             apply!(keyword, "inner") >>
             apply!(keyword, "join") >>
             source: join_source >>
-            condition: opt!(join_condition) >>
-            ((JoinType::Cross, condition, source))
+            condition: join_condition >>
+            ((JoinType::Inner, Some(condition), source))
         )
         | do_parse!(
             opt!(apply!(keyword, "cross")) >>
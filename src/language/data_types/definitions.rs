@@ -331,6 +331,8 @@ pub enum StringType {
     Text {
         character_set: Option<CharacterSet>,
     },
+    /// Колонка вложенного массива с JSON-массивом его записей. В языке не записывается.
+    Json,
 }
 
 impl StringType {
@@ -345,14 +347,17 @@ impl StringType {
                         self_size <= size
                     }
                     StringType::Text { character_set: _ } => true,
+                    StringType::Json => false,
                 }
             }
             StringType::Text { character_set: _ } => {
                 match target {
                     StringType::Varchar { size: _, character_set: _ } => false,
                     StringType::Text { character_set: _ } => true,
+                    StringType::Json => false,
                 }
             }
+            StringType::Json => target == &StringType::Json,
         }
     }
 }
@@ -377,6 +382,7 @@ impl fmt::Display for StringType {
                 }
                 Ok(())
             }
+            StringType::Json => write!(f, "json"),
         }
     }
 }
@@ -393,6 +399,8 @@ impl<'a> Format<TSQLParameters<'a>> for StringType {
                 }
             }
             StringType::Text { .. } => f.write_str("ntext"),
+            // ntext нельзя использовать в локальных переменных, куда записывается FOR JSON
+            StringType::Json => f.write_str("nvarchar(max)"),
         }
     }
 }
@@ -429,6 +437,28 @@ impl PrimitiveDataType {
         }
         false
     }
+    /**
+        Явное приведение, в отличие от неявного, может сужать числа и преобразовывать значения между числами, датами и строками.
+        Логические значения в T-SQL являются предикатами, а не значениями, поэтому явно не приводятся.
+    */
+    pub fn can_cast_explicitly(&self, target: &PrimitiveDataType) -> bool {
+        let boolean = PrimitiveDataType::Number(NumberType::Boolean);
+        if *self == boolean || *target == boolean {
+            return *self == *target;
+        }
+        match (self, target) {
+            (PrimitiveDataType::Null, _) => true,
+            (_, PrimitiveDataType::Null) => false,
+            (PrimitiveDataType::String(_), _) => true,
+            (_, PrimitiveDataType::String(_)) => true,
+            (PrimitiveDataType::Number(_), PrimitiveDataType::Number(_)) |
+            (PrimitiveDataType::Number(_), PrimitiveDataType::Year(_)) |
+            (PrimitiveDataType::Year(_), PrimitiveDataType::Number(_)) |
+            (PrimitiveDataType::Year(_), PrimitiveDataType::Year(_)) |
+            (PrimitiveDataType::DateTime(_), PrimitiveDataType::DateTime(_)) => true,
+            _ => false,
+        }
+    }
     #[inline]
     pub fn check(&self) -> Result<(), SemanticErrorKind> {
         match self {
@@ -485,8 +515,59 @@ impl PrimitiveDataType {
                 }
             }
             PrimitiveDataType::String(StringType::Text { .. }) => f.write_str("NText"),
+            PrimitiveDataType::String(StringType::Json) => f.write_str("NVarChar(_mssql.MAX)"),
         }
     }
+    pub fn fmt_rust(&self, f: &mut impl Write) -> fmt::Result {
+        f.write_str(match self {
+            PrimitiveDataType::Null => "()",
+            PrimitiveDataType::Number(NumberType::Bit { size }) => match size {
+                Some(0) => "()",
+                Some(1) | None => "bool",
+                _ => "u64",
+            },
+            PrimitiveDataType::Number(NumberType::Boolean) => "bool",
+            PrimitiveDataType::Number(NumberType::Integer { size, unsigned, .. }) => {
+                match (*size, *unsigned) {
+                    (0..=8, true) => "u8",
+                    (0..=8, false) => "i8",
+                    (9..=16, true) => "u16",
+                    (9..=16, false) => "i16",
+                    (17..=32, true) => "u32",
+                    (17..=32, false) => "i32",
+                    (_, true) => "u64",
+                    (_, false) => "i64",
+                }
+            }
+            PrimitiveDataType::Number(NumberType::Decimal { .. }) => "f64",
+            PrimitiveDataType::Number(NumberType::Float { size, double }) => match size {
+                Some((precision, _)) => if *precision <= 24 { "f32" } else { "f64" },
+                None => if *double { "f64" } else { "f32" },
+            },
+            PrimitiveDataType::DateTime(_) => "String",
+            PrimitiveDataType::Year(_) => "i16",
+            PrimitiveDataType::String(_) => "String",
+        })
+    }
+    pub fn fmt_python(&self, f: &mut impl Write) -> fmt::Result {
+        f.write_str(match self {
+            PrimitiveDataType::Null => "None",
+            PrimitiveDataType::Number(NumberType::Bit { size }) => match size {
+                Some(0) => "None",
+                Some(1) | None => "bool",
+                _ => "int",
+            },
+            PrimitiveDataType::Number(NumberType::Boolean) => "bool",
+            PrimitiveDataType::Number(NumberType::Integer { .. }) => "int",
+            PrimitiveDataType::Number(NumberType::Decimal { .. }) => "Decimal",
+            PrimitiveDataType::Number(NumberType::Float { .. }) => "float",
+            PrimitiveDataType::DateTime(DateTimeType::Date) => "date",
+            PrimitiveDataType::DateTime(DateTimeType::Time { .. }) => "time",
+            PrimitiveDataType::DateTime(_) => "datetime",
+            PrimitiveDataType::Year(_) => "int",
+            PrimitiveDataType::String(_) => "str",
+        })
+    }
 }
 
 // TODO Удалить impl fmt::Display у всех сущностей, которым это не нужно. В частности, у типов данных.
@@ -581,6 +662,19 @@ impl<'source> Assertion for FieldAST<'source> {
     }
 }
 
+impl Field {
+    /// Значения поля может не быть, например, на внешней стороне соединения
+    #[inline]
+    pub fn is_nullable(&self) -> bool {
+        find_attribute(&self.attributes, "nullable").is_some()
+    }
+    #[inline]
+    pub fn can_keep_nulls_in(&self, target: &Field) -> bool {
+        (!self.is_nullable() || target.is_nullable())
+            && self.field_type.can_keep_nulls_in(&target.field_type)
+    }
+}
+
 impl<'source> Resolve<SyncRef<Module>> for FieldAST<'source> {
     type Result = Field;
     type Error = SemanticError;
@@ -689,6 +783,21 @@ impl<'source> Assertion for CompoundDataTypeAST<'source> {
     }
 }
 
+impl<'source> FieldAST<'source> {
+    /// Элементы вложенного массива передаются JSON-объектами (`FOR JSON PATH`), где каждая колонка
+    /// записывается под своим путём. У примитивного элемента путь пустой, а безымянные колонки
+    /// `FOR JSON` не принимает, поэтому массив примитивов должен стать массивом структур.
+    fn check_nested_array(&self, name: String, field: &Field) -> Result<(), Vec<SemanticError>> {
+        match field.field_type.as_array() {
+            Some(sub_type) if sub_type.as_primitive().is_some() => {
+                SemanticError::array_of_primitives_field(self.position, name)
+                    .into_err_vec()
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 impl<'source> Resolve<SyncRef<Module>> for CompoundDataTypeAST<'source> {
     type Result = CompoundDataType;
     type Error = SemanticError;
@@ -696,7 +805,13 @@ impl<'source> Resolve<SyncRef<Module>> for CompoundDataTypeAST<'source> {
         match self {
             CompoundDataTypeAST::Structure(fields) => Ok(CompoundDataType::Structure(
                 match as_unique_identifier(fields.clone()) {
-                    Ok(map) => Arc::new(map.resolve(ctx)?),
+                    Ok(map) => {
+                        let resolved_fields = map.resolve(ctx)?;
+                        for (name, field) in map.iter() {
+                            field.check_nested_array(name.clone(), &resolved_fields[name])?;
+                        }
+                        Arc::new(resolved_fields)
+                    }
                     Err(name) => return SemanticError::duplicate_definition(
                         name.item_pos(),
                         name.text().to_string(),
@@ -705,7 +820,13 @@ impl<'source> Resolve<SyncRef<Module>> for CompoundDataTypeAST<'source> {
                         .into_err_vec()
                 }
             )),
-            CompoundDataTypeAST::Tuple(fields) => Ok(CompoundDataType::Tuple(Arc::new(fields.resolve(ctx)?))),
+            CompoundDataTypeAST::Tuple(fields) => {
+                let resolved_fields = fields.resolve(ctx)?;
+                for (i, (field, resolved_field)) in fields.iter().zip(resolved_fields.iter()).enumerate() {
+                    field.check_nested_array(format!("component{}", i), resolved_field)?;
+                }
+                Ok(CompoundDataType::Tuple(Arc::new(resolved_fields)))
+            }
         }
     }
 }
@@ -819,10 +940,68 @@ impl<'source> Resolve<SyncRef<Module>> for DataTypeAST<'source> {
     }
 }
 
+/// Перечисление. Сравнивается по полному пути объявления, а не по составу вариантов.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDataType {
+    pub path: PathBuf,
+    pub variants: Arc<Vec<String>>,
+    /// Хранится строкой с именем варианта вместо его номера
+    pub is_text: bool,
+}
+
+impl EnumDataType {
+    pub fn storage_type(&self) -> PrimitiveDataType {
+        if self.is_text {
+            let size = self.variants.iter()
+                .map(|variant| variant.len() as u32)
+                .max()
+                .unwrap_or(1);
+            PrimitiveDataType::String(StringType::Varchar {
+                size: Some(size),
+                character_set: None,
+            })
+        } else {
+            PrimitiveDataType::Number(NumberType::Integer {
+                size: if self.variants.len() <= 256 { 8 } else { 16 },
+                unsigned: true,
+                zerofill: false,
+            })
+        }
+    }
+    #[inline]
+    pub fn variant_index(&self, name: &str) -> Option<usize> {
+        self.variants.iter()
+            .position(|variant| variant == name)
+    }
+    /// Значение варианта в T-SQL
+    pub fn fmt_value(&self, f: &mut impl Write, index: usize) -> fmt::Result {
+        if self.is_text {
+            write!(f, "N'{}'", self.variants[index])
+        } else {
+            write!(f, "{}", index)
+        }
+    }
+    /// Ограничение колонки, не допускающее значений вне перечисления
+    pub fn fmt_check(&self, f: &mut impl Write, column: &str) -> fmt::Result {
+        if !self.is_text {
+            return write!(f, "CHECK ([{}] < {})", column, self.variants.len());
+        }
+        write!(f, "CHECK ([{}] IN (", column)?;
+        for index in 0..self.variants.len() {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+            self.fmt_value(f, index)?;
+        }
+        f.write_str("))")
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum DataType {
     Array(Arc<DataType>),
     Compound(CompoundDataType),
+    Enum(EnumDataType),
     Primitive(PrimitiveDataType),
     Reference(SyncRef<Item>),
     Void,
@@ -830,6 +1009,8 @@ pub enum DataType {
 
 pub const BOOLEAN_TYPE: DataType = DataType::Primitive(PrimitiveDataType::Number(NumberType::Boolean));
 
+pub const TEXT_TYPE: DataType = DataType::Primitive(PrimitiveDataType::String(StringType::Text { character_set: None }));
+
 impl DataType {
     pub fn property_type(&self, pos: ItemPosition, prop: Path) -> Result<DataType, SemanticError> {
         let mut path = prop;
@@ -893,10 +1074,76 @@ impl DataType {
                 };
                 return data_type.body.can_cast(target);
             }
+            DataType::Enum(self_enum) => {
+                if let DataType::Enum(target_enum) = target {
+                    return self_enum.path == target_enum.path;
+                }
+            }
             DataType::Void => return *target == DataType::Void,
         }
         false
     }
+    /**
+        Явно приводятся друг к другу только простые типы и перечисления.
+        Перечисление приводится как хранимое значение, то есть номер или имя варианта, но не к другому перечислению.
+    */
+    pub fn can_cast_explicitly(&self, target: &DataType) -> bool {
+        let is_enums = self.as_enum().is_some() && target.as_enum().is_some();
+        if is_enums || *self == DataType::Void || *target == DataType::Void {
+            return self.can_cast(target);
+        }
+        match (self.as_primitive(), target.as_primitive()) {
+            (Some(source), Some(target)) => source.can_cast_explicitly(&target),
+            _ => self.can_cast(target),
+        }
+    }
+    /**
+        Поле выборки, которое читает внешнюю сторону соединения, может оказаться `null`,
+        поэтому его нельзя положить в поле без атрибута `#[nullable]`.
+    */
+    pub fn can_keep_nulls_in(&self, target: &DataType) -> bool {
+        if let DataType::Reference(reference) = target {
+            let guard = reference.read();
+            return match guard.get_data_type() {
+                Some(data_type) => self.can_keep_nulls_in(&data_type.body),
+                None => true,
+            };
+        }
+        match (self, target) {
+            (DataType::Array(self_subtype), DataType::Array(subtype)) => self_subtype.can_keep_nulls_in(&*subtype),
+            (
+                DataType::Compound(CompoundDataType::Structure(self_fields)),
+                DataType::Compound(CompoundDataType::Structure(fields)),
+            ) => fields.iter().all(|(name, field)| match self_fields.get(name.as_str()) {
+                Some(self_field) => self_field.can_keep_nulls_in(field),
+                None => true,
+            }),
+            (
+                DataType::Compound(CompoundDataType::Tuple(self_fields)),
+                DataType::Compound(CompoundDataType::Tuple(fields)),
+            ) => self_fields.iter().zip(fields.iter())
+                .all(|(self_field, field)| self_field.can_keep_nulls_in(field)),
+            (DataType::Reference(reference), _) => {
+                let guard = reference.read();
+                match guard.get_data_type() {
+                    Some(data_type) => data_type.body.can_keep_nulls_in(target),
+                    None => true,
+                }
+            }
+            _ => true,
+        }
+    }
+    pub fn should_keep_nulls_in(&self, pos: ItemPosition, target: &DataType) -> Result<(), SemanticError> {
+        if self.can_keep_nulls_in(target) {
+            Ok(())
+        } else {
+            Err(SemanticError::cannot_cast_type(
+                pos,
+                self.clone(),
+                target.clone(),
+            ))
+        }
+    }
     pub fn should_cast_to(&self, pos: ItemPosition, target: &DataType) -> Result<(), SemanticError> {
         if self.can_cast(target) {
             Ok(())
@@ -913,6 +1160,7 @@ impl DataType {
             DataType::Array(item) => &*item,
             DataType::Compound(compound) => return compound.get_field(index)
                 .map(|field| field.field_type.clone()),
+            DataType::Enum(_) |
             DataType::Primitive(_) => self,
             DataType::Reference(item) => {
                 let item = item.read();
@@ -926,6 +1174,7 @@ impl DataType {
     pub fn field_len(&self) -> usize {
         match self {
             DataType::Array(_) |
+            DataType::Enum(_) |
             DataType::Primitive(_) |
             DataType::Void => 1,
             DataType::Compound(compound) => compound.field_len(),
@@ -939,12 +1188,34 @@ impl DataType {
             }
         }
     }
+    /// Выводит выборку всех примитивов типа из производной таблицы `t`: `SELECT t.[a], t.[b#c] FROM`
+    pub fn fmt_derived_table_selection(&self, f: &mut impl Write) -> fmt::Result {
+        f.write_str("SELECT ")?;
+        let mut primitives = self.primitives(PathBuf::new("#"))
+            .into_iter()
+            .peekable();
+        while let Some(primitive) = primitives.next() {
+            write!(f, "t.[{}]", primitive.path)?;
+            if primitives.peek().is_some() {
+                f.write_str(", ")?;
+            }
+        }
+        f.write_str(" FROM")
+    }
     pub fn make_primitives(&self, prefix: PathBuf, target: &mut Vec<FieldPrimitive>) {
         match self {
-            DataType::Array(sub_type) => {
-                let mut sub_prefix = prefix;
-                sub_prefix.push("[]");
-                sub_type.make_primitives(sub_prefix, target);
+            // Вложенный массив хранится одной колонкой: JSON-массивом записей (FOR JSON PATH)
+            DataType::Array(_) => {
+                target.push(FieldPrimitive {
+                    path: prefix,
+                    field_type: PrimitiveDataType::String(StringType::Json),
+                });
+            }
+            DataType::Enum(enum_type) => {
+                target.push(FieldPrimitive {
+                    path: prefix,
+                    field_type: enum_type.storage_type(),
+                });
             }
             DataType::Primitive(primitive) => {
                 target.push(FieldPrimitive {
@@ -988,6 +1259,36 @@ impl DataType {
         self.make_primitives(prefix, &mut result);
         result
     }
+    /// Собирает колонки, хранящие перечисления, в том же виде, что и `make_primitives`
+    pub fn make_enum_columns(&self, prefix: PathBuf, target: &mut Vec<(PathBuf, EnumDataType)>) {
+        match self {
+            DataType::Enum(enum_type) => target.push((prefix, enum_type.clone())),
+            DataType::Compound(CompoundDataType::Tuple(fields)) => {
+                for (i, field) in fields.iter().enumerate() {
+                    let mut path = prefix.clone();
+                    if path.push_fmt(format_args!("component{}", i)).is_ok() {
+                        field.field_type.make_enum_columns(path, target);
+                    }
+                }
+            }
+            DataType::Compound(CompoundDataType::Structure(fields)) => {
+                for (field_name, field) in fields.iter() {
+                    let mut path = prefix.clone();
+                    path.push(field_name.as_str());
+                    field.field_type.make_enum_columns(path, target);
+                }
+            }
+            DataType::Reference(item) => {
+                let item = item.read();
+                if let Some(data_type) = item.get_data_type() {
+                    data_type.body.make_enum_columns(prefix, target);
+                };
+            }
+            DataType::Array(_) |
+            DataType::Primitive(_) |
+            DataType::Void => {}
+        }
+    }
     pub fn can_be_table(&self) -> bool {
         match self {
             DataType::Array(_) |
@@ -1037,6 +1338,7 @@ impl DataType {
     pub fn as_primitive(&self) -> Option<PrimitiveDataType> {
         match self {
             DataType::Primitive(x) => Some(x.clone()),
+            DataType::Enum(enum_type) => Some(enum_type.storage_type()),
             DataType::Reference(item) => {
                 let item_guard = item.read();
                 let references_data_type = item_guard.get_data_type()?;
@@ -1050,6 +1352,16 @@ impl DataType {
             _ => None,
         }
     }
+    pub fn as_enum(&self) -> Option<EnumDataType> {
+        match self {
+            DataType::Enum(enum_type) => Some(enum_type.clone()),
+            DataType::Reference(item) => {
+                let item_guard = item.read();
+                item_guard.get_data_type()?.body.as_enum()
+            }
+            _ => None,
+        }
+    }
     #[inline]
     pub fn as_array(&self) -> Option<&Arc<DataType>> {
         match self {
@@ -1057,6 +1369,19 @@ impl DataType {
             _ => None,
         }
     }
+    /// Кортеж массивов - это несколько наборов записей. Возвращает типы записей каждого набора.
+    pub fn as_record_sets(&self) -> Option<Vec<&DataType>> {
+        let fields = match self {
+            DataType::Compound(CompoundDataType::Tuple(fields)) => fields,
+            _ => return None,
+        };
+        if fields.is_empty() {
+            return None;
+        }
+        fields.iter()
+            .map(|field| field.field_type.as_array().map(|sub_type| &**sub_type))
+            .collect()
+    }
     pub fn fmt(
         &self,
         f: &mut SimpleFormatter,
@@ -1076,6 +1401,9 @@ impl DataType {
                     for (field_name, field) in fields.iter() {
                         write!(sub_f, "{}: ", field_name)?;
                         field.field_type.fmt(&mut sub_f)?;
+                        if field.is_nullable() {
+                            write!(sub_f, " | null")?;
+                        }
                         writeln!(sub_f, ",")?;
                     }
                 }
@@ -1095,6 +1423,18 @@ impl DataType {
                 }
                 write!(f, "]")
             }
+            DataType::Enum(enum_type) => {
+                if !enum_type.is_text {
+                    return f.write_str("number");
+                }
+                for (i, variant) in enum_type.variants.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" | ")?;
+                    }
+                    write!(f, "'{}'", variant)?;
+                }
+                Ok(())
+            }
             DataType::Primitive(PrimitiveDataType::Null) => {
                 f.write_str("null")
             }
@@ -1153,16 +1493,56 @@ impl DataType {
                 self.fmt(f)?;
                 writeln!(f, "")
             }
+            // Строковые перечисления передаются как есть, а числовым нужны имена номеров
+            DataType::Enum(enum_type) => {
+                if enum_type.is_text {
+                    write!(f, "export type {} = ", name)?;
+                    self.fmt(f)?;
+                    return writeln!(f, ";");
+                }
+                writeln!(f, "export enum {} {{", name)?;
+                {
+                    let mut sub_f = f.sub_block();
+                    for (i, variant) in enum_type.variants.iter().enumerate() {
+                        writeln!(sub_f, "{} = {},", variant, i)?;
+                    }
+                }
+                writeln!(f, "}}")
+            }
         }
     }
+    #[inline]
     pub fn fmt_result_bind(
         &self,
         f: &mut SimpleFormatter,
         variable: &str,
         prefix: Path,
+    ) -> fmt::Result {
+        self.fmt_result_bind_from(f, variable, prefix, false)
+    }
+    /// `is_json` означает, что `variable` - элемент вложенного массива, разобранного из JSON
+    fn fmt_result_bind_from(
+        &self,
+        f: &mut SimpleFormatter,
+        variable: &str,
+        prefix: Path,
+        is_json: bool,
     ) -> fmt::Result {
         match self {
-            DataType::Array(_) => panic!("Array-type cannot be bind"),
+            DataType::Array(sub_type) => {
+                writeln!(f, "_jsonArray({var}['{path}']).map(item => (", var = variable, path = prefix)?;
+                {
+                    let mut sub_f = f.sub_block();
+                    sub_type.fmt_result_bind_from(
+                        &mut sub_f,
+                        "item",
+                        Path::new("", "#"),
+                        true,
+                    )?;
+                    writeln!(sub_f, "")?;
+                }
+                write!(f, "))")
+            }
             DataType::Compound(CompoundDataType::Tuple(fields)) => {
                 writeln!(f, "[")?;
                 {
@@ -1170,10 +1550,11 @@ impl DataType {
                     for (i, field) in fields.iter().enumerate() {
                         let mut field_prefix = prefix.into_buf();
                         field_prefix.push_fmt(format_args!("component{}", i))?;
-                        field.field_type.fmt_result_bind(
+                        field.field_type.fmt_result_bind_from(
                             &mut sub_f,
                             variable,
                             field_prefix.as_path(),
+                            is_json,
                         )?;
                         writeln!(sub_f, ",")?;
                     }
@@ -1188,16 +1569,27 @@ impl DataType {
                         let mut field_prefix = prefix.into_buf();
                         field_prefix.push(&*name);
                         write!(sub_f, "{}: ", name)?;
-                        field.field_type.fmt_result_bind(
+                        field.field_type.fmt_result_bind_from(
                             &mut sub_f,
                             variable,
                             field_prefix.as_path(),
+                            is_json,
                         )?;
                         writeln!(sub_f, ",")?;
                     }
                 }
                 write!(f, "}}")
             }
+            // В JSON даты приходят строками
+            DataType::Primitive(PrimitiveDataType::DateTime(_)) if is_json => {
+                write!(
+                    f,
+                    "new Date({var}['{path}'])",
+                    var = variable,
+                    path = prefix,
+                )
+            }
+            DataType::Enum(_) |
             DataType::Primitive(_) => {
                 write!(
                     f,
@@ -1210,10 +1602,11 @@ impl DataType {
                 item.read()
                     .get_data_type()
                     .expect("Wrong references are not allowed at generate-time")
-                    .body.fmt_result_bind(
+                    .body.fmt_result_bind_from(
                         f,
                         variable,
-                        prefix
+                        prefix,
+                        is_json,
                     )
             }
             DataType::Void => f.write_str("void 0"),
@@ -1247,6 +1640,7 @@ impl fmt::Display for DataType {
                 }
                 write!(f, ")")
             }
+            DataType::Enum(enum_type) => write!(f, "enum {}", enum_type.path),
             DataType::Primitive(primitive) => write!(f, "{}", primitive),
             DataType::Reference(refer) => {
                 let reference = refer.read();
@@ -1276,6 +1670,9 @@ impl PartialEq for DataType {
             DataType::Compound(lhs_compound) => if let DataType::Compound(rhs_compound) = rhs {
                 *lhs_compound == *rhs_compound
             } else { false }
+            DataType::Enum(lhs_enum) => if let DataType::Enum(rhs_enum) = rhs {
+                lhs_enum.path == rhs_enum.path
+            } else { false }
             DataType::Primitive(lhs_primitive) => if let DataType::Primitive(rhs_primitive) = rhs {
                 *lhs_primitive == *rhs_primitive
             } else { false }
@@ -8,9 +8,11 @@
 
     attributes = ...attribute
 
-    struct_field = attributes identifier ":" data_type
+    field_type = data_type ["[]"]
 
-    tuple_field = attributes data_type
+    struct_field = attributes identifier ":" field_type
+
+    tuple_field = attributes field_type
 
     struct_body = attributes "{" ...struct_field "}"
 
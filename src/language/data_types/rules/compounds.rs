@@ -39,25 +39,38 @@ parser_rule!(pub attributes(i) -> Vec<AttributeAST<'source>> {
     many0!(i, attribute)
 });
 
-/// attributes identifier ":" data_type
+/// data_type ["[]"]
+parser_rule!(pub field_type(i) -> DataTypeAST<'source> {
+    do_parse!(i,
+        data_type: data_type >>
+        is_array: opt!(apply!(symbols, "[]")) >>
+        (if is_array.is_some() {
+            data_type.array()
+        } else {
+            data_type
+        })
+    )
+});
+
+/// attributes identifier ":" field_type
 parser_rule!(struct_field(i) -> (Identifier<'source>, FieldAST<'source>) {
     do_parse!(i,
         begin: symbol_position >>
         attributes: attributes >>
         name: identifier >>
         apply!(symbols, ":") >>
-        field_type: data_type >>
+        field_type: field_type >>
         position: apply!(item_position, begin) >>
         ((name, FieldAST { attributes, field_type, position }))
     )
 });
 
-/// attributes data_type
+/// attributes field_type
 parser_rule!(tuple_field(i) -> FieldAST<'source> {
     do_parse!(i,
         begin: symbol_position >>
         attributes: attributes >>
-        field_type: data_type >>
+        field_type: field_type >>
         position: apply!(item_position, begin) >>
         (FieldAST { attributes, field_type, position })
     )
@@ -7,15 +7,28 @@ use helpers::{
 };
 use helpers::{
     Assertion,
+    CodeFormatter,
     is_f32_enough,
+    TSQL,
 };
 use language::{
+    BOOLEAN_TYPE,
     CompoundDataType,
+    ConstDefinition,
     DataType,
+    DataTypeAST,
+    Dialect,
     Field,
     ItemPath,
     NumberType,
+    OperandSide,
+    Operation,
     PrimitiveDataType,
+    Selection,
+    SelectionAST,
+    SelectionSortingItem,
+    SelectionSortingItemAST,
+    SelectionSortingOrder,
     StringType,
     TSQLFunctionContext,
 };
@@ -27,6 +40,7 @@ use project_analysis::{
     Item,
     SemanticError,
     SemanticItemType,
+    StdLibArgument,
     StdLibFunction,
 };
 use std::{
@@ -53,6 +67,8 @@ pub enum LiteralType {
     },
     StringLiteral {
         length: u32,
+        /// Национальная строка T-SQL (`N'...'`), которой записываются варианты строковых перечислений
+        national: bool,
     },
     BracedExpressionLiteral {
         length: u32,
@@ -91,7 +107,7 @@ impl LiteralType {
                     }))
                 }
             }
-            LiteralType::StringLiteral { length } => {
+            LiteralType::StringLiteral { length, .. } => {
                 let string_type = if length < 256 {
                     StringType::Varchar { size: Some(length), character_set: None }
                 } else {
@@ -153,6 +169,10 @@ impl Literal {
         f: &mut impl fmt::Write,
     ) -> fmt::Result {
         match &self.literal_type {
+            LiteralType::StringLiteral { national: true, .. } => {
+                f.write_char('N')?;
+                f.write_str(self.text.as_str())
+            }
             LiteralType::NumberLiteral { .. } |
             LiteralType::StringLiteral { .. } |
             LiteralType::BracedExpressionLiteral { .. } =>
@@ -165,6 +185,23 @@ impl Literal {
                 })
         }
     }
+    pub fn fmt_ts(
+        &self,
+        f: &mut impl fmt::Write,
+    ) -> fmt::Result {
+        match &self.literal_type {
+            LiteralType::NumberLiteral { .. } |
+            LiteralType::StringLiteral { .. } |
+            LiteralType::BracedExpressionLiteral { .. } =>
+                f.write_str(self.text.as_str()),
+            LiteralType::KeywordLiteral(keyword) =>
+                f.write_str(match keyword {
+                    KeywordLiteralType::True => "true",
+                    KeywordLiteralType::False => "false",
+                    KeywordLiteralType::Null => "null",
+                })
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -364,6 +401,71 @@ pub enum ExpressionASTBody<'source> {
     PropertyAccess(Box<ExpressionAST<'source>>, ItemPath),
     Set(Vec<ExpressionAST<'source>>),
     FunctionCall(ItemPath, Vec<ExpressionAST<'source>>),
+    ItemReference(ItemPath),
+    Case(Vec<(ExpressionAST<'source>, ExpressionAST<'source>)>, Option<Box<ExpressionAST<'source>>>),
+    /// Явное приведение типа, флаг означает `try_cast`, который при неудаче даёт `null` вместо ошибки
+    Cast(Box<ExpressionAST<'source>>, DataTypeAST<'source>, bool),
+    Selection(Box<SelectionAST<'source>>),
+    /// Вызов агрегатной или оконной функции стандартной библиотеки по окну строк выборки
+    WindowFunctionCall(ItemPath, Vec<ExpressionAST<'source>>, WindowAST<'source>),
+}
+
+/// Окно `over (partition by ... order by ...)`, по строкам которого вычисляется функция
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowAST<'source> {
+    pub partition_by: Vec<ExpressionAST<'source>>,
+    pub order_by: Vec<SelectionSortingItemAST<'source>>,
+}
+
+impl<'source> Resolve<SyncRef<FunctionVariableScope>> for WindowAST<'source> {
+    type Result = Window;
+    type Error = SemanticError;
+    fn resolve(&self, scope: &SyncRef<FunctionVariableScope>) -> Result<Self::Result, Vec<Self::Error>> {
+        Ok(Window {
+            partition_by: self.partition_by.resolve(scope)?,
+            order_by: self.order_by.resolve(scope)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    pub partition_by: Vec<Expression>,
+    pub order_by: Vec<SelectionSortingItem>,
+}
+
+impl Window {
+    pub fn fmt(
+        &self,
+        f: &mut impl fmt::Write,
+        context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        f.write_str("OVER (")?;
+        for (i, expr) in self.partition_by.iter().enumerate() {
+            f.write_str(if i == 0 { "PARTITION BY " } else { ", " })?;
+            expr.fmt(f, context)?;
+        }
+        for (i, item) in self.order_by.iter().enumerate() {
+            if i == 0 {
+                if !self.partition_by.is_empty() {
+                    f.write_char(' ')?;
+                }
+                f.write_str("ORDER BY ")?;
+            } else {
+                f.write_str(", ")?;
+            }
+            item.expr.fmt(f, context)?;
+            f.write_str(match &item.order {
+                SelectionSortingOrder::Asc => " ASC",
+                SelectionSortingOrder::Desc => " DESC",
+            })?;
+        }
+        f.write_char(')')
+    }
+    fn expressions(&self) -> impl Iterator<Item=&Expression> {
+        self.partition_by.iter()
+            .chain(self.order_by.iter().map(|item| &item.expr))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -382,7 +484,7 @@ impl<'source> Assertion for ExpressionAST<'source> {
             }
             ExpressionASTBody::Reference(ident_left) => {
                 if let ExpressionASTBody::Reference(ident_right) = &other.body {
-                    assert_eq!(ident_left, ident_right);
+                    assert_eq!(ident_left.text(), ident_right.text());
                 } else { assert_eq!(self.body, other.body) }
             }
             ExpressionASTBody::BinaryOperation(left_left, left_op, left_right) => {
@@ -421,6 +523,44 @@ impl<'source> Assertion for ExpressionAST<'source> {
                     left_args.as_slice().assert(&right_args.as_slice());
                 } else { assert_eq!(self.body, other.body) }
             }
+            ExpressionASTBody::ItemReference(left_path) => {
+                if let ExpressionASTBody::ItemReference(right_path) = &other.body {
+                    assert_eq!(left_path.path, right_path.path);
+                } else { assert_eq!(self.body, other.body) }
+            }
+            ExpressionASTBody::Case(left_branches, left_else) => {
+                if let ExpressionASTBody::Case(right_branches, right_else) = &other.body {
+                    assert_eq!(left_branches.len(), right_branches.len());
+                    for ((left_condition, left_value), (right_condition, right_value)) in left_branches.iter().zip(right_branches.iter()) {
+                        left_condition.assert(right_condition);
+                        left_value.assert(right_value);
+                    }
+                    match (left_else, right_else) {
+                        (Some(left_else), Some(right_else)) => (*left_else).assert(&**right_else),
+                        (None, None) => {}
+                        _ => assert_eq!(left_else, right_else),
+                    }
+                } else { assert_eq!(self.body, other.body) }
+            }
+            ExpressionASTBody::Cast(left, left_type, left_is_try) => {
+                if let ExpressionASTBody::Cast(right, right_type, right_is_try) = &other.body {
+                    (*left).assert(&**right);
+                    left_type.assert(right_type);
+                    assert_eq!(left_is_try, right_is_try);
+                } else { assert_eq!(self.body, other.body) }
+            }
+            ExpressionASTBody::Selection(left_query) => {
+                if let ExpressionASTBody::Selection(right_query) = &other.body {
+                    assert_eq!(left_query, right_query);
+                } else { assert_eq!(self.body, other.body) }
+            }
+            ExpressionASTBody::WindowFunctionCall(left_name, left_args, left_window) => {
+                if let ExpressionASTBody::WindowFunctionCall(right_name, right_args, right_window) = &other.body {
+                    assert_eq!(left_name.path, right_name.path);
+                    left_args.as_slice().assert(&right_args.as_slice());
+                    assert_eq!(left_window, right_window);
+                } else { assert_eq!(self.body, other.body) }
+            }
         }
     }
 }
@@ -445,7 +585,7 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for ExpressionAST<'source>
     type Result = Expression;
     type Error = SemanticError;
     fn resolve(&self, scope: &SyncRef<FunctionVariableScope>) -> Result<Self::Result, Vec<Self::Error>> {
-        match &self.body {
+        let result = match &self.body {
             ExpressionASTBody::Literal(lit) => {
                 Expression::literal(self.pos, lit)
                     .map_err(|e| vec![e])
@@ -472,7 +612,24 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for ExpressionAST<'source>
             ExpressionASTBody::FunctionCall(function, arguments) => {
                 Expression::function_call(scope, self.pos, function, arguments)
             }
-        }
+            ExpressionASTBody::ItemReference(path) => {
+                Expression::item_reference(scope, self.pos, path)
+                    .map_err(|e| vec![e])
+            }
+            ExpressionASTBody::Case(branches, else_branch) => {
+                Expression::case(scope, self.pos, branches, else_branch)
+            }
+            ExpressionASTBody::Cast(expr, data_type, is_try) => {
+                Expression::cast(scope, self.pos, expr, data_type, *is_try)
+            }
+            ExpressionASTBody::Selection(query) => {
+                Expression::scalar_selection(scope, self.pos, query)
+            }
+            ExpressionASTBody::WindowFunctionCall(function, arguments, window) => {
+                Expression::window_function_call(scope, self.pos, function, arguments, window)
+            }
+        };
+        result.map(Expression::fold)
     }
 }
 
@@ -487,6 +644,10 @@ pub enum ExpressionBody {
     Set(Vec<Expression>),
     FunctionCall(SyncRef<Item>, Vec<Expression>),
     StdFunctionCall(Arc<StdLibFunction>, Vec<Expression>),
+    Case(Vec<(Expression, Expression)>, Option<Box<Expression>>),
+    Cast(Box<Expression>, bool),
+    Selection(Box<Selection>),
+    WindowFunctionCall(Arc<StdLibFunction>, Vec<Expression>, Window),
 }
 
 impl cmp::PartialEq for ExpressionBody {
@@ -541,6 +702,28 @@ impl cmp::PartialEq for ExpressionBody {
                         (arguments == other_arguments);
                 }
             }
+            ExpressionBody::Case(branches, else_branch) => {
+                if let ExpressionBody::Case(other_branches, other_else_branch) = other {
+                    return (branches == other_branches) && (else_branch == other_else_branch);
+                }
+            }
+            ExpressionBody::Cast(expr, is_try) => {
+                if let ExpressionBody::Cast(other_expr, other_is_try) = other {
+                    return (is_try == other_is_try) && expr.eq(other_expr);
+                }
+            }
+            ExpressionBody::Selection(query) => {
+                if let ExpressionBody::Selection(other_query) = other {
+                    return query == other_query;
+                }
+            }
+            ExpressionBody::WindowFunctionCall(function, arguments, window) => {
+                if let ExpressionBody::WindowFunctionCall(other_function, other_arguments, other_window) = other {
+                    return (function == other_function)
+                        && (arguments == other_arguments)
+                        && (window == other_window);
+                }
+            }
         }
         false
     }
@@ -568,10 +751,31 @@ impl Expression {
         pos: ItemPosition,
         ident: &Identifier,
     ) -> Result<Self, SemanticError> {
-        let var = scope.access_to_variable(ident.item_pos(), ident.text())?;
+        let var = match scope.access_to_variable(ident.item_pos(), ident.text()) {
+            Ok(var) => var,
+            Err(error) => {
+                // Имя, не найденное среди переменных, может быть константой модуля
+                let item = scope.module().get_item(Path::new(ident.text(), "::"), &mut Vec::new());
+                return match item {
+                    Some(item) => match item.read().get_constant() {
+                        Some(constant) => Ok(Expression::constant(constant, pos)),
+                        None => Err(error),
+                    },
+                    None => Err(error),
+                };
+            }
+        };
         let data_type = var.property_type(pos, Path::empty())?;
         Ok(Expression::variable_access(var, pos, data_type))
     }
+    /// Константа подставляется в место использования своим значением
+    #[inline]
+    pub fn constant(constant: &ConstDefinition, pos: ItemPosition) -> Self {
+        Expression {
+            pos,
+            ..constant.value.clone()
+        }
+    }
     #[inline]
     pub fn variable_access(
         var: SyncRef<FunctionVariable>,
@@ -592,6 +796,9 @@ impl Expression {
         right: &Box<ExpressionAST>,
     ) -> Result<Self, Vec<SemanticError>>
     {
+        if let Some(result) = Expression::selection_comparison(scope, pos, op, left, right) {
+            return result;
+        }
         let (left, right) = (left, right).resolve(scope)?;
         let data_type = scope.project()
             .resolve_binary_operation(pos, op, &left.data_type, &right.data_type)?
@@ -628,6 +835,24 @@ impl Expression {
         expr: &Box<ExpressionAST>,
     ) -> Result<Self, Vec<SemanticError>>
     {
+        if let ExpressionASTBody::Selection(query) = &expr.body {
+            match op {
+                PrefixUnaryOperator::Exists => {
+                    let query = Expression::selection(scope, expr.pos, query)?;
+                    return Ok(Expression {
+                        body: ExpressionBody::PrefixUnaryOperation(op, Box::new(query)),
+                        pos,
+                        data_type: BOOLEAN_TYPE,
+                    });
+                }
+                PrefixUnaryOperator::All |
+                PrefixUnaryOperator::Any => {
+                    return SemanticError::not_allowed_here(pos, "quantified selection outside comparison")
+                        .into_err_vec();
+                }
+                _ => {}
+            }
+        }
         let expr = expr.resolve(scope)?;
         let data_type = scope.project()
             .resolve_prefix_unary_operation(pos, op, &expr.data_type)?
@@ -678,6 +903,206 @@ impl Expression {
             data_type,
         })
     }
+    /// Тип условного выражения - тип той ветви, к которому приводятся все остальные
+    pub fn case(
+        scope: &SyncRef<FunctionVariableScope>,
+        pos: ItemPosition,
+        branches: &Vec<(ExpressionAST, ExpressionAST)>,
+        else_branch: &Option<Box<ExpressionAST>>,
+    ) -> Result<Self, Vec<SemanticError>>
+    {
+        let branches = branches.resolve(scope)?;
+        let else_branch = else_branch.resolve(scope)?;
+        let errors: Vec<SemanticError> = branches.iter()
+            .filter_map(|(condition, _)| condition.should_cast_to_type(&BOOLEAN_TYPE).err())
+            .collect();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let data_type = {
+            let values: Vec<&Expression> = branches.iter()
+                .map(|(_, value)| value)
+                .chain(else_branch.iter().map(|value| &**value))
+                .collect();
+            let common = values.iter()
+                .find(|candidate| values.iter().all(|value| value.data_type.can_cast(&candidate.data_type)));
+            match common {
+                Some(common) => common.data_type.clone(),
+                None => {
+                    let first = values[0];
+                    let other = values.iter()
+                        .find(|value| !value.data_type.can_cast(&first.data_type))
+                        .expect("Some branch should not be castable to the first one");
+                    return SemanticError::cannot_cast_type(
+                        other.pos,
+                        other.data_type.clone(),
+                        first.data_type.clone(),
+                    )
+                        .into_err_vec();
+                }
+            }
+        };
+        Ok(Expression {
+            body: ExpressionBody::Case(branches, else_branch),
+            pos,
+            data_type,
+        })
+    }
+    /// Подзапрос. Если он возвращает одну колонку, выражение получает её тип, иначе - тип строки выборки.
+    pub fn selection(
+        scope: &SyncRef<FunctionVariableScope>,
+        pos: ItemPosition,
+        query: &SelectionAST,
+    ) -> Result<Self, Vec<SemanticError>>
+    {
+        let query = query.resolve(scope)?;
+        let data_type = if query.result.len() == 1 {
+            query.result[0].expr.data_type.clone()
+        } else {
+            match &query.result_data_type {
+                DataType::Array(row_type) => (**row_type).clone(),
+                row_type => row_type.clone(),
+            }
+        };
+        Ok(Expression {
+            body: ExpressionBody::Selection(Box::new(query)),
+            pos,
+            data_type,
+        })
+    }
+    /// Подзапрос, с которым сравнивается значение, должен возвращать ровно одну колонку простого типа
+    fn column_selection(
+        scope: &SyncRef<FunctionVariableScope>,
+        pos: ItemPosition,
+        query: &SelectionAST,
+    ) -> Result<Self, Vec<SemanticError>>
+    {
+        let result = Expression::selection(scope, pos, query)?;
+        let columns_count = match &result.body {
+            ExpressionBody::Selection(query) => query.result.len(),
+            _ => unreachable!(),
+        };
+        if columns_count != 1 || result.data_type.as_primitive().is_none() {
+            return SemanticError::not_allowed_inside(pos, "selection of several values", "expression")
+                .into_err_vec();
+        }
+        Ok(result)
+    }
+    /**
+        Подзапрос, используемый как значение, кроме того, должен возвращать не больше одной строки.
+        Лишние строки T-SQL обнаружил бы только при выполнении, поэтому без агрегации требуется `limit 1`.
+    */
+    pub fn scalar_selection(
+        scope: &SyncRef<FunctionVariableScope>,
+        pos: ItemPosition,
+        query: &SelectionAST,
+    ) -> Result<Self, Vec<SemanticError>>
+    {
+        let result = Expression::column_selection(scope, pos, query)?;
+        let is_single_row = match &result.body {
+            ExpressionBody::Selection(query) => {
+                query.result_data_type.as_array().is_none()
+                    || query.limit_clause.as_ref().map_or(false, |limit| limit.count <= 1)
+            }
+            _ => unreachable!(),
+        };
+        if !is_single_row {
+            return SemanticError::not_allowed_inside(pos, "selection of several rows without limit 1", "expression")
+                .into_err_vec();
+        }
+        Ok(result)
+    }
+    /**
+        Сравнение с подзапросом: `x is in (select ...)` и `x > all(select ...)`.
+        Типы проверяются по операции сравнения левого операнда с колонкой подзапроса.
+    */
+    fn selection_comparison(
+        scope: &SyncRef<FunctionVariableScope>,
+        pos: ItemPosition,
+        op: BinaryOperator,
+        left: &Box<ExpressionAST>,
+        right: &Box<ExpressionAST>,
+    ) -> Option<Result<Self, Vec<SemanticError>>>
+    {
+        let (quantifier, query) = match &right.body {
+            ExpressionASTBody::Selection(query) if op == BinaryOperator::IsIn => (None, (right.pos, &**query)),
+            ExpressionASTBody::PrefixUnaryOperation(quantifier @ PrefixUnaryOperator::All, expr) |
+            ExpressionASTBody::PrefixUnaryOperation(quantifier @ PrefixUnaryOperator::Any, expr) => match &expr.body {
+                ExpressionASTBody::Selection(query) => (Some(*quantifier), (expr.pos, &**query)),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let comparison = match op {
+            BinaryOperator::IsIn => BinaryOperator::Equals,
+            BinaryOperator::Equals |
+            BinaryOperator::MoreThanOrEquals |
+            BinaryOperator::MoreThan |
+            BinaryOperator::LessThanOrEquals |
+            BinaryOperator::LessThan => op,
+            _ => return Some(
+                SemanticError::not_allowed_here(pos, "quantified selection outside comparison")
+                    .into_err_vec()
+            ),
+        };
+        Some(Expression::resolve_selection_comparison(scope, pos, op, comparison, left, quantifier, query))
+    }
+    fn resolve_selection_comparison(
+        scope: &SyncRef<FunctionVariableScope>,
+        pos: ItemPosition,
+        op: BinaryOperator,
+        comparison: BinaryOperator,
+        left: &Box<ExpressionAST>,
+        quantifier: Option<PrefixUnaryOperator>,
+        (query_pos, query): (ItemPosition, &SelectionAST),
+    ) -> Result<Self, Vec<SemanticError>>
+    {
+        let left = left.resolve(scope)?;
+        let query = Expression::column_selection(scope, query_pos, query)?;
+        let data_type = scope.project()
+            .resolve_binary_operation(pos, comparison, &left.data_type, &query.data_type)?
+            .output
+            .clone();
+        let right = match quantifier {
+            Some(quantifier) => Expression {
+                pos: query.pos,
+                data_type: query.data_type.clone(),
+                body: ExpressionBody::PrefixUnaryOperation(quantifier, Box::new(query)),
+            },
+            None => query,
+        };
+        Ok(Expression {
+            body: ExpressionBody::BinaryOperation(left, op, Box::new(right)),
+            pos,
+            data_type,
+        })
+    }
+    /// Целевой тип ищется в модуле функции, а совместимость проверяется по правилам явного приведения
+    pub fn cast(
+        scope: &SyncRef<FunctionVariableScope>,
+        pos: ItemPosition,
+        expr: &Box<ExpressionAST>,
+        data_type: &DataTypeAST,
+        is_try: bool,
+    ) -> Result<Self, Vec<SemanticError>>
+    {
+        let expr = expr.resolve(scope)?;
+        let data_type = data_type.resolve(&scope.module())?;
+        // В T-SQL приводятся только простые значения, перечисление приводится как хранимое значение
+        if data_type == DataType::Void || data_type.as_primitive().is_none() {
+            return SemanticError::not_allowed_here(pos, "cast to a type that is neither primitive nor enum")
+                .into_err_vec();
+        }
+        if !expr.data_type.can_cast_explicitly(&data_type) {
+            return SemanticError::cannot_cast_type(expr.pos, expr.data_type.clone(), data_type)
+                .into_err_vec();
+        }
+        Ok(Expression {
+            body: ExpressionBody::Cast(expr, is_try),
+            pos,
+            data_type,
+        })
+    }
     pub fn function_call(
         scope: &SyncRef<FunctionVariableScope>,
         pos: ItemPosition,
@@ -716,6 +1141,16 @@ impl Expression {
                     .into_err_vec(),
             };
 
+            // Наборы записей процедуры возвращаются только клиенту
+            if function.result.as_record_sets().is_some() {
+                return SemanticError::not_allowed_inside(
+                    pos,
+                    "function with several record sets",
+                    "expression",
+                )
+                    .into_err_vec();
+            }
+
             if scope.is_lite_weight() && function.result.as_primitive().is_none() {
                 return SemanticError::not_allowed_here(
                     pos,
@@ -751,6 +1186,73 @@ impl Expression {
             data_type,
         })
     }
+    pub fn item_reference(
+        scope: &SyncRef<FunctionVariableScope>,
+        pos: ItemPosition,
+        path: &ItemPath,
+    ) -> Result<Self, SemanticError> {
+        if let Some(item) = scope.module().get_item(path.path.as_path(), &mut Vec::new()) {
+            if let Some(constant) = item.read().get_constant() {
+                return Ok(Expression::constant(constant, pos));
+            }
+        }
+        Expression::enum_variant(scope, pos, path)
+    }
+    /// Вариант перечисления сразу становится литералом с его значением в T-SQL
+    pub fn enum_variant(
+        scope: &SyncRef<FunctionVariableScope>,
+        pos: ItemPosition,
+        path: &ItemPath,
+    ) -> Result<Self, SemanticError> {
+        let mut type_path = path.path.as_path();
+        let variant = type_path.pop_right()
+            .expect("Enum variant's path should not be empty");
+        let item = match scope.module().get_item(type_path, &mut Vec::new()) {
+            Some(item) => item,
+            None => return Err(SemanticError::unresolved_item(path.pos, type_path.into_buf())),
+        };
+        let item_type = item.read().get_type();
+        if item_type != SemanticItemType::DataType {
+            return Err(SemanticError::expected_item_of_another_type(
+                path.pos,
+                SemanticItemType::DataType,
+                item_type,
+            ));
+        }
+        let data_type = DataType::Reference(item);
+        let (enum_type, index) = match data_type.as_enum() {
+            Some(enum_type) => match enum_type.variant_index(variant) {
+                Some(index) => (enum_type, index),
+                None => return Err(SemanticError::wrong_property(pos, variant.to_string())),
+            },
+            None => return Err(SemanticError::wrong_property(pos, variant.to_string())),
+        };
+        let (literal_type, text) = if enum_type.is_text {
+            (
+                LiteralType::StringLiteral { length: variant.len() as u32, national: true },
+                format!("'{}'", variant),
+            )
+        } else {
+            (
+                LiteralType::NumberLiteral {
+                    negative: false,
+                    fractional: false,
+                    radix: 10,
+                    approx_value: index as f64,
+                },
+                index.to_string(),
+            )
+        };
+        Ok(Expression {
+            body: ExpressionBody::Literal(Literal {
+                literal_type,
+                text,
+                pos,
+            }),
+            pos,
+            data_type,
+        })
+    }
     pub fn std_function_call(
         scope: &SyncRef<FunctionVariableScope>,
         pos: ItemPosition,
@@ -773,6 +1275,13 @@ impl Expression {
             ));
         }
 
+        if function.is_window {
+            return Err(SemanticError::not_allowed_here(
+                pos,
+                "window functions without OVER clause",
+            ));
+        }
+
         if !scope.is_aggregate() && function.is_aggregate {
             return Err(SemanticError::not_allowed_here(
                 pos,
@@ -780,24 +1289,106 @@ impl Expression {
             ));
         }
 
-        if arguments.len() != function.arguments.len() {
+        Expression::check_std_function_arguments(pos, &function, &arguments)?;
+
+        let data_type = Expression::std_function_output(&function, &arguments);
+
+        Ok(Expression {
+            body: ExpressionBody::StdFunctionCall(function, arguments),
+            pos,
+            data_type,
+        })
+    }
+    fn check_std_function_arguments(
+        pos: ItemPosition,
+        function: &StdLibFunction,
+        arguments: &[Expression],
+    ) -> Result<(), SemanticError> {
+        if arguments.len() > function.arguments.len() {
             return Err(SemanticError::wrong_arguments_count(
                 pos,
                 function.arguments.len(),
                 arguments.len(),
             ));
         }
-
-        for (i, argument) in arguments.iter().enumerate() {
-            let target_data_type = function.arguments.get(i)
-                .expect("The argument can not cease to exist immediately after checking the length of the collection");
-            argument.should_cast_to_type(target_data_type)?;
+        if arguments.len() < function.required_arguments {
+            return Err(SemanticError::wrong_arguments_count(
+                pos,
+                function.required_arguments,
+                arguments.len(),
+            ));
         }
 
-        let data_type = function.output.clone();
-
+        // Обобщённый тип задаёт первый из обобщённых аргументов
+        let mut generic_data_type = None;
+        for (argument, target) in arguments.iter().zip(function.arguments.iter()) {
+            match target {
+                StdLibArgument::Typed(target_data_type) => argument.should_cast_to_type(target_data_type)?,
+                StdLibArgument::Generic => match &generic_data_type {
+                    Some(generic_data_type) => argument.should_cast_to_type(generic_data_type)?,
+                    None => generic_data_type = Some(argument.data_type.clone()),
+                },
+            }
+        }
+        Ok(())
+    }
+    #[inline]
+    fn std_function_output(function: &StdLibFunction, arguments: &[Expression]) -> DataType {
+        if function.is_generic_output {
+            let generic_argument = arguments.iter()
+                .zip(function.arguments.iter())
+                .find(|(_, target)| **target == StdLibArgument::Generic);
+            if let Some((argument, _)) = generic_argument {
+                return argument.data_type.clone();
+            }
+        }
+        function.output.clone()
+    }
+    /**
+        Вызов функции стандартной библиотеки по окну.
+        С окном вызываются оконные функции, а также агрегатные, которые тогда не делают запрос группирующим.
+    */
+    pub fn window_function_call(
+        scope: &SyncRef<FunctionVariableScope>,
+        pos: ItemPosition,
+        function: &ItemPath,
+        arguments: &Vec<ExpressionAST>,
+        window: &WindowAST,
+    ) -> Result<Self, Vec<SemanticError>> {
+        if !scope.is_window() {
+            return SemanticError::not_allowed_here(pos, "window functions").into_err_vec();
+        }
+        let function = match function.path.as_path().the_only() {
+            Some(name) => match scope.project().resolve_stdlib_function(name) {
+                Some(function) => function,
+                None => return SemanticError::unresolved_item(function.pos, function.path.clone()).into_err_vec(),
+            },
+            None => return SemanticError::unresolved_item(function.pos, function.path.clone()).into_err_vec(),
+        };
+        if !function.is_window && !function.is_aggregate {
+            return SemanticError::not_allowed_inside(pos, "OVER clause", "call of not aggregate function")
+                .into_err_vec();
+        }
+        if scope.is_lite_weight() && !function.is_lite_weight {
+            return SemanticError::not_allowed_here(pos, "not lite-weight functions").into_err_vec();
+        }
+        // Внутри окна другие оконные функции недопустимы, а агрегатные допустимы только в группирующем запросе
+        let inner_scope = if scope.is_grouped() {
+            scope.aggregate_child()
+        } else {
+            scope.not_aggregate_child()
+        };
+        let arguments: Vec<Expression> = arguments.resolve(&inner_scope)?;
+        let window = window.resolve(&inner_scope)?;
+        if function.is_ordered && window.order_by.is_empty() {
+            return SemanticError::not_allowed_inside(pos, "ordered window function", "window without sorting")
+                .into_err_vec();
+        }
+        Expression::check_std_function_arguments(pos, &function, &arguments)
+            .map_err(|e| vec![e])?;
+        let data_type = Expression::std_function_output(&function, &arguments);
         Ok(Expression {
-            body: ExpressionBody::StdFunctionCall(function, arguments),
+            body: ExpressionBody::WindowFunctionCall(function, arguments, window),
             pos,
             data_type,
         })
@@ -850,6 +1441,28 @@ impl Expression {
             ExpressionBody::FunctionCall(_, expressions) => {
                 Expression::can_expressions_be_selected_by_aggregation_query(expressions, aggregates)
             }
+            ExpressionBody::Case(branches, else_branch) => {
+                for (condition, value) in branches.iter() {
+                    if !condition.can_be_selected_by_aggregation_query(aggregates.clone())?
+                        || !value.can_be_selected_by_aggregation_query(aggregates.clone())? {
+                        return Ok(false);
+                    }
+                }
+                match else_branch {
+                    Some(else_branch) => else_branch.can_be_selected_by_aggregation_query(aggregates),
+                    None => Ok(true),
+                }
+            }
+            ExpressionBody::Cast(expr, _) => {
+                expr.can_be_selected_by_aggregation_query(aggregates)
+            }
+            ExpressionBody::Selection(_) => Ok(true),
+            ExpressionBody::WindowFunctionCall(_, expressions, window) => {
+                Ok(
+                    Expression::can_expressions_be_selected_by_aggregation_query(expressions, aggregates.clone())?
+                        && Expression::can_expressions_be_selected_by_aggregation_query(window.expressions(), aggregates)?
+                )
+            }
             ExpressionBody::StdFunctionCall(function, expressions) => {
                 if function.is_aggregate {
                     let mut errors = Vec::new();
@@ -902,6 +1515,34 @@ impl Expression {
     pub fn should_cast_to_type(&self, target: &DataType) -> Result<(), SemanticError> {
         self.data_type.should_cast_to(self.pos, target)
     }
+    /// Значение может оказаться `null`, если выражение читает источник с внешней стороны соединения
+    pub fn is_nullable(&self) -> bool {
+        match &self.body {
+            ExpressionBody::Variable(var) => var.is_nullable(),
+            ExpressionBody::BinaryOperation(left, _, right) => left.is_nullable() || right.is_nullable(),
+            ExpressionBody::PrefixUnaryOperation(PrefixUnaryOperator::Exists, _) => false,
+            ExpressionBody::PrefixUnaryOperation(_, expr) |
+            ExpressionBody::PropertyAccess(expr, _) => expr.is_nullable(),
+            // Без ветви "else" выражение даёт null, если ни одно условие не выполнилось
+            ExpressionBody::Case(branches, else_branch) => match else_branch {
+                Some(else_branch) => else_branch.is_nullable() || branches.iter().any(|(_, value)| value.is_nullable()),
+                None => true,
+            },
+            ExpressionBody::Cast(expr, is_try) => *is_try || expr.is_nullable(),
+            // Подзапрос может не вернуть ни одной строки
+            ExpressionBody::Selection(_) => true,
+            // Функции смещения, такие как `lag`, за границей окна дают значение по умолчанию, а без него null
+            ExpressionBody::WindowFunctionCall(function, arguments, _) => {
+                (!function.is_aggregate && !arguments.is_empty() && arguments.len() < function.arguments.len())
+                    || arguments.iter().any(|argument| argument.is_nullable())
+            }
+            ExpressionBody::Literal(lit) => lit.is_null(),
+            ExpressionBody::PostfixUnaryOperation(_, _) |
+            ExpressionBody::Set(_) |
+            ExpressionBody::FunctionCall(_, _) |
+            ExpressionBody::StdFunctionCall(_, _) => false,
+        }
+    }
     pub fn is_lite_weight(&self) -> bool {
         match &self.body {
             ExpressionBody::Literal(_) => true,
@@ -935,8 +1576,118 @@ impl Expression {
                 function.is_lite_weight
                     && expressions.iter().all(|expr| expr.is_lite_weight())
             }
+            ExpressionBody::Case(branches, else_branch) => {
+                branches.iter().all(|(condition, value)| condition.is_lite_weight() && value.is_lite_weight())
+                    && else_branch.iter().all(|value| value.is_lite_weight())
+            }
+            ExpressionBody::Cast(expr, _) => {
+                expr.is_lite_weight()
+            }
+            ExpressionBody::Selection(_) => true,
+            ExpressionBody::WindowFunctionCall(function, expressions, window) => {
+                function.is_lite_weight
+                    && expressions.iter().all(|expr| expr.is_lite_weight())
+                    && window.expressions().all(|expr| expr.is_lite_weight())
+            }
+        }
+    }
+    /// Проверяет, что выражение можно вычислить без обращения к базе данных
+    pub fn check_constant(&self) -> Result<(), SemanticError> {
+        match &self.body {
+            ExpressionBody::Literal(_) => Ok(()),
+            ExpressionBody::BinaryOperation(left, op, right) => {
+                match op {
+                    BinaryOperator::XOr |
+                    BinaryOperator::IsIn |
+                    BinaryOperator::Like |
+                    BinaryOperator::SoundsLike |
+                    BinaryOperator::RegExp |
+                    BinaryOperator::Div |
+                    BinaryOperator::Interval => {
+                        return Err(SemanticError::not_allowed_inside(self.pos, op.get_description(), "constant"));
+                    }
+                    _ => {}
+                }
+                left.check_constant()?;
+                right.check_constant()
+            }
+            ExpressionBody::PrefixUnaryOperation(op, expr) => {
+                match op {
+                    PrefixUnaryOperator::Not |
+                    PrefixUnaryOperator::Plus |
+                    PrefixUnaryOperator::Minus |
+                    PrefixUnaryOperator::Tilde => expr.check_constant(),
+                    _ => Err(SemanticError::not_allowed_inside(self.pos, op.get_description(), "constant")),
+                }
+            }
+            ExpressionBody::PostfixUnaryOperation(op, _) => {
+                Err(SemanticError::not_allowed_inside(self.pos, op.get_description(), "constant"))
+            }
+            ExpressionBody::Variable(_) |
+            ExpressionBody::PropertyAccess(_, _) => {
+                Err(SemanticError::not_allowed_inside(self.pos, "variable", "constant"))
+            }
+            ExpressionBody::Set(_) => {
+                Err(SemanticError::not_allowed_inside(self.pos, "set", "constant"))
+            }
+            ExpressionBody::FunctionCall(_, _) |
+            ExpressionBody::StdFunctionCall(_, _) |
+            ExpressionBody::WindowFunctionCall(_, _, _) => {
+                Err(SemanticError::not_allowed_inside(self.pos, "function call", "constant"))
+            }
+            ExpressionBody::Case(_, _) => {
+                Err(SemanticError::not_allowed_inside(self.pos, "conditional expression", "constant"))
+            }
+            ExpressionBody::Cast(_, _) => {
+                Err(SemanticError::not_allowed_inside(self.pos, "type cast", "constant"))
+            }
+            ExpressionBody::Selection(_) => {
+                Err(SemanticError::not_allowed_inside(self.pos, "selection", "constant"))
+            }
+        }
+    }
+    /// Целочисленное деление T-SQL отбрасывает дробную часть, а в TypeScript его приходится записывать явно
+    fn is_ts_integer_division(&self) -> bool {
+        match (&self.body, self.data_type.as_primitive()) {
+            (
+                ExpressionBody::BinaryOperation(_, BinaryOperator::Divide, _),
+                Some(PrimitiveDataType::Number(NumberType::Integer { .. })),
+            ) => true,
+            _ => false,
+        }
+    }
+    /// Выводит значение константы в виде выражения TypeScript
+    pub fn fmt_ts_constant(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        match &self.body {
+            ExpressionBody::Literal(lit) => lit.fmt_ts(f),
+            ExpressionBody::BinaryOperation(left, op, right) if self.is_ts_integer_division() => {
+                f.write_str("Math.trunc(")?;
+                left.fmt_ts_operand(f, Operation::Binary(*op), OperandSide::Left)?;
+                f.write_str(" / ")?;
+                right.fmt_ts_operand(f, Operation::Binary(*op), OperandSide::Right)?;
+                f.write_char(')')
+            }
+            ExpressionBody::BinaryOperation(left, op, right) => {
+                left.fmt_ts_operand(f, self.operation(), OperandSide::Left)?;
+                write!(f, " {} ", Dialect::TypeScript.binary_operator(*op))?;
+                right.fmt_ts_operand(f, self.operation(), OperandSide::Right)
+            }
+            ExpressionBody::PrefixUnaryOperation(op, expr) => {
+                write!(f, "{} ", op.get_operator())?;
+                expr.fmt_ts_operand(f, self.operation(), OperandSide::Right)
+            }
+            _ => unreachable!("Constants should be checked by Expression::check_constant"),
         }
     }
+    fn fmt_ts_operand(&self, f: &mut impl fmt::Write, parent: Operation, side: OperandSide) -> fmt::Result {
+        let operation = if self.is_ts_integer_division() { Operation::Atom } else { self.operation() };
+        if !Dialect::TypeScript.needs_parentheses(parent, operation, side) {
+            return self.fmt_ts_constant(f);
+        }
+        f.write_char('(')?;
+        self.fmt_ts_constant(f)?;
+        f.write_char(')')
+    }
     pub fn get_property(&self, path: Path) -> Option<Expression> {
         if path.is_empty() {
             return Some(self.clone());
@@ -1108,6 +1859,12 @@ impl Expression {
             .property_type(ItemPosition::default(), path.clone())
             .expect("Property existing should be already checked at generate-time");
         if let ExpressionBody::Variable(var) = &expr.body {
+            if property_data_type.as_primitive().is_some() {
+                if let Some(pseudo_table) = context.get_pseudo_table(var) {
+                    write!(f, "[{}].", pseudo_table)?;
+                    return f.write_str(&path_buf.data);
+                }
+            }
             let var_guard = var.read();
             if property_data_type.as_primitive().is_some() {
                 Expression::fmt_variable(f, &*var_guard, !path_buf.is_empty())?;
@@ -1115,7 +1872,7 @@ impl Expression {
             }
         }
         if let Some(sub_expr) = expr.get_property(path) {
-            return sub_expr.fmt(f, context);
+            return sub_expr.fmt_value(f, context);
         }
 
         if property_data_type.as_primitive().is_some() {
@@ -1137,6 +1894,22 @@ impl Expression {
         expr.fmt(f, context)?;
         f.write_str(" as t )")
     }
+    pub fn fmt_std_function_call(
+        f: &mut impl fmt::Write,
+        function: &StdLibFunction,
+        arguments: &[Expression],
+        context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        write!(f, "{}(", function.name)?;
+        let mut arguments = arguments.iter().peekable();
+        while let Some(argument) = arguments.next() {
+            argument.fmt_value(f, context)?;
+            if arguments.peek().is_some() {
+                f.write_str(", ")?;
+            }
+        }
+        f.write_str(")")
+    }
     pub fn fmt_function_call(
         f: &mut impl fmt::Write,
         function: &SyncRef<Item>,
@@ -1163,8 +1936,8 @@ impl Expression {
 
             while let Some(primitive) = primitives.next() {
                 match argument.get_property_or_wrap(primitive.path.as_path()) {
-                    Some(sub_expr) => sub_expr.fmt(f, context)?,
-                    None => argument.fmt(f, context)?,
+                    Some(sub_expr) => sub_expr.fmt_value(f, context)?,
+                    None => argument.fmt_value(f, context)?,
                 }
                 if arguments.peek().is_some() || primitives.peek().is_some() {
                     f.write_char(',')?;
@@ -1173,6 +1946,54 @@ impl Expression {
         }
         f.write_str(")")
     }
+    /// Операнд заключается в скобки, только если без них порядок вычисления изменится
+    fn fmt_operand(
+        &self,
+        f: &mut impl fmt::Write,
+        parent: Operation,
+        side: OperandSide,
+        context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        if self.is_predicate() && !parent.is_logical() {
+            return self.fmt_value(f, context);
+        }
+        if !Dialect::TSQL.needs_parentheses(parent, self.operation(), side) {
+            return self.fmt(f, context);
+        }
+        f.write_char('(')?;
+        self.fmt(f, context)?;
+        f.write_char(')')
+    }
+    /// Логические константы T-SQL не может использовать как условия, поэтому они записываются сравнением
+    pub fn fmt_condition(
+        &self,
+        f: &mut impl fmt::Write,
+        context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        match self.as_boolean() {
+            Some(true) => f.write_str("1 = 1"),
+            Some(false) => f.write_str("1 = 0"),
+            None => self.fmt(f, context),
+        }
+    }
+    /// Сравнения, EXISTS и прочие предикаты дают в T-SQL не значение, а условие
+    #[inline]
+    pub fn is_predicate(&self) -> bool {
+        self.operation().is_predicate()
+    }
+    /// Там, где T-SQL ожидает значение, предикат превращается в 1 или 0
+    pub fn fmt_value(
+        &self,
+        f: &mut impl fmt::Write,
+        context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        if !self.is_predicate() {
+            return self.fmt(f, context);
+        }
+        f.write_str("IIF(")?;
+        self.fmt(f, context)?;
+        f.write_str(", 1, 0)")
+    }
     pub fn fmt(
         &self,
         f: &mut impl fmt::Write,
@@ -1184,28 +2005,25 @@ impl Expression {
                 let var_guard = var.read();
                 Expression::fmt_variable_data(f, &*var_guard)
             }
+            ExpressionBody::BinaryOperation(left, BinaryOperator::Pow, right) => {
+                f.write_str("POWER(")?;
+                left.fmt_value(f, context)?;
+                f.write_str(", ")?;
+                right.fmt_value(f, context)?;
+                f.write_char(')')
+            }
             ExpressionBody::BinaryOperation(left, op, right) => {
-                f.write_str("( ")?;
-                left.fmt(f, context)?;
-                f.write_str(" ")?;
-                f.write_str(op.get_operator())?;
-                f.write_str(" ")?;
-                right.fmt(f, context)?;
-                f.write_str(" )")
+                left.fmt_operand(f, self.operation(), OperandSide::Left, context)?;
+                write!(f, " {} ", Dialect::TSQL.binary_operator(*op))?;
+                right.fmt_operand(f, self.operation(), OperandSide::Right, context)
             }
             ExpressionBody::PostfixUnaryOperation(op, expr) => {
-                f.write_str("( ")?;
-                f.write_str(op.get_operator())?;
-                f.write_str(" ")?;
-                expr.fmt(f, context)?;
-                f.write_str(" )")
+                expr.fmt_operand(f, self.operation(), OperandSide::Left, context)?;
+                write!(f, " {}", Dialect::TSQL.postfix_operator(*op))
             }
             ExpressionBody::PrefixUnaryOperation(op, expr) => {
-                f.write_str("( ")?;
-                f.write_str(op.get_operator())?;
-                f.write_str(" ")?;
-                expr.fmt(f, context)?;
-                f.write_str(" )")
+                write!(f, "{} ", Dialect::TSQL.prefix_operator(*op))?;
+                expr.fmt_operand(f, self.operation(), OperandSide::Right, context)
             }
             ExpressionBody::PropertyAccess(expr, path) => {
                 Expression::fmt_property_access(
@@ -1222,7 +2040,7 @@ impl Expression {
                 } else {
                     let max_i = expressions.len() - 1;
                     for (i, expression) in expressions.iter().enumerate() {
-                        expression.fmt(f, context)?;
+                        expression.fmt_value(f, context)?;
                         write!(f, " as component{}", i)?;
                         if i < max_i {
                             f.write_str(", ")?;
@@ -1258,15 +2076,50 @@ impl Expression {
                 }
             }
             ExpressionBody::StdFunctionCall(function, arguments) => {
-                write!(f, "{}(", function.name)?;
-                let mut arguments = arguments.iter().peekable();
-                while let Some(argument) = arguments.next() {
-                    argument.fmt(f, context)?;
-                    if arguments.peek().is_some() {
-                        f.write_str(", ")?;
+                Expression::fmt_std_function_call(f, function, arguments, context)
+            }
+            ExpressionBody::WindowFunctionCall(function, arguments, window) => {
+                Expression::fmt_std_function_call(f, function, arguments, context)?;
+                f.write_char(' ')?;
+                window.fmt(f, context)
+            }
+            ExpressionBody::Case(branches, else_branch) => {
+                f.write_str("CASE")?;
+                for (condition, value) in branches.iter() {
+                    f.write_str(" WHEN ")?;
+                    condition.fmt_condition(f, context)?;
+                    f.write_str(" THEN ")?;
+                    value.fmt_value(f, context)?;
+                }
+                if let Some(else_branch) = else_branch {
+                    f.write_str(" ELSE ")?;
+                    else_branch.fmt_value(f, context)?;
+                }
+                f.write_str(" END")
+            }
+            ExpressionBody::Cast(expr, is_try) => {
+                let primitive = self.data_type.as_primitive()
+                    .expect("Explicit casts should be checked by DataType::can_cast_explicitly");
+                f.write_str(if *is_try { "TRY_CAST(" } else { "CAST(" })?;
+                expr.fmt_value(f, context)?;
+                write!(f, " AS {})", TSQL(&primitive, context.parameters.clone()))
+            }
+            ExpressionBody::Selection(query) => {
+                // Запрос выводится в одну строку, чтобы не нарушать вывод окружающего выражения
+                let mut text = String::new();
+                {
+                    let mut formatter = CodeFormatter::new(&mut text);
+                    formatter.indent_size = 0;
+                    query.fmt(formatter.root_block(), context)?;
+                }
+                f.write_char('(')?;
+                for (i, line) in text.lines().enumerate() {
+                    if i > 0 {
+                        f.write_char(' ')?;
                     }
+                    f.write_str(line.trim())?;
                 }
-                f.write_str(")")
+                f.write_char(')')
             }
         }
     }
@@ -0,0 +1,236 @@
+use helpers::SyncRef;
+use language::{
+    BinaryOperator,
+    Expression,
+    ExpressionBody,
+    KeywordLiteralType,
+    Literal,
+    LiteralType,
+    PrefixUnaryOperator,
+};
+use lexeme_scanner::ItemPosition;
+use project_analysis::{
+    FunctionVariableScope,
+    SemanticError,
+};
+
+impl Literal {
+    pub fn boolean(pos: ItemPosition, value: bool) -> Self {
+        Literal {
+            literal_type: LiteralType::KeywordLiteral(match value {
+                true => KeywordLiteralType::True,
+                false => KeywordLiteralType::False,
+            }),
+            text: value.to_string(),
+            pos,
+        }
+    }
+    pub fn integer(pos: ItemPosition, value: i64) -> Self {
+        Literal {
+            literal_type: LiteralType::NumberLiteral {
+                negative: value < 0,
+                fractional: false,
+                radix: 10,
+                approx_value: value as f64,
+            },
+            text: value.to_string(),
+            pos,
+        }
+    }
+    pub fn as_boolean(&self) -> Option<bool> {
+        match &self.literal_type {
+            LiteralType::KeywordLiteral(KeywordLiteralType::True) => Some(true),
+            LiteralType::KeywordLiteral(KeywordLiteralType::False) => Some(false),
+            _ => None,
+        }
+    }
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        self.literal_type == LiteralType::KeywordLiteral(KeywordLiteralType::Null)
+    }
+    /// Сворачиваются только десятичные целые, значение которых точно известно
+    pub fn as_integer(&self) -> Option<i64> {
+        match &self.literal_type {
+            LiteralType::NumberLiteral { fractional: false, radix: 10, .. } => self.text.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl Expression {
+    #[inline]
+    pub fn as_boolean(&self) -> Option<bool> {
+        match &self.body {
+            ExpressionBody::Literal(lit) => lit.as_boolean(),
+            _ => None,
+        }
+    }
+    #[inline]
+    pub fn as_integer(&self) -> Option<i64> {
+        match &self.body {
+            ExpressionBody::Literal(lit) => lit.as_integer(),
+            _ => None,
+        }
+    }
+    /// Выражение можно убрать или вычислить заранее, если оно не вызывает функций
+    pub fn is_pure(&self) -> bool {
+        match &self.body {
+            ExpressionBody::Literal(_) |
+            ExpressionBody::Variable(_) => true,
+            ExpressionBody::BinaryOperation(left, _, right) => left.is_pure() && right.is_pure(),
+            ExpressionBody::PostfixUnaryOperation(_, expr) |
+            ExpressionBody::PrefixUnaryOperation(_, expr) |
+            ExpressionBody::PropertyAccess(expr, _) |
+            ExpressionBody::Cast(expr, _) => expr.is_pure(),
+            ExpressionBody::Set(expressions) => expressions.iter().all(|expr| expr.is_pure()),
+            ExpressionBody::Case(branches, else_branch) => {
+                branches.iter().all(|(condition, value)| condition.is_pure() && value.is_pure())
+                    && else_branch.iter().all(|value| value.is_pure())
+            }
+            ExpressionBody::FunctionCall(_, _) |
+            ExpressionBody::StdFunctionCall(_, _) |
+            ExpressionBody::WindowFunctionCall(_, _, _) |
+            ExpressionBody::Selection(_) => false,
+        }
+    }
+    /// Заменяет выражение литералом, сохраняя его позицию и тип
+    fn replace_with_literal(self, literal: Literal) -> Self {
+        Expression {
+            body: ExpressionBody::Literal(literal),
+            ..self
+        }
+    }
+    /// Заменяет выражение его частью, сохраняя позицию и тип всего выражения
+    fn replace_with(self, expr: Expression) -> Self {
+        Expression {
+            body: expr.body,
+            ..self
+        }
+    }
+    /**
+        Упрощает выражение, вычисляя то, что известно на этапе компиляции.
+        Операнды к этому моменту уже должны быть упрощены.
+    */
+    pub fn fold(self) -> Self {
+        let pos = self.pos;
+        let replacement = match &self.body {
+            ExpressionBody::BinaryOperation(left, op, right) => {
+                Expression::fold_binary_operation(pos, left, *op, right)
+            }
+            ExpressionBody::PrefixUnaryOperation(op, expr) => {
+                Expression::fold_prefix_unary_operation(pos, *op, expr)
+            }
+            ExpressionBody::Case(branches, else_branch) => {
+                Expression::fold_case(branches, else_branch).map(Err)
+            }
+            _ => None,
+        };
+        match replacement {
+            Some(Ok(literal)) => self.replace_with_literal(literal),
+            Some(Err(expr)) => self.replace_with(expr),
+            None => self,
+        }
+    }
+    fn fold_binary_operation(
+        pos: ItemPosition,
+        left: &Expression,
+        op: BinaryOperator,
+        right: &Expression,
+    ) -> Option<Result<Literal, Expression>> {
+        if let (Some(left), Some(right)) = (left.as_integer(), right.as_integer()) {
+            let value = match op {
+                BinaryOperator::Plus => left.checked_add(right),
+                BinaryOperator::Minus => left.checked_sub(right),
+                BinaryOperator::Times => left.checked_mul(right),
+                BinaryOperator::Mod => left.checked_rem(right),
+                BinaryOperator::Equals => return Some(Ok(Literal::boolean(pos, left == right))),
+                BinaryOperator::MoreThanOrEquals => return Some(Ok(Literal::boolean(pos, left >= right))),
+                BinaryOperator::MoreThan => return Some(Ok(Literal::boolean(pos, left > right))),
+                BinaryOperator::LessThanOrEquals => return Some(Ok(Literal::boolean(pos, left <= right))),
+                BinaryOperator::LessThan => return Some(Ok(Literal::boolean(pos, left < right))),
+                _ => None,
+            };
+            return value.map(|value| Ok(Literal::integer(pos, value)));
+        }
+        match op {
+            BinaryOperator::And | BinaryOperator::Or => {
+                // Для "и" поглощающим значением является ложь, для "или" - истина
+                let absorbing = op == BinaryOperator::Or;
+                for (constant, other) in [(left, right), (right, left)].iter() {
+                    match constant.as_boolean() {
+                        Some(value) if value == absorbing => if other.is_pure() {
+                            return Some(Ok(Literal::boolean(pos, absorbing)));
+                        },
+                        Some(_) => return Some(Err((*other).clone())),
+                        None => {}
+                    }
+                }
+                None
+            }
+            // Сравнение с null даёт null, поэтому сворачиваются только одинаковые литералы, отличные от null
+            BinaryOperator::Equals => match (&left.body, &right.body) {
+                (ExpressionBody::Literal(left), ExpressionBody::Literal(right))
+                if !left.is_null() && left.literal_type == right.literal_type && left.text == right.text => {
+                    Some(Ok(Literal::boolean(pos, true)))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+    fn fold_prefix_unary_operation(
+        pos: ItemPosition,
+        op: PrefixUnaryOperator,
+        expr: &Expression,
+    ) -> Option<Result<Literal, Expression>> {
+        match op {
+            PrefixUnaryOperator::Not => {
+                if let Some(value) = expr.as_boolean() {
+                    return Some(Ok(Literal::boolean(pos, !value)));
+                }
+            }
+            PrefixUnaryOperator::Minus => {
+                if let Some(value) = expr.as_integer() {
+                    return value.checked_neg()
+                        .map(|value| Ok(Literal::integer(pos, value)));
+                }
+            }
+            _ => return None,
+        }
+        // Двойное отрицание взаимно уничтожается
+        match &expr.body {
+            ExpressionBody::PrefixUnaryOperation(inner_op, inner) if *inner_op == op => {
+                Some(Err((**inner).clone()))
+            }
+            _ => None,
+        }
+    }
+    /// Выбирает ветвь, если её условие заранее известно как истинное, а все предыдущие - как ложные
+    fn fold_case(branches: &[(Expression, Expression)], else_branch: &Option<Box<Expression>>) -> Option<Expression> {
+        for (condition, value) in branches.iter() {
+            match condition.as_boolean() {
+                Some(true) => return Some(value.clone()),
+                Some(false) => {}
+                None => return None,
+            }
+        }
+        else_branch.as_ref().map(|value| (**value).clone())
+    }
+    /**
+        Проверяет условие ветвления или фильтрации на постоянство.
+        О постоянном условии сообщается предупреждением, а его значение возвращается.
+    */
+    pub fn check_constant_condition(&self, scope: &SyncRef<FunctionVariableScope>) -> Option<bool> {
+        let value = self.as_boolean()?;
+        scope.module().warn(SemanticError::constant_condition(self.pos, value));
+        Some(value)
+    }
+    /// Всегда истинный фильтр не нужен вовсе
+    pub fn fold_filter(filter: Option<Expression>, scope: &SyncRef<FunctionVariableScope>) -> Option<Expression> {
+        let filter = filter?;
+        match filter.check_constant_condition(scope) {
+            Some(true) => None,
+            _ => Some(filter),
+        }
+    }
+}
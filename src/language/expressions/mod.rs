@@ -58,26 +58,64 @@
         | "**"
         | ".."
         
+    case =
+        | "case" ("when" expression "then" expression)+ ["else" expression] "end"
+        | "if" expression "then" expression "else" expression
+
+    cast =
+        | "cast" "(" expression "," data_type ")"
+        | "try_cast" "(" expression "," data_type ")"
+
+    window =
+        "over" "(" ["partition" "by" comma_list(expression)] [select_sorting("order")] ")"
+
     expression =
         | literal
+        | case
+        | cast
+        | "(" selection ")"
         | expression "." property_path
         | expression "(" comma_list(expression) ")"
         | expression module_path "(" comma_list(expression) ")"
+        | module_path "(" comma_list(expression) ")" window
+        | identifier "::" module_path
         | prefix_unary_operator expression
         | expression postfix_unary_operator
         | expression binary_operator expression
     ```
 
+    Ветви условного выражения должны приводиться к типу одной из них, он и становится типом всего выражения.
+    Без ветви `else` выражение может вернуть `null`.
+
+    Явное приведение допускает сужение чисел и преобразования между числами, датами и строками.
+    `try_cast` при неудачном преобразовании даёт `null` вместо ошибки.
+
+    Подзапрос в роли значения, а также справа от `is in` или сравнения с `all` и `any`, должен возвращать одну колонку.
+    Под `exists` подзапрос может возвращать любые колонки.
+    Внутри подзапроса доступны переменные внешних запросов.
+
+    С окном вызываются оконные функции стандартной библиотеки (`row_number`, `rank`, `lag`) и агрегатные функции.
+    Такой вызов допустим только в списке результатов выборки и не делает запрос группирующим.
+    Ранжирующие функции и `lag` требуют сортировки в окне, а `lag` дополнительно принимает смещение и значение по умолчанию: `lag(x, 2, 0)`.
+    Агрегатные функции внутри окна допустимы только в запросе с `group by`.
+
     Правило `expression` определено в модуле `language::expressions`.
 
     Правила `property_path` и `module_path` определены в модуле `language::others`.
 
+    Правило `data_type` определено в модуле `language::data_types`.
+
+    Правила `selection` и `select_sorting` определены в модуле `language::selections`.
+
     Правила `comma_list` и `token` определены в модуле `parser_basics`.
 */
 
 pub use self::definitions::*;
+pub use self::precedence::*;
 pub use self::rules::*;
 
 pub mod definitions;
+pub mod folding;
+pub mod precedence;
 pub mod rules;
 
@@ -0,0 +1,228 @@
+//! Приоритеты операций в целевых языках. По ним при выводе выражений расставляются только необходимые скобки.
+
+use language::{
+    BinaryOperator,
+    Expression,
+    ExpressionBody,
+    PostfixUnaryOperator,
+    PrefixUnaryOperator,
+};
+
+/// Вид операции, от которого зависит необходимость скобок вокруг выражения
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Atom,
+    Binary(BinaryOperator),
+    Prefix(PrefixUnaryOperator),
+    Postfix(PostfixUnaryOperator),
+}
+
+impl Operation {
+    /// Сравнения, EXISTS и прочие предикаты дают в T-SQL не значение, а условие
+    pub fn is_predicate(&self) -> bool {
+        match self {
+            Operation::Binary(op) => match op {
+                BinaryOperator::Or |
+                BinaryOperator::XOr |
+                BinaryOperator::And |
+                BinaryOperator::IsIn |
+                BinaryOperator::Equals |
+                BinaryOperator::MoreThanOrEquals |
+                BinaryOperator::MoreThan |
+                BinaryOperator::LessThanOrEquals |
+                BinaryOperator::LessThan |
+                BinaryOperator::Like |
+                BinaryOperator::SoundsLike |
+                BinaryOperator::RegExp => true,
+                _ => false,
+            },
+            Operation::Prefix(PrefixUnaryOperator::Not) |
+            Operation::Prefix(PrefixUnaryOperator::Exists) |
+            Operation::Postfix(_) => true,
+            _ => false,
+        }
+    }
+    /// Логические операции T-SQL принимают условия, а не значения
+    pub fn is_logical(&self) -> bool {
+        match self {
+            Operation::Binary(BinaryOperator::And) |
+            Operation::Binary(BinaryOperator::Or) |
+            Operation::Prefix(PrefixUnaryOperator::Not) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Сторона, с которой операнд стоит относительно знака операции
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandSide {
+    Left,
+    Right,
+}
+
+/// Язык, в который выводится выражение. У каждого свои приоритеты операций.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    TSQL,
+    TypeScript,
+}
+
+const ATOM_PRECEDENCE: u8 = u8::max_value();
+
+impl Dialect {
+    pub fn binary_precedence(&self, op: BinaryOperator) -> u8 {
+        match self {
+            // Предикаты LIKE и IN на практике связывают сильнее AND, поэтому стоят наравне со сравнениями
+            Dialect::TSQL => match op {
+                BinaryOperator::Or => 3,
+                BinaryOperator::And => 4,
+                BinaryOperator::XOr |
+                BinaryOperator::IsIn |
+                BinaryOperator::Equals |
+                BinaryOperator::MoreThanOrEquals |
+                BinaryOperator::MoreThan |
+                BinaryOperator::LessThanOrEquals |
+                BinaryOperator::LessThan |
+                BinaryOperator::Like |
+                BinaryOperator::SoundsLike |
+                BinaryOperator::RegExp |
+                BinaryOperator::Interval => 6,
+                BinaryOperator::BitOr |
+                BinaryOperator::BitXOr |
+                BinaryOperator::BitAnd |
+                BinaryOperator::ShiftLeft |
+                BinaryOperator::ShiftRight |
+                BinaryOperator::Plus |
+                BinaryOperator::Minus => 7,
+                BinaryOperator::Times |
+                BinaryOperator::Divide |
+                BinaryOperator::Mod |
+                BinaryOperator::Div => 8,
+                // Возведение в степень выводится вызовом POWER
+                BinaryOperator::Pow => ATOM_PRECEDENCE,
+            },
+            Dialect::TypeScript => match op {
+                BinaryOperator::Or => 3,
+                BinaryOperator::And => 4,
+                BinaryOperator::BitOr => 5,
+                BinaryOperator::BitXOr => 6,
+                BinaryOperator::BitAnd => 7,
+                BinaryOperator::XOr |
+                BinaryOperator::Equals => 8,
+                BinaryOperator::IsIn |
+                BinaryOperator::MoreThanOrEquals |
+                BinaryOperator::MoreThan |
+                BinaryOperator::LessThanOrEquals |
+                BinaryOperator::LessThan |
+                BinaryOperator::Like |
+                BinaryOperator::SoundsLike |
+                BinaryOperator::RegExp |
+                BinaryOperator::Interval => 9,
+                BinaryOperator::ShiftLeft |
+                BinaryOperator::ShiftRight => 10,
+                BinaryOperator::Plus |
+                BinaryOperator::Minus => 11,
+                BinaryOperator::Times |
+                BinaryOperator::Divide |
+                BinaryOperator::Mod |
+                BinaryOperator::Div => 12,
+                BinaryOperator::Pow => 13,
+            },
+        }
+    }
+    pub fn prefix_precedence(&self, op: PrefixUnaryOperator) -> u8 {
+        match self {
+            Dialect::TSQL => match op {
+                PrefixUnaryOperator::Not => 5,
+                PrefixUnaryOperator::Plus |
+                PrefixUnaryOperator::Minus => 7,
+                _ => 9,
+            },
+            Dialect::TypeScript => 14,
+        }
+    }
+    pub fn postfix_precedence(&self, _op: PostfixUnaryOperator) -> u8 {
+        match self {
+            Dialect::TSQL => 6,
+            Dialect::TypeScript => 9,
+        }
+    }
+    pub fn precedence(&self, operation: Operation) -> u8 {
+        match operation {
+            Operation::Atom => ATOM_PRECEDENCE,
+            Operation::Binary(op) => self.binary_precedence(op),
+            Operation::Prefix(op) => self.prefix_precedence(op),
+            Operation::Postfix(op) => self.postfix_precedence(op),
+        }
+    }
+    #[inline]
+    pub fn is_right_associative(&self, op: BinaryOperator) -> bool {
+        op == BinaryOperator::Pow && *self != Dialect::TSQL
+    }
+    pub fn binary_operator(&self, op: BinaryOperator) -> &'static str {
+        match (self, op) {
+            (Dialect::TSQL, BinaryOperator::Or) => "OR",
+            // Операнды xor выводятся битовыми значениями, а для них исключающее или — это неравенство
+            (Dialect::TSQL, BinaryOperator::XOr) => "<>",
+            (Dialect::TSQL, BinaryOperator::And) => "AND",
+            (Dialect::TSQL, BinaryOperator::IsIn) => "IN",
+            (Dialect::TypeScript, BinaryOperator::Equals) => "===",
+            (Dialect::TypeScript, BinaryOperator::XOr) => "!==",
+            _ => op.get_operator(),
+        }
+    }
+    pub fn prefix_operator(&self, op: PrefixUnaryOperator) -> &'static str {
+        match (self, op) {
+            (Dialect::TSQL, PrefixUnaryOperator::Not) => "NOT",
+            _ => op.get_operator(),
+        }
+    }
+    /// В T-SQL нет проверок на истинность, поэтому битовое значение сравнивается с константой
+    pub fn postfix_operator(&self, op: PostfixUnaryOperator) -> &'static str {
+        match (self, op) {
+            (Dialect::TSQL, PostfixUnaryOperator::IsTrue) => "= 1",
+            (Dialect::TSQL, PostfixUnaryOperator::IsFalse) => "= 0",
+            (Dialect::TSQL, PostfixUnaryOperator::IsUnknown) => "is null",
+            _ => op.get_operator(),
+        }
+    }
+    /// Определяет, нужно ли заключить операнд в скобки, чтобы он не распался при обратном разборе
+    pub fn needs_parentheses(&self, parent: Operation, child: Operation, side: OperandSide) -> bool {
+        // Унарный минус слева от возведения в степень в JavaScript запрещён
+        if *self == Dialect::TypeScript && parent == Operation::Binary(BinaryOperator::Pow) && side == OperandSide::Left {
+            if let Operation::Prefix(_) = child {
+                return true;
+            }
+        }
+        let parent_precedence = self.precedence(parent);
+        let child_precedence = self.precedence(child);
+        if child_precedence != parent_precedence {
+            return child_precedence < parent_precedence;
+        }
+        match (parent, child) {
+            (_, Operation::Atom) => false,
+            // Префиксная операция слева поглотила бы правый операнд родителя
+            (Operation::Binary(_), Operation::Prefix(_)) if side == OperandSide::Left => true,
+            (Operation::Binary(op), _) => match side {
+                OperandSide::Left => self.is_right_associative(op),
+                OperandSide::Right => !self.is_right_associative(op),
+            },
+            (Operation::Prefix(_), Operation::Prefix(_)) => false,
+            (Operation::Postfix(_), Operation::Postfix(_)) => false,
+            _ => true,
+        }
+    }
+}
+
+impl Expression {
+    pub fn operation(&self) -> Operation {
+        match &self.body {
+            ExpressionBody::BinaryOperation(_, op, _) => Operation::Binary(*op),
+            ExpressionBody::PrefixUnaryOperation(op, _) => Operation::Prefix(*op),
+            ExpressionBody::PostfixUnaryOperation(op, _) => Operation::Postfix(*op),
+            // Отрицательное число ведёт себя как унарный минус
+            ExpressionBody::Literal(lit) if lit.text.starts_with('-') => Operation::Prefix(PrefixUnaryOperator::Minus),
+            _ => Operation::Atom,
+        }
+    }
+}
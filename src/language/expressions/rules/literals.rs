@@ -39,7 +39,7 @@ parser_rule!(string_literal(i) -> LiteralAST<'source> {
         ({
             let literal_type = match token.kind {
                 TokenKind::StringLiteral { length } => {
-                    LiteralType::StringLiteral { length }
+                    LiteralType::StringLiteral { length, national: false }
                 },
                 other => return i.err(ParserErrorKind::expected_got(
                     ParserErrorTokenInfo::from_kind(TokenKindLess::StringLiteral),
@@ -8,9 +8,14 @@ use parser_basics::{
 use self::binary_operations::binary_expression;
 use self::literals::literal;
 use self::others::{
+    case,
+    cast,
+    condition,
     function_call,
+    item_reference,
     property_access,
     set,
+    subquery,
 };
 use self::unary_operations::unary_operation;
 use super::*;
@@ -25,8 +30,13 @@ parser_rule!(expression_atom(i) -> ExpressionAST<'source> {
         begin: symbol_position >>
         body: alt!(
             literal => { |x| ExpressionASTBody::Literal(x) } |
+            apply!(case, expression) |
+            apply!(condition, expression) |
+            apply!(cast, expression) |
             apply!(function_call, expression) |
+            subquery |
             apply!(set, expression) |
+            item_reference |
             identifier => { |x| ExpressionASTBody::Reference(x) }
         ) >>
         pos: apply!(item_position, begin) >>
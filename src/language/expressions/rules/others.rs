@@ -1,10 +1,19 @@
 use language::{
+    data_type,
+    ItemPath,
     module_path,
     property_path,
+    select_sorting,
+    selection,
 };
+use helpers::PathBuf;
 use lexeme_scanner::ItemPosition;
 use parser_basics::{
     comma_list,
+    identifier,
+    item_position,
+    keyword,
+    symbol_position,
     Parser,
     ParserResult,
     symbols,
@@ -59,6 +68,33 @@ pub fn set<'token, 'source>(
         })
 }
 
+/// Путь из нескольких элементов, за которым не следуют аргументы, - константа (`users::MAX_AGE`) или вариант перечисления (`Status::Paid`)
+parser_rule!(pub item_reference(i) -> ExpressionASTBody<'source> {
+    do_parse!(i,
+        begin: symbol_position >>
+        enum_name: identifier >>
+        apply!(symbols, "::") >>
+        tail: module_path >>
+        pos: apply!(item_position, begin) >>
+        ({
+            let mut path = PathBuf::new("::");
+            path.push(enum_name.text());
+            path.append(tail.path.as_path());
+            ExpressionASTBody::ItemReference(ItemPath { pos, path })
+        })
+    )
+});
+
+/// "(" selection ")"
+parser_rule!(pub subquery(i) -> ExpressionASTBody<'source> {
+    do_parse!(i,
+        apply!(symbols, "(") >>
+        query: selection >>
+        apply!(symbols, ")") >>
+        (ExpressionASTBody::Selection(Box::new(query)))
+    )
+});
+
 pub fn function_call<'token, 'source>(
     input: &'token [Token<'source>],
     atom: Parser<'token, 'source, ExpressionAST<'source>>,
@@ -66,6 +102,89 @@ pub fn function_call<'token, 'source>(
     do_parse!(input,
         name: module_path >>
         args: apply!(expression_set, atom) >>
-        (ExpressionASTBody::FunctionCall(name, args))
+        window: opt!(apply!(window, atom)) >>
+        (match window {
+            Some(window) => ExpressionASTBody::WindowFunctionCall(name, args, window),
+            None => ExpressionASTBody::FunctionCall(name, args),
+        })
+    )
+}
+
+/// "over" "(" ["partition" "by" comma_list(expression)] [select_sorting("order")] ")"
+parser_rule!(window(i, atom: Parser<'token, 'source, ExpressionAST<'source>>) -> WindowAST<'source> {
+    do_parse!(i,
+        apply!(keyword, "over") >>
+        apply!(symbols, "(") >>
+        partition_by: opt!(do_parse!(
+            apply!(keyword, "partition") >>
+            apply!(keyword, "by") >>
+            items: apply!(comma_list, atom) >>
+            (items)
+        )) >>
+        order_by: opt!(apply!(select_sorting, "order")) >>
+        apply!(symbols, ")") >>
+        (WindowAST {
+            partition_by: partition_by.unwrap_or_default(),
+            order_by: order_by.unwrap_or_default(),
+        })
+    )
+});
+
+/// "case" ("when" expression "then" expression)+ ["else" expression] "end"
+pub fn case<'token, 'source>(
+    input: &'token [Token<'source>],
+    atom: Parser<'token, 'source, ExpressionAST<'source>>,
+) -> ParserResult<'token, 'source, ExpressionASTBody<'source>> {
+    do_parse!(input,
+        apply!(keyword, "case") >>
+        branches: many1!(do_parse!(
+            apply!(keyword, "when") >>
+            condition: atom >>
+            apply!(keyword, "then") >>
+            value: atom >>
+            ((condition, value))
+        )) >>
+        else_branch: opt!(do_parse!(
+            apply!(keyword, "else") >>
+            value: atom >>
+            (Box::new(value))
+        )) >>
+        apply!(keyword, "end") >>
+        (ExpressionASTBody::Case(branches, else_branch))
+    )
+}
+
+/// "if" expression "then" expression "else" expression
+pub fn condition<'token, 'source>(
+    input: &'token [Token<'source>],
+    atom: Parser<'token, 'source, ExpressionAST<'source>>,
+) -> ParserResult<'token, 'source, ExpressionASTBody<'source>> {
+    do_parse!(input,
+        apply!(keyword, "if") >>
+        condition: atom >>
+        apply!(keyword, "then") >>
+        value: atom >>
+        apply!(keyword, "else") >>
+        else_value: atom >>
+        (ExpressionASTBody::Case(vec![(condition, value)], Some(Box::new(else_value))))
+    )
+}
+
+/// ("cast" | "try_cast") "(" expression "," data_type ")"
+pub fn cast<'token, 'source>(
+    input: &'token [Token<'source>],
+    atom: Parser<'token, 'source, ExpressionAST<'source>>,
+) -> ParserResult<'token, 'source, ExpressionASTBody<'source>> {
+    do_parse!(input,
+        is_try: alt!(
+            apply!(keyword, "cast") => { |_| false }
+            | apply!(keyword, "try_cast") => { |_| true }
+        ) >>
+        apply!(symbols, "(") >>
+        expr: atom >>
+        apply!(symbols, ",") >>
+        target: data_type >>
+        apply!(symbols, ")") >>
+        (ExpressionASTBody::Cast(Box::new(expr), target, is_try))
     )
 }
@@ -16,6 +16,7 @@ use helpers::{
 use indexmap::IndexMap;
 use language::{
     AttributeAST,
+    CommonTable,
     DataType,
     DataTypeAST,
     Expression,
@@ -23,6 +24,7 @@ use language::{
     find_attribute_ast,
     Statement,
     StatementAST,
+    StatementBody,
     TableDefinition,
 };
 use lexeme_scanner::ItemPosition;
@@ -120,10 +122,32 @@ impl<'source> Resolve<(SyncRef<Module>, Vec<AttributeAST<'source>>)> for Functio
             None => (self.pos, DataType::Void),
         };
 
+        let is_transactional = find_attribute_ast(&ctx.1, "transactional").is_some();
         let body = self.body.resolve(&root)?;
 
+        let body = match body {
+            FunctionBody::Implementation(stmt) => if is_transactional {
+                let pos = stmt.pos;
+                FunctionBody::Implementation(Statement {
+                    body: StatementBody::Transaction {
+                        body: Box::new(stmt),
+                    },
+                    pos,
+                })
+            } else {
+                FunctionBody::Implementation(stmt)
+            },
+            FunctionBody::External => {
+                if is_transactional {
+                    return SemanticError::not_allowed_inside(self.pos, "transactional attribute", "external function")
+                        .into_err_vec();
+                }
+                FunctionBody::External
+            }
+        };
+
         if let FunctionBody::Implementation(body) = &body {
-            let body_jumping = body.jumping_check(StatementFlowControlPosition::new(), &result)?;
+            let body_jumping = body.jumping_check(&StatementFlowControlPosition::new(), &result)?;
             if (body_jumping != StatementFlowControlJumping::AlwaysReturns)
                 && (result != DataType::Void) {
                 return SemanticError::not_all_branches_returns(body.pos)
@@ -139,6 +163,9 @@ impl<'source> Resolve<(SyncRef<Module>, Vec<AttributeAST<'source>>)> for Functio
                 stmt.is_lite_weight()
             }
         };
+        // Несколько наборов записей может вернуть только процедура
+        let has_record_sets = result.as_record_sets().is_some();
+        let is_lite_weight = is_lite_weight && !has_record_sets;
 
         if let Some(sub_type) = result.as_array() {
             if !is_lite_weight {
@@ -160,7 +187,7 @@ impl<'source> Resolve<(SyncRef<Module>, Vec<AttributeAST<'source>>)> for Functio
                 .into_err_vec();
         }
 
-        let result_var_name = if is_lite_weight && result.as_primitive().is_some() {
+        let result_var_name = if (is_lite_weight && result.as_primitive().is_some()) || has_record_sets {
             None
         } else {
             Some(generate_name(
@@ -226,6 +253,8 @@ impl FunctionDefinition {
         context: &mut TSQLFunctionContext,
     ) -> fmt::Result {
         let is_procedure = !context.function.is_lite_weight;
+        // Процедура, возвращающая наборы записей, не имеет OUTPUT-параметров
+        let has_outputs = is_procedure && context.function.result_var_name.is_some();
         if !is_procedure {
             f.write_line('(')?;
         }
@@ -244,13 +273,14 @@ impl FunctionDefinition {
                     primitives,
                     context,
                     false,
-                    is_procedure || arguments.peek().is_some(),
+                    has_outputs || arguments.peek().is_some(),
                     false,
                 )?;
             }
         }
         if is_procedure {
-            let table = if context.function.result.can_be_table() {
+            let table = if context.function.result.can_be_table()
+                && context.function.result.as_record_sets().is_none() {
                 context.function.result.as_table_type(context.make_result_variable_prefix())
             } else {
                 None
@@ -363,7 +393,7 @@ impl FunctionDefinition {
             FunctionDefinition::fmt_variable(sub_f.clone(), context, &*variable_guard)?;
         }
 
-        {
+        let ends_with_return = {
             let array;
             let statements = if let Some(statements) = body.as_block() {
                 statements
@@ -376,7 +406,11 @@ impl FunctionDefinition {
                 context,
                 statements,
             )?;
-        }
+            match statements.last() {
+                Some(Statement { body: StatementBody::Return { value: Some(_) }, .. }) => true,
+                _ => false,
+            }
+        };
 
         if context.function.result == DataType::Void {
             if let Some(var_name) = &context.function.result_var_name {
@@ -384,26 +418,41 @@ impl FunctionDefinition {
             }
         }
         if context.function.is_lite_weight {
-            if context.function.result.as_primitive().is_some() {
-                sub_f.write_line("RETURN 0;")?;
-            } else {
-                sub_f.write_line("RETURN;")?;
+            // T-SQL требует, чтобы функция завершалась оператором RETURN
+            if !ends_with_return {
+                if context.function.result.as_primitive().is_some() {
+                    sub_f.write_line("RETURN 0;")?;
+                } else {
+                    sub_f.write_line("RETURN;")?;
+                }
             }
         }
 
         f.write_line("END")
     }
+    pub fn argument_primitives(&self) -> Vec<FieldPrimitive> {
+        let mut result = Vec::new();
+        for (argument_name, argument) in &self.arguments {
+            let argument_guard = argument.read();
+            let argument_data_type = argument_guard.data_type()
+                .expect("Variable's data-type should not be unknown at generate-time");
+            let mut prefix = PathBuf::new("#");
+            prefix.push(&*argument_name);
+            argument_data_type.make_primitives(prefix, &mut result);
+        }
+        result
+    }
     pub fn fmt_export(
         &self,
         f: &mut SimpleFormatter,
         module_path: Path,
     ) -> fmt::Result {
-        writeln!(f, "export function {}(", self.name)?;
+        writeln!(f, "export async function {}(", self.name)?;
 
         // Arguments
         {
             let mut args_f = f.sub_block();
-            writeln!(args_f, "_pool: _mssql.ConnectionPool,")?;
+            writeln!(args_f, "_connection: Connection,")?;
             for (argument_name, argument) in &self.arguments {
                 write!(args_f, "{}: ", argument_name)?;
                 let argument_guard = argument.read();
@@ -424,148 +473,158 @@ impl FunctionDefinition {
             let mut body_f = f.sub_block();
 
             // Binding arguments into the query
-            writeln!(body_f, "const _req = new _mssql.Request(_pool)")?;
-            for (argument_name, argument) in &self.arguments {
-                let argument_guard = argument.read();
-                let argument_data_type = argument_guard.data_type()
-                    .expect("Variable's data-type should not be unknown at generate-time");
-                let mut prefix = PathBuf::new("#");
-                prefix.push(&*argument_name);
-                for primitive in argument_data_type.primitives(prefix) {
-                    let data_path = primitive.path.as_path().into_new_buf(".");
-                    write!(body_f, "_req.input('{target}', _mssql.", target = primitive.path)?;
-                    primitive.field_type.fmt_ts_mssql(&mut body_f)?;
-                    writeln!(body_f, ", {source})", source = data_path)?;
-                }
+            writeln!(body_f, "const _req = new _mssql.Request(_connection)")?;
+            for primitive in self.argument_primitives() {
+                let data_path = primitive.path.as_path().into_new_buf(".");
+                write!(body_f, "_req.input('{target}', _mssql.", target = primitive.path)?;
+                primitive.field_type.fmt_ts_mssql(&mut body_f)?;
+                writeln!(body_f, ", {source})", source = data_path)?;
             }
 
-
-            if self.is_lite_weight {
-                // Arguments of function for call expression
-                let arguments = {
-                    let mut buffer = String::new();
-                    let mut arguments = self.arguments.iter()
-                        .peekable();
-                    while let Some((argument_name, argument)) = arguments.next() {
-                        let argument_guard = argument.read();
-                        let argument_data_type = argument_guard.data_type()
-                            .expect("Variable's data-type should not be unknown at generate-time");
-
-                        let mut prefix = PathBuf::new("#");
-                        prefix.push(&*argument_name);
-                        let mut primitives = argument_data_type.primitives(prefix)
-                            .into_iter()
-                            .peekable();
-                        while let Some(primitive) = primitives.next() {
-                            buffer.push('@');
-                            buffer.push_str(primitive.path.data.as_str());
-                            if primitives.peek().is_some() || arguments.peek().is_some() {
-                                buffer.push_str(", ");
-                            }
-                        }
-                    }
-                    buffer
-                };
-
-                // For primitive results
-                if self.result.as_primitive().is_some() {
-                    writeln!(
-                        body_f,
-                        "return _req.query('SELECT dbo.[{module}::{name}]({args}) as result')",
-                        module = module_path,
-                        name = self.name,
-                        args = arguments,
-                    )?;
-                    let mut then_f = body_f.sub_block();
-                    writeln!(then_f, ".then(_result => {{")?;
-                    {
-                        let mut closure_f = then_f.sub_block();
-                        writeln!(closure_f, "return _result.recordset[0].result")?;
-                    }
-                    writeln!(then_f, "}})")?;
-                } else {
-                    writeln!(
-                        body_f,
-                        "return _req.query('SELECT * FROM dbo.[{module}::{name}]({args})')",
-                        module = module_path,
-                        name = self.name,
-                        args = arguments,
-                    )?;
-
-                    // Extracting result
-                    let mut then_f = body_f.sub_block();
-                    writeln!(then_f, ".then(_result => {{")?;
-                    {
-                        let mut closure_f = then_f.sub_block();
-                        let (is_array, result) = self.result.as_array()
-                            .map(|t| (true, &**t))
-                            .unwrap_or_else(|| (false, &self.result));
-                        writeln!(closure_f, "return _result.recordset.map(record => {{")?;
-                        {
-                            // Binding results
-                            let mut sub_closure_f = closure_f.sub_block();
-                            sub_closure_f.write_str("return ")?;
-                            result.fmt_result_bind(
-                                &mut sub_closure_f,
-                                "record",
-                                Path::new("", "#"),
-                            )?;
-                            writeln!(sub_closure_f, "")?;
-                        }
-
-                        // Recordset is always an array
-                        // but if needs a one item, not an array
-                        // then we extract the first item and return it
-                        writeln!(closure_f, "}}){}", if is_array {
-                            ""
-                        } else {
-                            "[0]"
-                        })?;
-                    }
-                    writeln!(then_f, "}})")?;
-                }
-            } else {
-                // Binding result of procedure
-                let mut prefix = PathBuf::new("#");
-                let result_variable_name = self.result_var_name.as_ref()
-                    .expect("Procedures should know their return-value name.");
-                prefix.push(result_variable_name);
-                for primitive in self.result.primitives(prefix) {
+            // Binding result of procedure
+            if !self.is_lite_weight && self.result_var_name.is_some() {
+                for primitive in self.result.primitives(self.make_result_prefix()) {
                     write!(body_f, "_req.output('{}', _mssql.", primitive.path)?;
                     primitive.field_type.fmt_ts_mssql(&mut body_f)?;
                     writeln!(body_f, ")")?;
                 }
+            }
 
-                // Calling procedure
-                writeln!(
-                    body_f,
-                    "return _req.execute('dbo.[{module}::{name}]')",
-                    module = module_path,
-                    name = self.name,
-                )?;
+            writeln!(body_f, "try {{")?;
+            {
+                let mut try_f = body_f.sub_block();
+                self.fmt_export_call(&mut try_f, module_path)?;
+            }
+            // Driver's errors are mapped into the NLangError hierarchy
+            writeln!(body_f, "}} catch (_error) {{")?;
+            writeln!(body_f.sub_block(), "throw NLangError.wrap(_error)")?;
+            writeln!(body_f, "}}")?;
+        }
 
-                // Awaiting result
-                let mut then_f = body_f.sub_block();
-                writeln!(then_f, ".then(_result => {{")?;
+        // End of function
+        writeln!(f, "}}")
+    }
+    fn make_result_prefix(&self) -> PathBuf {
+        let mut prefix = PathBuf::new("#");
+        let result_variable_name = self.result_var_name.as_ref()
+            .expect("Procedures should know their return-value name.");
+        prefix.push(result_variable_name);
+        prefix
+    }
+    fn fmt_export_records_bind(
+        &self,
+        f: &mut SimpleFormatter,
+        records: &str,
+    ) -> fmt::Result {
+        let (is_array, result) = self.result.as_array()
+            .map(|t| (true, &**t))
+            .unwrap_or_else(|| (false, &self.result));
+        writeln!(f, "return {}.map(record => {{", records)?;
+        {
+            // Binding results
+            let mut closure_f = f.sub_block();
+            closure_f.write_str("return ")?;
+            result.fmt_result_bind(
+                &mut closure_f,
+                "record",
+                Path::new("", "#"),
+            )?;
+            writeln!(closure_f, "")?;
+        }
 
+        // Recordset is always an array
+        // but if needs a one item, not an array
+        // then we extract the first item and return it
+        writeln!(f, "}}){}", if is_array {
+            ""
+        } else {
+            "[0]"
+        })
+    }
+    fn fmt_export_record_sets_bind(
+        f: &mut SimpleFormatter,
+        record_sets: &[&DataType],
+        sets: &str,
+    ) -> fmt::Result {
+        writeln!(f, "return [")?;
+        {
+            let mut sets_f = f.sub_block();
+            for (i, item_data_type) in record_sets.iter().enumerate() {
+                writeln!(sets_f, "{}[{}].map(record => (", sets, i)?;
                 {
-                    // Binding result
-                    let mut closure_f = then_f.sub_block();
-                    write!(closure_f, "return ")?;
-                    self.result.fmt_result_bind(
+                    let mut closure_f = sets_f.sub_block();
+                    item_data_type.fmt_result_bind(
                         &mut closure_f,
-                        "_result.output",
-                        Path::new(result_variable_name, "#"),
+                        "record",
+                        Path::new("", "#"),
                     )?;
                     writeln!(closure_f, "")?;
                 }
-
-                writeln!(then_f, "}})")?;
+                writeln!(sets_f, ")),")?;
             }
         }
+        writeln!(f, "]")
+    }
+    fn fmt_export_call(
+        &self,
+        f: &mut SimpleFormatter,
+        module_path: Path,
+    ) -> fmt::Result {
+        if let Some(record_sets) = self.result.as_record_sets() {
+            writeln!(
+                f,
+                "const _result = await _req.execute('dbo.[{module}::{name}]')",
+                module = module_path,
+                name = self.name,
+            )?;
+            return FunctionDefinition::fmt_export_record_sets_bind(f, &record_sets, "_result.recordsets");
+        }
+        if !self.is_lite_weight {
+            // Calling procedure
+            writeln!(
+                f,
+                "const _result = await _req.execute('dbo.[{module}::{name}]')",
+                module = module_path,
+                name = self.name,
+            )?;
 
-        /// End of function
-        writeln!(f, "}}")
+            // Binding result
+            write!(f, "return ")?;
+            self.result.fmt_result_bind(
+                f,
+                "_result.output",
+                self.make_result_prefix().as_path(),
+            )?;
+            return writeln!(f, "");
+        }
+
+        // Arguments of function for call expression
+        let arguments = self.argument_primitives()
+            .into_iter()
+            .map(|primitive| format!("@{}", primitive.path))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        // For primitive results
+        if self.result.as_primitive().is_some() {
+            writeln!(
+                f,
+                "const _result = await _req.query('SELECT dbo.[{module}::{name}]({args}) as result')",
+                module = module_path,
+                name = self.name,
+                args = arguments,
+            )?;
+            return writeln!(f, "return _result.recordset[0].result");
+        }
+
+        writeln!(
+            f,
+            "const _result = await _req.query('SELECT * FROM dbo.[{module}::{name}]({args})')",
+            module = module_path,
+            name = self.name,
+            args = arguments,
+        )?;
+        self.fmt_export_records_bind(f, "_result.recordset")
     }
 }
 
@@ -577,6 +636,22 @@ impl<'a> Generate<TSQLParameters<'a>> for FunctionDefinition {
     }
 }
 
+/// Метки T-SQL, через которые операторы управления циклом покидают вложенные циклы
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TSQLCycleLabels {
+    pub name: String,
+    pub break_label: String,
+    pub continue_label: String,
+}
+
+/// Открытый при выводе цикл
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TSQLCycle {
+    pub labels: Option<TSQLCycleLabels>,
+    /// Курсор цикла-перебора, который нужно закрыть при выходе из цикла
+    pub cursor: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct TSQLFunctionContext<'a, 'b> {
     pub function: &'a FunctionDefinition,
@@ -586,6 +661,15 @@ pub struct TSQLFunctionContext<'a, 'b> {
     // TODO Учесть пре-вызовы перед каждой вставкой выражения
     pub temp_vars_scope: SyncRef<FunctionVariableScope>,
     pub pre_calc_calls: Vec<String>,
+    pub cycles: Vec<TSQLCycle>,
+    /// Переменные со значением `@@TRANCOUNT` на входе в каждую из открытых транзакций
+    pub transactions: Vec<String>,
+    /// Общие табличные выражения следующего выводимого запроса уже объявлены в `WITH`
+    pub common_tables_declared: bool,
+    /// Запросы общих табличных выражений, которые подставляются производными таблицами
+    pub inline_common_tables: Vec<(SyncRef<CommonTable>, String)>,
+    /// Переменные источника, колонки которых в `OUTPUT` читаются из псевдотаблицы `inserted` или `deleted`
+    pub pseudo_tables: Vec<(SyncRef<FunctionVariable>, &'static str)>,
 }
 
 impl<'a, 'b> TSQLFunctionContext<'a, 'b> {
@@ -605,6 +689,11 @@ impl<'a, 'b> TSQLFunctionContext<'a, 'b> {
             function_name: None,
             temp_vars_scope,
             pre_calc_calls: Vec::new(),
+            cycles: Vec::new(),
+            transactions: Vec::new(),
+            common_tables_declared: false,
+            inline_common_tables: Vec::new(),
+            pseudo_tables: Vec::new(),
         }
     }
     pub fn make_function_name(&mut self) -> Path {
@@ -625,6 +714,58 @@ impl<'a, 'b> TSQLFunctionContext<'a, 'b> {
         }
         prefix
     }
+    pub fn enter_cycle(&mut self, label: Option<&str>) -> Option<TSQLCycleLabels> {
+        let labels = label.map(|name| TSQLCycleLabels {
+            name: name.to_string(),
+            break_label: self.names.add_name(format!("{}_break", name)),
+            continue_label: self.names.add_name(format!("{}_continue", name)),
+        });
+        self.cycles.push(TSQLCycle {
+            labels: labels.clone(),
+            cursor: None,
+        });
+        labels
+    }
+    /// Запоминает курсор самого вложенного цикла
+    pub fn set_cycle_cursor(&mut self, cursor: String) {
+        if let Some(cycle) = self.cycles.last_mut() {
+            cycle.cursor = Some(cursor);
+        }
+    }
+    #[inline]
+    pub fn leave_cycle(&mut self) {
+        self.cycles.pop();
+    }
+    /// Возвращает метки цикла, если оператор управления должен покинуть не самый вложенный цикл
+    pub fn get_outer_cycle_labels(&self, label: &str) -> Option<&TSQLCycleLabels> {
+        let mut cycles = self.cycles.iter().rev();
+        match cycles.next() {
+            Some(TSQLCycle { labels: Some(innermost), .. }) if innermost.name == label => return None,
+            _ => {}
+        }
+        cycles
+            .filter_map(|cycle| cycle.labels.as_ref())
+            .find(|labels| labels.name == label)
+    }
+    /// Возвращает курсоры циклов, вложенных в помеченный цикл `label`,
+    /// а при `is_leaving_labeled` - и курсор самого помеченного цикла
+    pub fn get_left_cycle_cursors(&self, label: &str, is_leaving_labeled: bool) -> Vec<String> {
+        let mut result = Vec::new();
+        for cycle in self.cycles.iter().rev() {
+            let is_labeled = cycle.labels.as_ref()
+                .map_or(false, |labels| labels.name == label);
+            if is_labeled && !is_leaving_labeled {
+                break;
+            }
+            if let Some(cursor) = &cycle.cursor {
+                result.push(cursor.clone());
+            }
+            if is_labeled {
+                break;
+            }
+        }
+        result
+    }
     pub fn add_pre_calc_call(&mut self, function: &SyncRef<Item>, arguments: &[Expression]) -> Result<SyncRef<FunctionVariable>, fmt::Error> {
         let result_name = self.names.add_name("t".into());
         let result_data_type = {
@@ -661,6 +802,25 @@ impl<'a, 'b> TSQLFunctionContext<'a, 'b> {
         self.pre_calc_calls.push(buffer);
         Ok(var)
     }
+    /// Возвращает запрос, который подставляется на место упоминания общего табличного выражения
+    pub fn get_inline_common_table(&self, table: &SyncRef<CommonTable>) -> Option<&str> {
+        self.inline_common_tables.iter()
+            .find(|(inline_table, _)| inline_table.is_same_ref(table))
+            .map(|(_, query)| query.as_str())
+    }
+    /// Без запроса на выражение ссылаются по имени, под которым оно объявлено в `WITH`
+    pub fn set_inline_common_table(&mut self, table: &SyncRef<CommonTable>, query: Option<String>) {
+        self.inline_common_tables.retain(|(inline_table, _)| !inline_table.is_same_ref(table));
+        if let Some(query) = query {
+            self.inline_common_tables.push((table.clone(), query));
+        }
+    }
+    /// Возвращает псевдотаблицу, под именем которой выводится переменная источника
+    pub fn get_pseudo_table(&self, var: &SyncRef<FunctionVariable>) -> Option<&'static str> {
+        self.pseudo_tables.iter()
+            .find(|(pseudo_var, _)| pseudo_var.is_same_ref(var))
+            .map(|(_, pseudo_table)| *pseudo_table)
+    }
     #[inline]
     pub fn extract_pre_calc_calls(&mut self) -> Extractor<String> {
         Extractor::new(&mut self.pre_calc_calls)
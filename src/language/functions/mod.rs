@@ -6,20 +6,25 @@
     ```md
     type_of = ":" data_type
 
+    result_type_of = ":" field_type
+
     argument = identifier type_of
 
     arguments = "(" comma_list(argument) ")"
 
     function_definition =
-        | "extern" "fn" identifier arguments [type_of]
-        | "fn" identifier arguments [type_of] block
+        | "extern" "fn" identifier arguments [result_type_of]
+        | "fn" identifier arguments [result_type_of] block
     ```
 
     Правила `identifier` и `comma_list` определены в модуле `parser_basics`.
 
     Правило `block` определено в модуле `language::statements`.
 
-    Правило `data_type` определено в модуле `language::data_types`.
+    Правила `data_type` и `field_type` определены в модуле `language::data_types`.
+
+    Результатом процедуры может быть кортеж массивов, например `(Users::entity[], {total: integer}[])`:
+    каждый массив возвращается отдельным набором записей.
 */
 
 pub use self::definitions::*;
@@ -2,6 +2,7 @@ use language::{
     block,
     data_type,
     DataTypeAST,
+    field_type,
 };
 use lexeme_scanner::Token;
 use parser_basics::{
@@ -27,13 +28,8 @@ parser_rule!(type_of(i) -> DataTypeAST<'source> {
 parser_rule!(result_type_of(i) -> DataTypeAST<'source> {
     do_parse!(i,
         apply!(symbols, ":") >>
-        data_type: data_type >>
-        is_array: opt!(apply!(symbols, "[]")) >>
-        (if is_array.is_some() {
-            data_type.array()
-        } else {
-            data_type
-        })
+        data_type: field_type >>
+        (data_type)
     )
 });
 
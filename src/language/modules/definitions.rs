@@ -5,6 +5,7 @@ use helpers::{
     Generate,
     PathBuf,
     Resolve,
+    SimpleFormatter,
     SyncRef,
     TSQL,
     TSQLParameters,
@@ -16,23 +17,31 @@ use language::{
     CompoundDataType,
     DataType,
     DataTypeAST,
+    EnumDataType,
+    Expression,
+    ExpressionAST,
     Field,
     FieldAST,
     FieldPrimitive,
     find_attribute,
+    find_attribute_ast,
     FunctionDefinitionAST,
     ItemPath,
 };
 use lexeme_scanner::ItemPosition;
 use parser_basics::Identifier;
 use project_analysis::{
+    FunctionContext,
     Item,
     Module,
     SemanticError,
     SemanticItemType,
 };
 use std::{
-    fmt,
+    fmt::{
+        self,
+        Write,
+    },
     sync::Arc,
 };
 
@@ -61,6 +70,46 @@ pub struct DataTypeDefinition {
     pub body: DataType,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumDefinitionAST<'source> {
+    pub name: Identifier<'source>,
+    pub pos: ItemPosition,
+    pub variants: Vec<Identifier<'source>>,
+}
+
+impl<'source> Resolve<(SyncRef<Module>, Vec<AttributeAST<'source>>)> for EnumDefinitionAST<'source> {
+    type Result = DataTypeDefinition;
+    type Error = SemanticError;
+    fn resolve(&self, ctx: &(SyncRef<Module>, Vec<AttributeAST<'source>>)) -> Result<Self::Result, Vec<Self::Error>> {
+        if self.variants.is_empty() {
+            return SemanticError::not_allowed_here(self.pos, "enum without variants")
+                .into_err_vec();
+        }
+        let mut variants: Vec<String> = Vec::with_capacity(self.variants.len());
+        for variant in self.variants.iter() {
+            if variants.iter().any(|name| variant == name.as_str()) {
+                return SemanticError::duplicate_definition(
+                    variant.item_pos(),
+                    variant.text().to_string(),
+                    SemanticItemType::Variant,
+                )
+                    .into_err_vec();
+            }
+            variants.push(variant.text().to_string());
+        }
+        let mut path = ctx.0.read().path().read().clone();
+        path.push(self.name.text());
+        Ok(DataTypeDefinition {
+            name: self.name.to_string(),
+            body: DataType::Enum(EnumDataType {
+                path,
+                variants: Arc::new(variants),
+                is_text: find_attribute_ast(&ctx.1, "as_text").is_some(),
+            }),
+        })
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TableDefinitionAST<'source> {
     pub name: Identifier<'source>,
@@ -166,6 +215,18 @@ impl<'a> Generate<TSQLParameters<'a>> for TableDefinition {
             )?;
         }
 
+        let mut enum_columns = Vec::new();
+        for (field_name, field) in self.body.iter() {
+            let mut prefix = PathBuf::new("#");
+            prefix.push(field_name.as_str());
+            field.field_type.make_enum_columns(prefix, &mut enum_columns);
+        }
+        for (path, enum_type) in enum_columns {
+            let mut check = columns.line()?;
+            enum_type.fmt_check(&mut check, &path.data)?;
+            check.write(",")?;
+        }
+
         {
             let mut primary_key = columns.line()?;
             primary_key.write("PRIMARY KEY (")?;
@@ -185,6 +246,112 @@ impl<'a> Generate<TSQLParameters<'a>> for TableDefinition {
     }
 }
 
+/// Номера пользовательских ошибок T-SQL должны быть больше 50000
+pub const MIN_ERROR_NUMBER: u32 = 50001;
+
+/// Диапазон номеров, из которого ошибке выдаётся номер по умолчанию
+const DEFAULT_ERROR_NUMBER_BASE: u32 = 100_000;
+const DEFAULT_ERROR_NUMBER_RANGE: u32 = 900_000;
+
+/// Номер по умолчанию вычисляется хэшем FNV-1a от полного пути ошибки, поэтому не зависит от прочих объявлений
+fn default_error_number(path: &str) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in path.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    DEFAULT_ERROR_NUMBER_BASE + hash % DEFAULT_ERROR_NUMBER_RANGE
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorDefinitionAST<'source> {
+    pub name: Identifier<'source>,
+    pub pos: ItemPosition,
+    pub number: Option<u32>,
+}
+
+impl<'source> Resolve<SyncRef<Module>> for ErrorDefinitionAST<'source> {
+    type Result = ErrorDefinition;
+    type Error = SemanticError;
+    fn resolve(&self, ctx: &SyncRef<Module>) -> Result<Self::Result, Vec<Self::Error>> {
+        let mut path = ctx.read().path().read().clone();
+        path.push(self.name.text());
+        let number = match self.number {
+            Some(number) if number < MIN_ERROR_NUMBER => {
+                return SemanticError::not_allowed_here(self.pos, "error number less than 50001")
+                    .into_err_vec();
+            }
+            Some(number) => number,
+            None => default_error_number(&path.data),
+        };
+        Ok(ErrorDefinition {
+            name: self.name.to_string(),
+            pos: self.pos,
+            path,
+            number,
+            is_default_number: self.number.is_none(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorDefinition {
+    pub name: String,
+    pub pos: ItemPosition,
+    pub path: PathBuf,
+    pub number: u32,
+    /// Номер не указан в объявлении и вычислен хэшем пути
+    pub is_default_number: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstDefinitionAST<'source> {
+    pub name: Identifier<'source>,
+    pub pos: ItemPosition,
+    pub data_type: Option<DataTypeAST<'source>>,
+    pub value: ExpressionAST<'source>,
+}
+
+impl<'source> Resolve<SyncRef<Module>> for ConstDefinitionAST<'source> {
+    type Result = ConstDefinition;
+    type Error = SemanticError;
+    fn resolve(&self, ctx: &SyncRef<Module>) -> Result<Self::Result, Vec<Self::Error>> {
+        // Значение вычисляется в пустой области видимости, поэтому ссылаться оно может только на другие константы
+        let scope = FunctionContext::new(ctx.clone()).root();
+        let mut value = self.value.resolve(&scope)?;
+        value.check_constant()
+            .map_err(|e| vec![e])?;
+        if let Some(data_type) = &self.data_type {
+            let data_type = data_type.resolve(ctx)?;
+            value.should_cast_to_type(&data_type)
+                .map_err(|e| vec![e])?;
+            value.data_type = data_type;
+        }
+        Ok(ConstDefinition {
+            name: self.name.to_string(),
+            pos: self.pos,
+            value,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstDefinition {
+    pub name: String,
+    pub pos: ItemPosition,
+    pub value: Expression,
+}
+
+impl ConstDefinition {
+    pub fn fmt_export(&self, f: &mut SimpleFormatter) -> fmt::Result {
+        write!(f, "export const {}: ", self.name)?;
+        self.value.data_type.fmt(f)?;
+        f.write_str(" = ")?;
+        self.value.fmt_ts_constant(f)?;
+        writeln!(f, ";")
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ExternalItemTailAST<'source> {
     None,
@@ -224,16 +391,20 @@ pub struct ExternalItemImport {
 #[derive(Debug, Clone, PartialEq)]
 pub enum ModuleDefinitionValueAST<'source> {
     DataType(DataTypeDefinitionAST<'source>),
+    Enum(EnumDefinitionAST<'source>),
     Table(TableDefinitionAST<'source>),
     Function(FunctionDefinitionAST<'source>),
     Module(ModuleDefinitionAST<'source>),
     Import(ExternalItemImportAST<'source>),
+    Error(ErrorDefinitionAST<'source>),
+    Const(ConstDefinitionAST<'source>),
 }
 
 impl<'source> ModuleDefinitionValueAST<'source> {
     pub fn name(&'source self) -> &'source str {
         match self {
             ModuleDefinitionValueAST::DataType(def) => def.name.text(),
+            ModuleDefinitionValueAST::Enum(def) => def.name.text(),
             ModuleDefinitionValueAST::Import(def) => {
                 match &def.tail {
                     ExternalItemTailAST::None | &ExternalItemTailAST::Asterisk => {
@@ -249,6 +420,8 @@ impl<'source> ModuleDefinitionValueAST<'source> {
             ModuleDefinitionValueAST::Function(def) => def.name.text(),
             ModuleDefinitionValueAST::Table(def) => def.name.text(),
             ModuleDefinitionValueAST::Module(def) => def.name.text(),
+            ModuleDefinitionValueAST::Error(def) => def.name.text(),
+            ModuleDefinitionValueAST::Const(def) => def.name.text(),
         }
     }
 }
@@ -280,6 +453,10 @@ impl<'source> Resolve<SyncRef<Module>> for ModuleDefinitionItemAST<'source> {
                 ModuleDefinitionValueAST::DataType(def) => {
                     SyncRef::new(def.resolve(ctx)?)
                 }
+                ModuleDefinitionValueAST::Enum(def) => {
+                    let def = def.resolve(&(ctx.clone(), attributes.clone()))?;
+                    SyncRef::new(Item::data_type(ctx.clone(), def))
+                }
                 ModuleDefinitionValueAST::Import(
                     ExternalItemImportAST { path, tail }
                 ) => {
@@ -313,6 +490,14 @@ impl<'source> Resolve<SyncRef<Module>> for ModuleDefinitionItemAST<'source> {
                     let def = def.resolve(ctx)?;
                     SyncRef::new(Item::table(ctx.clone(), def))
                 }
+                ModuleDefinitionValueAST::Error(def) => {
+                    let def = def.resolve(ctx)?;
+                    SyncRef::new(Item::error(ctx.clone(), def))
+                }
+                ModuleDefinitionValueAST::Const(def) => {
+                    let def = def.resolve(ctx)?;
+                    SyncRef::new(Item::constant(ctx.clone(), def))
+                }
                 ModuleDefinitionValueAST::Module(_) => {
                     return SemanticError::not_supported_yet(self.position, "file-scoped modules")
                         .into_err_vec();
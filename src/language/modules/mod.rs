@@ -6,10 +6,16 @@
     ```md
     data_type_definition = "struct" identifier compound_type
 
+    enum_definition = "enum" identifier "{" comma_list(identifier) "}"
+
     table_definition = "table" identifier struct_body
 
     function_definition_in_module = function_definition
 
+    error_definition = "error" identifier ["=" u32_literal] ";"
+
+    const_definition = "const" identifier [":" data_type] "=" expression ";"
+
     module_definitions = "mod" identifier "{" module "}"
 
     external_item_definition =
@@ -19,20 +25,25 @@
 
     module_definition_item = attributes ["pub"] (
         | data_type_definition
+        | enum_definition
         | table_definition
         | function_definition_in_module
         | module_definitions
         | external_item_definition
+        | error_definition
+        | const_definition
     )
 
     module = module_definition_item*
     ```
 
-    Правила `attributes`, `compound_type, и `struct_body` определены в модуле `language::data_types`.
+    Правила `attributes`, `compound_type, `data_type` и `struct_body` определены в модуле `language::data_types`.
+
+    Правило `expression` определено в модуле `language::expressions`.
 
     Правило `module_path` определено в модуле `language::others`.
 
-    Правило `identifier` определено в модуле `parser_basics`.
+    Правила `identifier` и `u32_literal` определены в модуле `parser_basics`.
 
     Правило `function_definition` определено в модуле `language::functions`.
 */
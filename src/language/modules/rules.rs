@@ -1,6 +1,8 @@
 use language::{
     attributes,
     compound_type,
+    data_type,
+    expression,
     function_definition,
     module_path,
     struct_body,
@@ -8,6 +10,7 @@ use language::{
 use lexeme_scanner::Token;
 use nom::IResult;
 use parser_basics::{
+    comma_list,
     end_of_input,
     identifier,
     item_position,
@@ -16,6 +19,7 @@ use parser_basics::{
     ParserResult,
     symbol_position,
     symbols,
+    u32_literal,
 };
 use super::*;
 
@@ -28,6 +32,19 @@ parser_rule!(data_type_definition(i) -> ModuleDefinitionValueAST<'source> {
     )
 });
 
+parser_rule!(enum_definition(i) -> ModuleDefinitionValueAST<'source> {
+    do_parse!(i,
+        begin: symbol_position >>
+        apply!(keyword, "enum") >>
+        name: identifier >>
+        apply!(symbols, "{") >>
+        variants: apply!(comma_list, identifier) >>
+        apply!(symbols, "}") >>
+        pos: apply!(item_position, begin) >>
+        (ModuleDefinitionValueAST::Enum(EnumDefinitionAST { name, pos, variants }))
+    )
+});
+
 parser_rule!(table_definition(i) -> ModuleDefinitionValueAST<'source> {
     do_parse!(i,
         begin: symbol_position >>
@@ -39,6 +56,40 @@ parser_rule!(table_definition(i) -> ModuleDefinitionValueAST<'source> {
     )
 });
 
+parser_rule!(error_definition(i) -> ModuleDefinitionValueAST<'source> {
+    do_parse!(i,
+        begin: symbol_position >>
+        apply!(keyword, "error") >>
+        name: identifier >>
+        number: opt!(do_parse!(
+            apply!(symbols, "=") >>
+            number: u32_literal >>
+            (number)
+        )) >>
+        apply!(symbols, ";") >>
+        pos: apply!(item_position, begin) >>
+        (ModuleDefinitionValueAST::Error(ErrorDefinitionAST { name, pos, number }))
+    )
+});
+
+parser_rule!(const_definition(i) -> ModuleDefinitionValueAST<'source> {
+    do_parse!(i,
+        begin: symbol_position >>
+        apply!(keyword, "const") >>
+        name: identifier >>
+        data_type: opt!(do_parse!(
+            apply!(symbols, ":") >>
+            data_type: data_type >>
+            (data_type)
+        )) >>
+        apply!(symbols, "=") >>
+        value: expression >>
+        apply!(symbols, ";") >>
+        pos: apply!(item_position, begin) >>
+        (ModuleDefinitionValueAST::Const(ConstDefinitionAST { name, pos, data_type, value }))
+    )
+});
+
 parser_rule!(function_definition_in_module(i) -> ModuleDefinitionValueAST<'source> {
     do_parse!(i,
         def: function_definition >>
@@ -87,10 +138,13 @@ parser_rule!(module_definition_item(i) -> ModuleDefinitionItemAST<'source> {
         public: opt!(apply!(keyword, "pub")) >>
         value: alt!(
             data_type_definition
+            | enum_definition
             | table_definition
             | function_definition_in_module
             | module_definitions
             | external_item_definition
+            | error_definition
+            | const_definition
         ) >>
         position: apply!(item_position, begin) >>
         (ModuleDefinitionItemAST {
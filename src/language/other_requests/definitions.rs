@@ -14,9 +14,12 @@ use language::{
     DataType,
     Expression,
     ExpressionAST,
+    find_attribute,
     ItemPath,
     Selection,
     SelectionAST,
+    SelectionExpression,
+    SelectionResultAST,
     SelectionSortingItem,
     SelectionSortingItemAST,
     SelectionSortingOrder,
@@ -24,13 +27,17 @@ use language::{
 };
 use lexeme_scanner::ItemPosition;
 use project_analysis::{
+    FunctionVariable,
     FunctionVariableScope,
     InsertSourceContext,
     SemanticError,
 };
-use std::fmt::{
-    self,
-    Write,
+use std::{
+    fmt::{
+        self,
+        Write,
+    },
+    sync::Arc,
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -104,7 +111,7 @@ impl UpdatingAssignment {
                 write!(line, "{}", self.target.property.as_path().into_new_buf("#"))?;
             }
             line.write_str(" = ")?;
-            self.value.fmt(&mut line, context)?;
+            self.value.fmt_value(&mut line, context)?;
             if last_comma {
                 line.write_char(',')?;
             }
@@ -154,6 +161,122 @@ impl<'a, 'b, 'source> Assertion<(&'a str, Option<&'b str>)> for UpdatingAssignme
     }
 }
 
+/// Строки, затронутые запросом, которые нужно вернуть
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReturningAST<'source> {
+    pub result: SelectionResultAST<'source>,
+    pub pos: ItemPosition,
+}
+
+impl<'source> ReturningAST<'source> {
+    /// Возвращать можно только строки единственного источника, в который пишет запрос
+    pub fn resolve(
+        &self,
+        scope: &SyncRef<FunctionVariableScope>,
+        source: &DataSource,
+    ) -> Result<Returning, Vec<SemanticError>> {
+        let mut vars = Vec::new();
+        source.entity_variables(&mut vars);
+        if vars.len() != 1 {
+            return SemanticError::not_supported_yet(self.pos, "RETURNING clause for JOIN of data sources")
+                .into_err_vec();
+        }
+        let result = match &self.result {
+            SelectionResultAST::All(pos) => {
+                let var = vars.remove(0);
+                let data_type = var.data_type(*pos)?;
+                vec![SelectionExpression {
+                    expr: Expression::variable_access(var, *pos, data_type),
+                    alias: None,
+                }]
+            }
+            SelectionResultAST::Some(_) => self.result.resolve(scope)?,
+        };
+        let data_type = SelectionExpression::type_of_expression_set(result.as_slice());
+        let var = scope.new_temp_variable(self.pos, DataType::Array(Arc::new(data_type)));
+        Ok(Returning {
+            result,
+            var,
+            pos: self.pos,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Returning {
+    pub result: Vec<SelectionExpression>,
+    /// Табличная переменная, в которую выводятся строки
+    pub var: SyncRef<FunctionVariable>,
+    pub pos: ItemPosition,
+}
+
+impl Returning {
+    /// Значение запроса, которое можно присвоить переменной
+    pub fn value(&self) -> Expression {
+        let data_type = self.var.data_type(self.pos)
+            .expect("Returning variable should have known data-type");
+        Expression::variable_access(self.var.clone(), self.pos, data_type)
+    }
+    /// Перед выполнением запроса переменную нужно очистить, так как `OUTPUT INTO` дописывает строки
+    pub fn fmt_clear(&self, mut f: BlockFormatter<impl fmt::Write>) -> fmt::Result {
+        f.write_line(format_args!("DELETE FROM @{};", self.var.read().name()))
+    }
+    /// Выводит `OUTPUT ... INTO`, где колонки источника читаются из псевдотаблицы `inserted` или `deleted`
+    pub fn fmt(
+        &self,
+        mut f: BlockFormatter<impl fmt::Write>,
+        context: &mut TSQLFunctionContext,
+        source: &DataSource,
+        pseudo_table: &'static str,
+    ) -> fmt::Result {
+        let mut vars = Vec::new();
+        source.entity_variables(&mut vars);
+        context.pseudo_tables = vars.into_iter()
+            .map(|var| (var, pseudo_table))
+            .collect();
+
+        f.write_line("OUTPUT")?;
+        let mut sub_f = f.sub_block();
+        let mut result_items = self.result.iter()
+            .peekable();
+        while let Some(result_item) = result_items.next() {
+            let mut primitives = result_item.expr.data_type.primitives(PathBuf::new("."))
+                .into_iter()
+                .peekable();
+            while let Some(primitive) = primitives.next() {
+                let mut line = sub_f.line()?;
+                Expression::fmt_property_access(
+                    &mut line,
+                    &result_item.expr,
+                    primitive.path.as_path(),
+                    context,
+                )?;
+                if primitives.peek().is_some() || result_items.peek().is_some() {
+                    line.write_char(',')?;
+                }
+            }
+        }
+        context.pseudo_tables.clear();
+
+        let var_guard = self.var.read();
+        let data_type = var_guard.data_type()
+            .and_then(|data_type| data_type.as_array())
+            .expect("Returning variable should be a table");
+        let mut line = f.line()?;
+        write!(line, "INTO @{} (", var_guard.name())?;
+        let mut primitives = data_type.primitives(PathBuf::new("#"))
+            .into_iter()
+            .peekable();
+        while let Some(primitive) = primitives.next() {
+            write!(line, "[{}]", primitive.path)?;
+            if primitives.peek().is_some() {
+                line.write_str(", ")?;
+            }
+        }
+        line.write_char(')')
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct UpdatingAST<'source> {
     pub source: DataSourceAST<'source>,
@@ -161,6 +284,7 @@ pub struct UpdatingAST<'source> {
     pub where_clause: Option<ExpressionAST<'source>>,
     pub order_by_clause: Option<Vec<SelectionSortingItemAST<'source>>>,
     pub limit_clause: Option<u32>,
+    pub returning: Option<ReturningAST<'source>>,
     pub pos: ItemPosition,
 }
 
@@ -184,13 +308,17 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for UpdatingAST<'source> {
             None => return Err(errors),
         };
         let where_clause = match where_clause {
-            Some(x) => x,
+            Some(x) => Expression::fold_filter(x, scope),
             None => return Err(errors),
         };
         let order_by_clause = match order_by_clause {
             Some(x) => x,
             None => return Err(errors),
         };
+        let returning = match &self.returning {
+            Some(returning) => Some(returning.resolve(scope, &source)?),
+            None => None,
+        };
 
         Ok(Updating {
             source,
@@ -198,6 +326,7 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for UpdatingAST<'source> {
             where_clause,
             order_by_clause,
             limit_clause: self.limit_clause,
+            returning,
         })
     }
 }
@@ -209,6 +338,7 @@ pub struct Updating {
     pub where_clause: Option<Expression>,
     pub order_by_clause: Option<Vec<SelectionSortingItem>>,
     pub limit_clause: Option<u32>,
+    pub returning: Option<Returning>,
 }
 
 impl Updating {
@@ -221,6 +351,9 @@ impl Updating {
         mut f: BlockFormatter<impl fmt::Write>,
         context: &mut TSQLFunctionContext,
     ) -> fmt::Result {
+        if let Some(returning) = &self.returning {
+            returning.fmt_clear(f.clone())?;
+        }
         f.write_line("UPDATE")?;
         let mut sub_f = f.sub_block();
         let sub_sub_f = sub_f.sub_block();
@@ -240,10 +373,13 @@ impl Updating {
                 )?;
             }
         }
+        if let Some(returning) = &self.returning {
+            returning.fmt(sub_f.clone(), context, &self.source, "inserted")?;
+        }
         if let Some(where_clause) = &self.where_clause {
             let mut line = sub_f.line()?;
             line.write_str("WHERE ")?;
-            where_clause.fmt(&mut line, context)?;
+            where_clause.fmt_condition(&mut line, context)?;
         }
         if let Some(order_by_clause) = &self.order_by_clause {
             let mut line = sub_f.line()?;
@@ -260,7 +396,7 @@ impl Updating {
                 }
             }
         }
-        Ok(())
+        sub_f.write_line(';')
     }
 }
 
@@ -337,7 +473,7 @@ impl<'source, 'a> Resolve<InsertSourceContext<'a>> for InsertingSourceAST<'sourc
                 })
             }
             InsertingSourceASTBody::Selection { properties, query } => {
-                let query = query.resolve(ctx.scope)?;
+                let query = query.resolve_statement(ctx.scope)?;
                 let properties = {
                     let query_result_type: &DataType = match &query.result_data_type {
                         DataType::Array(query_result_type) => &**query_result_type,
@@ -368,6 +504,7 @@ impl<'source, 'a> Resolve<InsertSourceContext<'a>> for InsertingSourceAST<'sourc
                             let var = ctx.target.get_target_for_insert(query.pos)?;
                             let target_data_type = var.data_type(query.pos)?;
                             query_result_type.should_cast_to(query.pos, &target_data_type)?;
+                            query_result_type.should_keep_nulls_in(query.pos, &target_data_type)?;
                             let mut primitives_prefix = PathBuf::new(".");
                             primitives_prefix.push(var.read().name());
                             let properties: Result<_, _> = target_data_type.primitives(primitives_prefix)
@@ -401,6 +538,29 @@ pub enum InsertingSource {
 }
 
 impl InsertingSource {
+    #[inline]
+    pub fn properties(&self) -> &[AssignmentTarget] {
+        match self {
+            InsertingSource::ValueLists { properties, lists: _ } => properties,
+            InsertingSource::Selection { properties, query: _ } => properties,
+        }
+    }
+    /// Имена записываемых колонок без указания источника
+    pub fn column_names(target: &[AssignmentTarget]) -> Vec<String> {
+        let mut result = Vec::new();
+        for property in target.iter() {
+            let var_guard = property.var.read();
+            let primitives = var_guard.data_type()
+                .expect("Variable data-type should be known at generate time")
+                .property_type(ItemPosition::default(), property.property.as_path())
+                .expect("Property existing should be already checked at generate time.")
+                .primitives(property.property.as_path().into_new_buf("#"));
+            for primitive in primitives {
+                result.push(primitive.path.data);
+            }
+        }
+        result
+    }
     pub fn fmt_target_list(
         target: &[AssignmentTarget],
         line: &mut impl fmt::Write,
@@ -433,11 +593,21 @@ impl InsertingSource {
         &self,
         mut f: BlockFormatter<impl fmt::Write>,
         context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        if let InsertingSource::ValueLists { properties, lists: _ } = self {
+            InsertingSource::fmt_target_list(&properties, &mut f.line()?)?;
+        }
+        self.fmt_rows(f, context)
+    }
+    /// Выводит записываемые строки: список значений или запрос выборки
+    pub fn fmt_rows(
+        &self,
+        mut f: BlockFormatter<impl fmt::Write>,
+        context: &mut TSQLFunctionContext,
     ) -> fmt::Result {
         let mut sub_f = f.sub_block();
         match self {
             InsertingSource::ValueLists { properties, lists } => {
-                InsertingSource::fmt_target_list(&properties, &mut f.line()?)?;
                 f.write_line("VALUES")?;
                 let mut lists_iter = lists.iter()
                     .peekable();
@@ -506,10 +676,19 @@ impl InsertingSource {
     }
 }
 
+/// Обновление уже существующей строки с тем же первичным ключом вместо записи новой
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsertingOnConflictAST<'source> {
+    pub assignments: Vec<UpdatingAssignmentAST<'source>>,
+    pub pos: ItemPosition,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct InsertingAST<'source> {
     pub target: DataSourceAST<'source>,
     pub source: InsertingSourceAST<'source>,
+    pub on_conflict: Option<InsertingOnConflictAST<'source>>,
+    pub returning: Option<ReturningAST<'source>>,
 }
 
 impl<'source> Resolve<SyncRef<FunctionVariableScope>> for InsertingAST<'source> {
@@ -526,17 +705,218 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for InsertingAST<'source>
             self.source.resolve(&ctx)?
         };
 
+        let on_conflict = match &self.on_conflict {
+            Some(on_conflict) => Some(on_conflict.resolve(scope, &target, &source)?),
+            None => None,
+        };
+        let returning = match &self.returning {
+            Some(returning) => Some(returning.resolve(scope, &target)?),
+            None => None,
+        };
+
         Ok(Inserting {
             target,
             source,
+            on_conflict,
+            returning,
+        })
+    }
+}
+
+impl<'source> InsertingOnConflictAST<'source> {
+    pub fn resolve(
+        &self,
+        scope: &SyncRef<FunctionVariableScope>,
+        target: &DataSource,
+        source: &InsertingSource,
+    ) -> Result<InsertingOnConflict, Vec<SemanticError>> {
+        // Источник выводится внутри MERGE, куда `WITH` не подставить
+        if let InsertingSource::Selection { properties: _, query } = source {
+            if query.is_recursive() {
+                return SemanticError::not_allowed_inside(
+                    query.pos,
+                    "recursive common table expression",
+                    "source of upsert",
+                )
+                    .into_err_vec();
+            }
+        }
+        let (var, primary_key) = match target {
+            DataSource::Table { item, var } => {
+                let item_guard = item.read();
+                let table = item_guard.get_table()
+                    .expect("Table data source should refer to table");
+                // Колонку IDENTITY нельзя записать явно, а первичный ключ записью с обновлением обязан быть записан
+                let is_primary_key_auto_increment = table.body.values()
+                    .any(|field| {
+                        find_attribute(&field.attributes, "primary_key").is_some()
+                            && find_attribute(&field.attributes, "auto_increment").is_some()
+                    });
+                if is_primary_key_auto_increment {
+                    return SemanticError::not_allowed_inside(
+                        self.pos,
+                        "ON CONFLICT clause",
+                        "insert into table with auto increment primary key",
+                    )
+                        .into_err_vec();
+                }
+                let primary_key = table.primary_key.primitives(PathBuf::new("#"))
+                    .into_iter()
+                    .map(|primitive| primitive.path.data)
+                    .collect::<Vec<_>>();
+                (var.clone(), primary_key)
+            }
+            _ => return SemanticError::cannot_do_with_datasource(self.pos, "upsert into")
+                .into_err_vec(),
+        };
+        if primary_key.is_empty() {
+            return SemanticError::empty_primary_key(self.pos)
+                .into_err_vec();
+        }
+
+        let mut errors = Vec::new();
+        let columns = InsertingSource::column_names(source.properties());
+        for name in primary_key.iter() {
+            if !columns.contains(name) {
+                errors.push(SemanticError::primary_key_is_not_inserted(self.pos, name.clone()));
+            }
+        }
+
+        let assignments = self.assignments.accumulative_resolve(scope, &mut errors);
+        let assignments = match assignments {
+            Some(x) => x,
+            None => return Err(errors),
+        };
+        for assignment in assignments.iter() {
+            if !target.is_target_belongs_to_source(&assignment.target) {
+                errors.push(SemanticError::not_allowed_inside(
+                    assignment.target.pos,
+                    "assignment not belonging to the target data source",
+                    "ON CONFLICT clause",
+                ));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(InsertingOnConflict {
+            var,
+            primary_key,
+            columns,
+            assignments,
         })
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsertingOnConflict {
+    pub var: SyncRef<FunctionVariable>,
+    /// Колонки первичного ключа, по которым ищется существующая строка
+    pub primary_key: Vec<String>,
+    pub columns: Vec<String>,
+    pub assignments: Vec<UpdatingAssignment>,
+}
+
+impl InsertingOnConflict {
+    /// Выводит запись с обновлением в виде `MERGE`, так как `ON CONFLICT` в T-SQL нет.
+    /// Проект генерирует только T-SQL, поэтому вывода для других диалектов SQL нет, и запрос с `ON CONFLICT`
+    /// переносим только на SQL Server.
+    pub fn fmt(
+        &self,
+        mut f: BlockFormatter<impl fmt::Write>,
+        context: &mut TSQLFunctionContext,
+        target: &DataSource,
+        source: &InsertingSource,
+        returning: Option<&Returning>,
+    ) -> fmt::Result {
+        let source_name = context.names.add_name("source".into());
+
+        f.write_line("MERGE INTO")?;
+        let mut sub_f = f.sub_block();
+        let sub_sub_f = sub_f.sub_block();
+        // Без HOLDLOCK два параллельных запроса с одним ключом могут оба пойти в WHEN NOT MATCHED
+        match target {
+            DataSource::Table { item, var } => sub_f.write_line(format_args!(
+                "[{}] WITH (HOLDLOCK) AS [{}]",
+                item.read().get_path(),
+                var.read().name(),
+            ))?,
+            _ => unreachable!("Upsert target should be checked by InsertingOnConflictAST::resolve"),
+        }
+        f.write_line("USING (")?;
+        source.fmt_rows(sub_f.clone(), context)?;
+        {
+            let mut line = f.line()?;
+            write!(line, ") AS [{}] (", source_name)?;
+            for (i, column) in self.columns.iter().enumerate() {
+                if i > 0 {
+                    line.write_str(", ")?;
+                }
+                write!(line, "[{}]", column)?;
+            }
+            line.write_char(')')?;
+        }
+        {
+            let mut line = f.line()?;
+            line.write_str("ON ")?;
+            for (i, column) in self.primary_key.iter().enumerate() {
+                if i > 0 {
+                    line.write_str(" AND ")?;
+                }
+                Expression::fmt_variable(&mut line, &*self.var.read(), true)?;
+                write!(line, "{} = [{}].[{}]", column, source_name, column)?;
+            }
+        }
+        if !self.assignments.is_empty() {
+            f.write_line("WHEN MATCHED THEN")?;
+            sub_f.write_line("UPDATE SET")?;
+            let mut assignments = self.assignments.iter()
+                .peekable();
+            while let Some(assignment) = assignments.next() {
+                assignment.fmt(
+                    sub_sub_f.clone(),
+                    context,
+                    assignments.peek().is_some(),
+                )?;
+            }
+        }
+        f.write_line("WHEN NOT MATCHED THEN")?;
+        {
+            let mut line = sub_f.line()?;
+            line.write_str("INSERT (")?;
+            for (i, column) in self.columns.iter().enumerate() {
+                if i > 0 {
+                    line.write_str(", ")?;
+                }
+                write!(line, "[{}]", column)?;
+            }
+            line.write_char(')')?;
+        }
+        {
+            let mut line = sub_f.line()?;
+            line.write_str("VALUES (")?;
+            for (i, column) in self.columns.iter().enumerate() {
+                if i > 0 {
+                    line.write_str(", ")?;
+                }
+                write!(line, "[{}].[{}]", source_name, column)?;
+            }
+            line.write_char(')')?;
+        }
+        if let Some(returning) = returning {
+            returning.fmt(f.clone(), context, target, "inserted")?;
+        }
+        f.write_line(";")
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Inserting {
     pub target: DataSource,
     pub source: InsertingSource,
+    pub on_conflict: Option<InsertingOnConflict>,
+    pub returning: Option<Returning>,
 }
 
 impl Inserting {
@@ -549,10 +929,28 @@ impl Inserting {
         mut f: BlockFormatter<impl fmt::Write>,
         context: &mut TSQLFunctionContext,
     ) -> fmt::Result {
+        if let Some(returning) = &self.returning {
+            returning.fmt_clear(f.clone())?;
+        }
+        if let Some(on_conflict) = &self.on_conflict {
+            return on_conflict.fmt(f, context, &self.target, &self.source, self.returning.as_ref());
+        }
+        if let InsertingSource::Selection { properties: _, query } = &self.source {
+            query.fmt_with_clause(f.clone(), context)?;
+        }
         f.write_line("INSERT INTO")?;
         let sub_f = f.sub_block();
         self.target.fmt(sub_f.clone(), context, false)?;
-        self.source.fmt(sub_f, context)?;
+        match &self.returning {
+            Some(returning) => {
+                if let InsertingSource::ValueLists { properties, lists: _ } = &self.source {
+                    InsertingSource::fmt_target_list(&properties, &mut sub_f.clone().line()?)?;
+                }
+                returning.fmt(sub_f.clone(), context, &self.target, "inserted")?;
+                self.source.fmt_rows(sub_f, context)?;
+            }
+            None => self.source.fmt(sub_f, context)?,
+        }
         f.write_line(";")
     }
 }
@@ -563,6 +961,7 @@ pub struct DeletingAST<'source> {
     pub where_clause: Option<ExpressionAST<'source>>,
     pub order_by_clause: Option<Vec<SelectionSortingItemAST<'source>>>,
     pub limit_clause: Option<u32>,
+    pub returning: Option<ReturningAST<'source>>,
 }
 
 impl<'source> Resolve<SyncRef<FunctionVariableScope>> for DeletingAST<'source> {
@@ -576,19 +975,24 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for DeletingAST<'source> {
         let order_by_clause = self.order_by_clause.accumulative_resolve(scope, &mut errors);
 
         let where_clause = match where_clause {
-            Some(x) => x,
+            Some(x) => Expression::fold_filter(x, scope),
             None => return Err(errors)
         };
         let order_by_clause = match order_by_clause {
             Some(x) => x,
             None => return Err(errors)
         };
+        let returning = match &self.returning {
+            Some(returning) => Some(returning.resolve(scope, &source)?),
+            None => None,
+        };
 
         Ok(Deleting {
             source,
             where_clause,
             order_by_clause,
             limit_clause: self.limit_clause,
+            returning,
         })
     }
 }
@@ -599,6 +1003,7 @@ pub struct Deleting {
     pub where_clause: Option<Expression>,
     pub order_by_clause: Option<Vec<SelectionSortingItem>>,
     pub limit_clause: Option<u32>,
+    pub returning: Option<Returning>,
 }
 
 impl Deleting {
@@ -611,16 +1016,22 @@ impl Deleting {
         mut f: BlockFormatter<impl fmt::Write>,
         context: &mut TSQLFunctionContext,
     ) -> fmt::Result {
+        if let Some(returning) = &self.returning {
+            returning.fmt_clear(f.clone())?;
+        }
         match &self.limit_clause {
             Some(limit) => f.write_line(format_args!("DELETE TOP({}) FROM", limit))?,
             None => f.write_line("DELETE FROM")?,
         }
         let mut sub_f = f.sub_block();
         self.source.fmt(sub_f.sub_block(), context, false)?;
+        if let Some(returning) = &self.returning {
+            returning.fmt(sub_f.clone(), context, &self.source, "deleted")?;
+        }
         if let Some(where_clause) = &self.where_clause {
             let mut line = sub_f.line()?;
             line.write_str("WHERE ")?;
-            where_clause.fmt(&mut line, context)?;
+            where_clause.fmt_condition(&mut line, context)?;
         }
         sub_f.write_line(';')
     }
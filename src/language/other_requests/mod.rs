@@ -21,7 +21,9 @@
         | "set" comma_list(updating_assignment)
         | [property_list] selection
 
-    inserting_on_duplicate_key_update = "on" "duplicate" "key" "update" comma_list(updating_assignment)
+    inserting_on_conflict_update = "on" "conflict" "update" "set" comma_list(updating_assignment)
+
+    returning_clause = "returning" select_result
 
     where_clause = select_condition("where")
 
@@ -31,17 +33,26 @@
         "update" ["low_priority"] ["ignore"] data_source
         "set" comma_list(updating_assignment)
         [where_clause] [order_by_clause] [limit_clause]
+        [returning_clause]
 
     inserting =
         "insert" inserting_priority ["ignore"]
         "into" data_source inserting_source
-        [inserting_on_duplicate_key_update]
+        [inserting_on_conflict_update]
+        [returning_clause]
 
     deleting =
         "delete" ["low_priority"] ["quick"] ["ignore"]
         "from" data_source [where_clause] [order_by_clause] [limit_clause]
+        [returning_clause]
     ```
 
+    При конфликте по первичному ключу таблицы `inserting_on_conflict_update` обновляет существующую строку,
+    поэтому все колонки первичного ключа должны быть записаны, а значит, первичный ключ не может быть `#[auto_increment]`.
+    В T-SQL такой запрос выводится как `MERGE` с `HOLDLOCK`, чтобы параллельные запросы с одним ключом не вставили строку дважды.
+    Проект генерирует только T-SQL, поэтому запрос с `inserting_on_conflict_update` переносим только на SQL Server.
+
+    Запрос с `returning_clause` возвращает массив затронутых строк и может быть значением переменной.
 
     Правила `comma_list` и `u32_literal` определены в модуле `parser_basics`.
 
@@ -51,7 +62,7 @@
 
     Правило `property_path` определено в модуле `language::others`.
 
-    Правила `selection`, `select_condition` и `select_sorting` определены в модуле `language::selections`.
+    Правила `selection`, `select_condition`, `select_result` и `select_sorting` определены в модуле `language::selections`.
 */
 
 pub use self::definitions::*;
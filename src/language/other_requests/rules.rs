@@ -4,6 +4,7 @@ use language::{
     ItemPath,
     property_path,
     select_condition,
+    select_result,
     select_sorting,
     selection,
 };
@@ -45,6 +46,16 @@ parser_rule!(limit_clause(i) -> u32 {
     )
 });
 
+parser_rule!(returning_clause(i) -> ReturningAST<'source> {
+    do_parse!(i,
+        begin: symbol_position >>
+        apply!(keyword, "returning") >>
+        result: select_result >>
+        pos: apply!(item_position, begin) >>
+        (ReturningAST { result, pos })
+    )
+});
+
 /// Выполняет разбор запроса обновления
 pub fn updating<'token, 'source>(input: &'token [Token<'source>]) -> ParserResult<'token, 'source, UpdatingAST<'source>> {
     do_parse!(input,
@@ -56,6 +67,7 @@ pub fn updating<'token, 'source>(input: &'token [Token<'source>]) -> ParserResul
         where_clause: opt!(apply!(select_condition, "where")) >>
         order_by_clause: opt!(apply!(select_sorting, "order")) >>
         limit_clause: opt!(limit_clause) >>
+        returning: opt!(returning_clause) >>
         pos: apply!(item_position, begin) >>
         (UpdatingAST {
             source,
@@ -63,6 +75,7 @@ pub fn updating<'token, 'source>(input: &'token [Token<'source>]) -> ParserResul
             where_clause,
             order_by_clause,
             limit_clause,
+            returning,
             pos,
         })
     )
@@ -109,16 +122,18 @@ parser_rule!(inserting_source(i) -> InsertingSourceAST<'source> {
     )
 });
 
-//parser_rule!(inserting_on_duplicate_key_update(i) -> Vec<UpdatingAssignmentAST<'source>> {
-//    do_parse!(i,
-//        apply!(keyword, "on") >>
-//        apply!(keyword, "duplicate") >>
-//        apply!(keyword, "key") >>
-//        apply!(keyword, "update") >>
-//        assignments: apply!(comma_list, updating_assignment) >>
-//        (assignments)
-//    )
-//});
+parser_rule!(inserting_on_conflict_update(i) -> InsertingOnConflictAST<'source> {
+    do_parse!(i,
+        begin: symbol_position >>
+        apply!(keyword, "on") >>
+        apply!(keyword, "conflict") >>
+        apply!(keyword, "update") >>
+        apply!(keyword, "set") >>
+        assignments: apply!(comma_list, updating_assignment) >>
+        pos: apply!(item_position, begin) >>
+        (InsertingOnConflictAST { assignments, pos })
+    )
+});
 
 /// Выполняет разбор запроса записи
 pub fn inserting<'token, 'source>(input: &'token [Token<'source>]) -> ParserResult<'token, 'source, InsertingAST<'source>> {
@@ -127,9 +142,13 @@ pub fn inserting<'token, 'source>(input: &'token [Token<'source>]) -> ParserResu
         apply!(keyword, "into") >>
         target: data_source >>
         source: inserting_source >>
+        on_conflict: opt!(inserting_on_conflict_update) >>
+        returning: opt!(returning_clause) >>
         (InsertingAST {
             target,
             source,
+            on_conflict,
+            returning,
         })
     )
 }
@@ -143,11 +162,13 @@ pub fn deleting<'token, 'source>(input: &'token [Token<'source>]) -> ParserResul
         where_clause: opt!(apply!(select_condition, "where")) >>
         order_by_clause: opt!(apply!(select_sorting, "order")) >>
         limit_clause: opt!(limit_clause) >>
+        returning: opt!(returning_clause) >>
         (DeletingAST {
             source,
             where_clause,
             order_by_clause,
             limit_clause,
+            returning,
         })
     )
 }
@@ -1,11 +1,13 @@
 use helpers::{
     BlockFormatter,
+    CodeFormatter,
     PathBuf,
     Resolve,
     SyncRef,
 };
 use indexmap::IndexMap;
 use language::{
+    Attribute,
     BOOLEAN_TYPE,
     CompoundDataType,
     DataSource,
@@ -27,6 +29,7 @@ use std::{
         self,
         Write,
     },
+    mem,
     sync::Arc,
 };
 
@@ -75,8 +78,18 @@ impl SelectionExpression {
     }
     #[inline]
     pub fn make_field(&self) -> Field {
+        self.make_field_with_nullability(self.expr.is_nullable())
+    }
+    pub fn make_field_with_nullability(&self, is_nullable: bool) -> Field {
+        let mut attributes = Vec::new();
+        if is_nullable {
+            attributes.push(Attribute {
+                name: "nullable".to_string(),
+                arguments: None,
+            });
+        }
         Field {
-            attributes: Vec::new(),
+            attributes,
             field_type: self.expr.data_type.clone(),
         }
     }
@@ -96,22 +109,35 @@ impl SelectionExpression {
         }
         Some(result)
     }
+    #[inline]
     pub fn type_of_expression_set(expressions: &[SelectionExpression]) -> DataType {
+        SelectionExpression::type_of_expression_sets(expressions, &[])
+    }
+    /**
+        Тип строки составного запроса. Имена и типы колонок берутся из первого запроса,
+        а колонка может быть `null`, если она может быть `null` хотя бы в одном из запросов.
+    */
+    pub fn type_of_expression_sets(expressions: &[SelectionExpression], others: &[&[SelectionExpression]]) -> DataType {
+        let make_field = |i: usize, expression: &SelectionExpression| {
+            let is_nullable = expression.expr.is_nullable() || others.iter()
+                .any(|other| other.get(i).map_or(false, |other| other.expr.is_nullable()));
+            expression.make_field_with_nullability(is_nullable)
+        };
         match SelectionExpression::names_of_expression_set(expressions) {
             Some(names) => {
-                let mut expressions_iter = expressions.iter();
+                let mut expressions_iter = expressions.iter().enumerate();
                 let mut fields = IndexMap::with_capacity(expressions.len());
                 for name in names {
-                    let expression = expressions_iter.next()
+                    let (i, expression) = expressions_iter.next()
                         .expect("expression's set and set of theirs names should have equal sizes");
-                    fields.insert(name, expression.make_field());
+                    fields.insert(name, make_field(i, expression));
                 }
                 DataType::Compound(CompoundDataType::Structure(Arc::new(fields)))
             }
             None => {
                 let mut fields = Vec::with_capacity(expressions.len());
-                for expression in expressions {
-                    fields.push(expression.make_field());
+                for (i, expression) in expressions.iter().enumerate() {
+                    fields.push(make_field(i, expression));
                 }
                 DataType::Compound(CompoundDataType::Tuple(Arc::new(fields)))
             }
@@ -237,30 +263,281 @@ pub struct SelectionLimit {
     pub count: u32,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommonTableAST<'source> {
+    pub name: Identifier<'source>,
+    pub anchor: SelectionAST<'source>,
+    /// Запрос после `union all`. В рекурсивном выражении он может ссылаться на саму таблицу.
+    pub union_member: Option<SelectionAST<'source>>,
+    pub pos: ItemPosition,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithClauseAST<'source> {
+    pub is_recursive: bool,
+    pub tables: Vec<CommonTableAST<'source>>,
+}
+
+impl<'source> WithClauseAST<'source> {
+    /// Возвращает таблицу, второй член которой может ссылаться на неё саму
+    pub fn recursive_table(&self) -> Option<&CommonTableAST<'source>> {
+        if !self.is_recursive {
+            return None;
+        }
+        self.tables.iter().find(|table| table.union_member.is_some())
+    }
+}
+
+impl<'source> Resolve<SyncRef<FunctionVariableScope>> for WithClauseAST<'source> {
+    type Result = Vec<CommonTableExpression>;
+    type Error = SemanticError;
+    fn resolve(&self, scope: &SyncRef<FunctionVariableScope>) -> Result<Self::Result, Vec<Self::Error>> {
+        let mut result = Vec::with_capacity(self.tables.len());
+        for table in self.tables.iter() {
+            let anchor: Selection = table.anchor.resolve(scope)?;
+            let entity = SelectionExpression::type_of_expression_set(anchor.result.as_slice());
+            if let DataType::Compound(CompoundDataType::Tuple(_)) = &entity {
+                return SemanticError::not_allowed_inside(
+                    table.anchor.pos,
+                    "unnamed column",
+                    "common table expression",
+                )
+                    .into_err_vec();
+            }
+            let common_table = SyncRef::new(CommonTable {
+                name: table.name.to_string(),
+                entity,
+            });
+            // Рекурсивная таблица становится видна своему второму члену, остальные - только следующим за ними
+            if self.is_recursive {
+                scope.new_common_table(table.name.item_pos(), common_table.clone())?;
+            }
+            let union_member = match &table.union_member {
+                Some(member) => {
+                    let member: Selection = member.resolve(scope)?;
+                    common_table.read().check_union_member(&member)?;
+                    Some(member)
+                }
+                None => None,
+            };
+            if !self.is_recursive {
+                scope.new_common_table(table.name.item_pos(), common_table.clone())?;
+            }
+            result.push(CommonTableExpression {
+                table: common_table,
+                is_recursive: self.is_recursive && union_member.is_some(),
+                anchor,
+                union_member,
+            });
+        }
+        Ok(result)
+    }
+}
+
+/// Имя и тип строки общего табличного выражения, на которые ссылаются источники данных
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommonTable {
+    pub name: String,
+    pub entity: DataType,
+}
+
+impl CommonTable {
+    /// Выводит список столбцов таблицы
+    pub fn fmt_columns(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        let mut primitives = self.entity.primitives(PathBuf::new("#"))
+            .into_iter()
+            .peekable();
+        while let Some(primitive) = primitives.next() {
+            write!(f, "[{}]", primitive.path)?;
+            if primitives.peek().is_some() {
+                f.write_str(", ")?;
+            }
+        }
+        Ok(())
+    }
+    /// Столбцы второго члена должны совпадать по типам со столбцами первого
+    fn check_union_member(&self, member: &Selection) -> Result<(), SemanticError> {
+        let fields = match &self.entity {
+            DataType::Compound(CompoundDataType::Structure(fields)) => fields,
+            _ => unreachable!("Common table should have named columns"),
+        };
+        let is_matches = fields.len() == member.result.len() && fields.values()
+            .zip(member.result.iter())
+            .all(|(field, expression)| field.field_type == expression.expr.data_type);
+        if is_matches {
+            return Ok(());
+        }
+        Err(SemanticError::expected_expression_of_another_type(
+            member.pos,
+            self.entity.clone(),
+            SelectionExpression::type_of_expression_set(member.result.as_slice()),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommonTableExpression {
+    pub table: SyncRef<CommonTable>,
+    /// Второй член выражения ссылается на саму таблицу, поэтому её можно объявить только в `WITH`
+    pub is_recursive: bool,
+    pub anchor: Selection,
+    pub union_member: Option<Selection>,
+}
+
+impl CommonTableExpression {
+    /// Выводит определение `[имя] (столбцы) AS (...` без закрывающей скобки
+    fn fmt_definition(
+        &self,
+        mut f: BlockFormatter<impl fmt::Write>,
+        prefix: &str,
+        context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        {
+            let table = self.table.read();
+            let mut line = f.line()?;
+            write!(line, "{}[{}] (", prefix, table.name)?;
+            table.fmt_columns(&mut line)?;
+            line.write_str(") AS (")?;
+        }
+        self.anchor.fmt(f.sub_block(), context)?;
+        if let Some(member) = &self.union_member {
+            f.write_line("UNION ALL")?;
+            member.fmt(f.sub_block(), context)?;
+        }
+        Ok(())
+    }
+    /// Нерекурсивное выражение подставляется производной таблицей,
+    /// поэтому внутри него остаются доступны столбцы внешнего запроса
+    pub fn fmt_inline(&self, context: &mut TSQLFunctionContext) -> fmt::Result {
+        if self.is_recursive {
+            unreachable!("Recursive common tables of nested selections should be rejected at semantic-check-time");
+        }
+        let mut query = String::new();
+        {
+            let mut formatter = CodeFormatter::new(&mut query);
+            formatter.indent_size = 4;
+            let mut f = formatter.root_block();
+            self.anchor.fmt(f.clone(), context)?;
+            if let Some(member) = &self.union_member {
+                f.write_line("UNION ALL")?;
+                member.fmt(f, context)?;
+            }
+        }
+        context.set_inline_common_table(&self.table, Some(query));
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    Union,
+    UnionAll,
+    Intersect,
+    Except,
+}
+
+impl SetOperation {
+    pub fn get_description(&self) -> &'static str {
+        match self {
+            SetOperation::Union => "union",
+            SetOperation::UnionAll => "union all",
+            SetOperation::Intersect => "intersect",
+            SetOperation::Except => "except",
+        }
+    }
+    pub fn fmt_tsql(&self) -> &'static str {
+        match self {
+            SetOperation::Union => "UNION",
+            SetOperation::UnionAll => "UNION ALL",
+            SetOperation::Intersect => "INTERSECT",
+            SetOperation::Except => "EXCEPT",
+        }
+    }
+}
+
+/// Следующий член составного запроса. Сортировка и ограничение у него свои быть не могут, они относятся ко всему запросу.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectionSetOperationAST<'source> {
+    pub operation: SetOperation,
+    pub query: SelectionAST<'source>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectionSetOperation {
+    pub operation: SetOperation,
+    pub query: Selection,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct SelectionAST<'source> {
+    pub with_clause: Option<WithClauseAST<'source>>,
     pub distinct: bool,
     pub result: SelectionResultAST<'source>,
     pub source: DataSourceAST<'source>,
     pub where_clause: Option<ExpressionAST<'source>>,
     pub group_by_clause: Option<SelectionGroupByClauseAST<'source>>,
     pub having_clause: Option<ExpressionAST<'source>>,
+    pub set_operations: Vec<SelectionSetOperationAST<'source>>,
     pub order_by_clause: Option<Vec<SelectionSortingItemAST<'source>>>,
     pub limit_clause: Option<SelectionLimit>,
     pub pos: ItemPosition,
 }
 
-impl<'source> Resolve<SyncRef<FunctionVariableScope>> for SelectionAST<'source> {
-    type Result = Selection;
-    type Error = SemanticError;
-    fn resolve(&self, parent_scope: &SyncRef<FunctionVariableScope>) -> Result<Self::Result, Vec<Self::Error>> {
+impl<'source> SelectionAST<'source> {
+    /// Отделяет от запроса последний член `union all`, который в общем табличном выражении может быть рекурсивным
+    pub fn split_union_member(mut self) -> (Self, Option<Self>) {
+        let is_splittable = self.order_by_clause.is_none()
+            && self.limit_clause.is_none()
+            && self.set_operations.last().map(|item| item.operation) == Some(SetOperation::UnionAll);
+        if !is_splittable {
+            return (self, None);
+        }
+        let member = self.set_operations.pop()
+            .expect("Set operations should not be empty after the check");
+        (self, Some(member.query))
+    }
+}
+
+impl<'source> SelectionAST<'source> {
+    /**
+        Разрешает запрос, который выводится отдельным оператором T-SQL.
+        Только у такого запроса общие табличные выражения объявляются в `WITH`, поэтому могут быть рекурсивными.
+    */
+    pub fn resolve_statement(&self, scope: &SyncRef<FunctionVariableScope>) -> Result<Selection, Vec<SemanticError>> {
+        self.resolve_selection(scope, true)
+    }
+    fn resolve_selection(
+        &self,
+        parent_scope: &SyncRef<FunctionVariableScope>,
+        is_statement: bool,
+    ) -> Result<Selection, Vec<SemanticError>> {
         //TODO Non-array result of SELECT ... LIMIT 1 or aggregate SELECT without GROUP BY
         //TODO Moreover, result of SELECT ... LIMIT 1 must be nullable
-        let scope = parent_scope.lite_weight_child();
+        // Общие табличные выражения видны всем членам составного запроса, а источники каждого члена - только ему
+        let with_scope = parent_scope.lite_weight_child();
+        let with_clause = match &self.with_clause {
+            Some(with_clause) => {
+                // Вложенный запрос выводится внутри выражения, а T-SQL не разрешает там `WITH`
+                if let (false, Some(table)) = (is_statement, with_clause.recursive_table()) {
+                    return SemanticError::not_allowed_inside(
+                        table.pos,
+                        "recursive common table expression",
+                        "nested selection",
+                    )
+                        .into_err_vec();
+                }
+                with_clause.resolve(&with_scope)?
+            }
+            None => Vec::new(),
+        };
+        let scope = with_scope.lite_weight_child();
         let aggregate_scope = scope.aggregate_child();
+        // Группирующим запрос делают агрегатные функции результата, но в окне они допустимы только при явной группировке
+        let is_grouped = self.group_by_clause.is_some() || self.having_clause.is_some();
+        let result_scope = scope.window_child(is_grouped);
 
         let source = self.source.resolve(&scope)?;
-        let result = self.result.resolve(&aggregate_scope)?;
+        let result = self.result.resolve(&result_scope)?;
 
         let mut errors = Vec::new();
         let is_aggregate_query = result.iter()
@@ -270,6 +547,7 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for SelectionAST<'source>
             Some(where_clause) => where_clause.accumulative_resolve(&scope, &mut errors),
             None => None,
         };
+        let where_clause = Expression::fold_filter(where_clause, &scope);
         if let Some(where_clause) = &where_clause {
             if !where_clause.data_type.can_cast(&BOOLEAN_TYPE) {
                 errors.push(SemanticError::expected_expression_of_another_type(
@@ -350,8 +628,73 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for SelectionAST<'source>
             }
         }
 
-        let result_data_type = SelectionExpression::type_of_expression_set(result.as_slice());
-        let result_data_type = if is_aggregate_query && group_by_clause.is_none() {
+        let mut set_operations = Vec::with_capacity(self.set_operations.len());
+        for set_operation in self.set_operations.iter() {
+            if let Some(query) = set_operation.query.accumulative_resolve(&with_scope, &mut errors) {
+                set_operations.push(SelectionSetOperation {
+                    operation: set_operation.operation,
+                    query,
+                });
+            }
+        }
+        let expected_data_type = SelectionExpression::type_of_expression_set(result.as_slice());
+        for set_operation in set_operations.iter() {
+            let query = &set_operation.query;
+            let got_data_type = SelectionExpression::type_of_expression_set(query.result.as_slice());
+            if got_data_type.field_len() != expected_data_type.field_len() {
+                errors.push(SemanticError::select_with_wrong_column_count(
+                    query.pos,
+                    expected_data_type.field_len(),
+                    got_data_type.field_len(),
+                ));
+                continue;
+            }
+            for (expected, got) in result.iter().zip(query.result.iter()) {
+                if let Err(error) = got.expr.should_cast_to_type(&expected.expr.data_type) {
+                    errors.push(error);
+                }
+            }
+        }
+        if !set_operations.is_empty() {
+            // T-SQL сортирует составной запрос только по колонкам его результата
+            if let Some(order_by_clause) = &order_by_clause {
+                for item in order_by_clause.iter() {
+                    if !result.iter().any(|expression| expression.expr == item.expr) {
+                        errors.push(SemanticError::not_allowed_inside(
+                            item.expr.pos,
+                            "sorting by expression out of selection result",
+                            "compound query",
+                        ));
+                    } else if item.expr.data_type.primitives(PathBuf::new(".")).len() != 1 {
+                        // Составное значение занимает несколько колонок результата, и одного имени для сортировки у него нет
+                        errors.push(SemanticError::not_allowed_inside(
+                            item.expr.pos,
+                            "sorting by compound value",
+                            "compound query",
+                        ));
+                    }
+                }
+            }
+            let is_order_by_clause_empty = match &order_by_clause {
+                Some(clause) => clause.is_empty(),
+                None => true,
+            };
+            if self.limit_clause.is_some() && is_order_by_clause_empty {
+                errors.push(SemanticError::not_allowed_inside(
+                    self.pos,
+                    "LIMIT clause",
+                    "compound query without sorting",
+                ));
+            }
+        }
+
+        let result_data_type = {
+            let others: Vec<&[SelectionExpression]> = set_operations.iter()
+                .map(|set_operation| set_operation.query.result.as_slice())
+                .collect();
+            SelectionExpression::type_of_expression_sets(result.as_slice(), others.as_slice())
+        };
+        let result_data_type = if is_aggregate_query && group_by_clause.is_none() && set_operations.is_empty() {
             result_data_type
         } else {
             DataType::Array(Arc::new(result_data_type))
@@ -375,12 +718,14 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for SelectionAST<'source>
 
         if errors.is_empty() {
             Ok(Selection {
+                with_clause,
                 distinct: self.distinct,
                 result,
                 source,
                 where_clause,
                 group_by_clause,
                 having_clause,
+                set_operations,
                 order_by_clause,
                 limit_clause: self.limit_clause,
                 result_data_type,
@@ -392,14 +737,25 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for SelectionAST<'source>
     }
 }
 
+impl<'source> Resolve<SyncRef<FunctionVariableScope>> for SelectionAST<'source> {
+    type Result = Selection;
+    type Error = SemanticError;
+    #[inline]
+    fn resolve(&self, scope: &SyncRef<FunctionVariableScope>) -> Result<Self::Result, Vec<Self::Error>> {
+        self.resolve_selection(scope, false)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Selection {
+    pub with_clause: Vec<CommonTableExpression>,
     pub distinct: bool,
     pub result: Vec<SelectionExpression>,
     pub source: DataSource,
     pub where_clause: Option<Expression>,
     pub group_by_clause: Option<SelectionGroupByClause>,
     pub having_clause: Option<Expression>,
+    pub set_operations: Vec<SelectionSetOperation>,
     pub order_by_clause: Option<Vec<SelectionSortingItem>>,
     pub limit_clause: Option<SelectionLimit>,
     pub result_data_type: DataType,
@@ -407,11 +763,53 @@ pub struct Selection {
 }
 
 impl Selection {
+    #[inline]
+    pub fn is_recursive(&self) -> bool {
+        self.with_clause.iter().any(|common_table| common_table.is_recursive)
+    }
+    /**
+        Объявляет общие табличные выражения запроса в `WITH` перед оператором, который его содержит.
+        Во вложенных запросах T-SQL не разрешает `WITH`, там выражения выводятся при выводе самого запроса.
+    */
+    pub fn fmt_with_clause(
+        &self,
+        mut f: BlockFormatter<impl fmt::Write>,
+        context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        if self.with_clause.is_empty() {
+            return Ok(());
+        }
+        for common_table in self.with_clause.iter() {
+            context.set_inline_common_table(&common_table.table, None);
+        }
+        for (i, common_table) in self.with_clause.iter().enumerate() {
+            common_table.fmt_definition(f.clone(), if i == 0 { "WITH " } else { "), " }, context)?;
+        }
+        f.write_line(")")?;
+        context.common_tables_declared = true;
+        Ok(())
+    }
     pub fn fmt(
         &self,
         mut f: BlockFormatter<impl fmt::Write>,
         context: &mut TSQLFunctionContext,
     ) -> fmt::Result {
+        if !mem::replace(&mut context.common_tables_declared, false) {
+            for common_table in self.with_clause.iter() {
+                common_table.fmt_inline(context)?;
+            }
+        }
+        // В T-SQL INTERSECT связывает сильнее UNION и EXCEPT, а в N операции выполняются слева направо
+        let mut is_wrapped = Vec::with_capacity(self.set_operations.len());
+        let mut has_weaker_operation = false;
+        for set_operation in self.set_operations.iter() {
+            let is_intersect = set_operation.operation == SetOperation::Intersect;
+            is_wrapped.push(is_intersect && has_weaker_operation);
+            has_weaker_operation = !is_intersect;
+        }
+        for _ in is_wrapped.iter().filter(|is_wrapped| **is_wrapped) {
+            f.write_line("(")?;
+        }
         let mut offset_fetch_clause = None;
         {
             let mut line = f.line()?;
@@ -424,6 +822,10 @@ impl Selection {
                     Some(offset) => {
                         offset_fetch_clause = Some((limit_clause.count, *offset));
                     }
+                    // TOP ограничил бы только первый член составного запроса
+                    None if !self.set_operations.is_empty() => {
+                        offset_fetch_clause = Some((limit_clause.count, 0));
+                    }
                     None => {
                         write!(line, " TOP({})", limit_clause.count)?;
                     }
@@ -450,7 +852,7 @@ impl Selection {
                     .into_new_buf("#");
 
                 if let Some(sub_expr) = result_item.expr.get_property_or_wrap(primitive.path.as_path()) {
-                    sub_expr.fmt(&mut line, context)?;
+                    sub_expr.fmt_value(&mut line, context)?;
                 } else {
                     write!(line, "( SELECT t.[{}] FROM (", new_path)?;
                     result_item.expr.fmt(&mut line, context)?;
@@ -491,7 +893,7 @@ impl Selection {
         if let Some(where_clause) = &self.where_clause {
             let mut line = f.line()?;
             line.write_str("WHERE ")?;
-            where_clause.fmt(&mut line, context)?;
+            where_clause.fmt_condition(&mut line, context)?;
         }
 
         if let Some(group_by_clause) = &self.group_by_clause {
@@ -516,7 +918,15 @@ impl Selection {
         if let Some(having_clause) = &self.having_clause {
             let mut line = f.line()?;
             line.write_str("HAVING ")?;
-            having_clause.fmt(&mut line, context)?;
+            having_clause.fmt_condition(&mut line, context)?;
+        }
+
+        for (set_operation, is_wrapped) in self.set_operations.iter().zip(is_wrapped) {
+            if is_wrapped {
+                f.write_line(")")?;
+            }
+            f.write_line(set_operation.operation.fmt_tsql())?;
+            set_operation.query.fmt(f.clone(), context)?;
         }
 
         {
@@ -534,7 +944,21 @@ impl Selection {
                     }
                     let mut items = order_by_clause.iter().peekable();
                     while let Some(expr) = items.next() {
-                        expr.expr.fmt(&mut line, context)?;
+                        // Составной запрос в T-SQL сортируется только по именам колонок результата
+                        let column = if self.set_operations.is_empty() {
+                            None
+                        } else {
+                            self.result.iter()
+                                .enumerate()
+                                .find(|(_, item)| item.expr == expr.expr)
+                        };
+                        match column {
+                            Some((i, item)) => match item.can_be_named() {
+                                Some(alias) => line.write_str(&alias)?,
+                                None => write!(line, "component{}", i)?,
+                            },
+                            None => expr.expr.fmt(&mut line, context)?,
+                        }
                         line.write_str(match &expr.order {
                             SelectionSortingOrder::Asc => " ASC",
                             SelectionSortingOrder::Desc => " DESC",
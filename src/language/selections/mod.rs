@@ -31,17 +31,36 @@
         | "limit" u32_literal, u32_literal
         | "limit" u32_literal "offset" u32_literal
 
-    selection =
+    common_table = identifier "as" "(" selection ")"
+
+    with_clause = "with" ["recursive"] comma_list(common_table)
+
+    set_operation =
+        | "union" "all"
+        | "union" ["distinct"]
+        | "intersect"
+        | "except"
+
+    selection_core =
         "select" select_distincty ["high_priority"] ["straight_join"]
         select_result_size select_cache select_result
         "from" data_source
         [select_condition("where")]
         [select_group_by_clause]
         [select_condition("having")]
+
+    selection =
+        [with_clause]
+        selection_core
+        {set_operation selection_core}
         [select_sorting("order")]
         [selection_limit]
     ```
 
+    В рекурсивном `with_clause` последний член после `union all` может ссылаться на саму определяемую таблицу.
+
+    Сортировка и ограничение относятся ко всему составному запросу. Операции над множествами, включая `intersect`, применяются слева направо; в T-SQL `intersect` связывает члены сильнее, поэтому при выводе расставляются скобки, сохраняющие этот порядок.
+
     Правила `comma_list` и `u32_literal` определены в модуле `parser_basics`.
 
     Правило `expression` определено в модуле `language::expressions`.
@@ -39,7 +39,7 @@ parser_rule!(select_expression(i) -> SelectionExpressionAST<'source> {
     )
 });
 
-parser_rule!(select_result(i) -> SelectionResultAST<'source> {
+parser_rule!(pub select_result(i) -> SelectionResultAST<'source> {
     alt!(i,
         do_parse!(
             begin: symbol_position >>
@@ -124,8 +124,50 @@ parser_rule!(selection_limit(i) -> SelectionLimit {
     )
 });
 
-/// Функция, выполняющая разбор запроса выборки
-pub fn selection<'token, 'source>(input: &'token [Token<'source>]) -> ParserResult<'token, 'source, SelectionAST<'source>> {
+parser_rule!(common_table(i) -> CommonTableAST<'source> {
+    do_parse!(i,
+        begin: symbol_position >>
+        name: identifier >>
+        apply!(keyword, "as") >>
+        apply!(symbols, "(") >>
+        query: selection >>
+        apply!(symbols, ")") >>
+        pos: apply!(item_position, begin) >>
+        ({
+            let (anchor, union_member) = query.split_union_member();
+            CommonTableAST { name, anchor, union_member, pos }
+        })
+    )
+});
+
+parser_rule!(with_clause(i) -> WithClauseAST<'source> {
+    do_parse!(i,
+        apply!(keyword, "with") >>
+        is_recursive: opt!(apply!(keyword, "recursive")) >>
+        tables: apply!(comma_list, common_table) >>
+        (WithClauseAST { is_recursive: is_recursive.is_some(), tables })
+    )
+});
+
+parser_rule!(set_operation(i) -> SetOperation {
+    alt!(i,
+        do_parse!(
+            apply!(keyword, "union") >>
+            apply!(keyword, "all") >>
+            (SetOperation::UnionAll)
+        )
+        | do_parse!(
+            apply!(keyword, "union") >>
+            opt!(apply!(keyword, "distinct")) >>
+            (SetOperation::Union)
+        )
+        | apply!(keyword, "intersect") => { |_| SetOperation::Intersect }
+        | apply!(keyword, "except") => { |_| SetOperation::Except }
+    )
+});
+
+/// Функция, выполняющая разбор одного члена составного запроса выборки
+fn selection_core<'token, 'source>(input: &'token [Token<'source>]) -> ParserResult<'token, 'source, SelectionAST<'source>> {
     do_parse!(input,
         begin: symbol_position >>
         apply!(keyword, "select") >>
@@ -136,19 +178,48 @@ pub fn selection<'token, 'source>(input: &'token [Token<'source>]) -> ParserResu
         where_clause: opt!(apply!(select_condition, "where")) >>
         group_by_clause: opt!(select_group_by_clause) >>
         having_clause: opt!(apply!(select_condition, "having")) >>
-        order_by_clause: opt!(apply!(select_sorting, "order")) >>
-        limit_clause: opt!(selection_limit) >>
         pos: apply!(item_position, begin) >>
         (SelectionAST {
+            with_clause: None,
             distinct,
             result,
             source,
             where_clause,
             group_by_clause,
             having_clause,
+            set_operations: Vec::new(),
+            order_by_clause: None,
+            limit_clause: None,
+            pos,
+        })
+    )
+}
+
+parser_rule!(selection_set_operation(i) -> SelectionSetOperationAST<'source> {
+    do_parse!(i,
+        operation: set_operation >>
+        query: selection_core >>
+        (SelectionSetOperationAST { operation, query })
+    )
+});
+
+/// Функция, выполняющая разбор запроса выборки
+pub fn selection<'token, 'source>(input: &'token [Token<'source>]) -> ParserResult<'token, 'source, SelectionAST<'source>> {
+    do_parse!(input,
+        begin: symbol_position >>
+        with_clause: opt!(with_clause) >>
+        core: selection_core >>
+        set_operations: many0!(selection_set_operation) >>
+        order_by_clause: opt!(apply!(select_sorting, "order")) >>
+        limit_clause: opt!(selection_limit) >>
+        pos: apply!(item_position, begin) >>
+        (SelectionAST {
+            with_clause,
+            set_operations,
             order_by_clause,
             limit_clause,
             pos,
+            ..core
         })
     )
 }
@@ -6,19 +6,29 @@ use helpers::{
     Resolve,
     SyncRef,
 };
+use indexmap::IndexMap;
 use language::{
     BOOLEAN_TYPE,
+    CompoundDataType,
     DataType,
     DataTypeAST,
     Deleting,
     DeletingAST,
     Expression,
     ExpressionAST,
+    ExpressionASTBody,
+    Field,
     Inserting,
     InsertingAST,
     ItemPath,
+    NumberType,
+    PrimitiveDataType,
+    Returning,
     Selection,
     SelectionAST,
+    StringType,
+    TEXT_TYPE,
+    TSQLCycleLabels,
     TSQLFunctionContext,
     Updating,
     UpdatingAST,
@@ -30,29 +40,111 @@ use project_analysis::{
     FunctionVariableScope,
     Item,
     SemanticError,
+    SemanticItemType,
     StatementFlowControlJumping,
     StatementFlowControlPosition,
 };
-use std::fmt::{
-    self,
-    Write,
+use std::{
+    fmt::{
+        self,
+        Write,
+    },
+    sync::Arc,
 };
 
+/// Номер ошибки, которую порождает `raise` без объявленной ошибки
+pub const DEFAULT_RAISE_ERROR_NUMBER: u32 = 50000;
+
+/// Тип переменной блока `catch`: номер и текст перехваченной ошибки.
+/// Текст имеет тип результата `ERROR_MESSAGE()`, так как ntext не может быть локальной переменной.
+pub fn caught_error_type() -> DataType {
+    let mut fields = IndexMap::new();
+    fields.insert("number".to_string(), Field {
+        attributes: Vec::new(),
+        field_type: DataType::Primitive(PrimitiveDataType::Number(NumberType::Integer {
+            unsigned: false,
+            zerofill: false,
+            size: 32,
+        })),
+    });
+    fields.insert("message".to_string(), Field {
+        attributes: Vec::new(),
+        field_type: DataType::Primitive(PrimitiveDataType::String(StringType::Varchar {
+            size: Some(4000),
+            character_set: None,
+        })),
+    });
+    DataType::Compound(CompoundDataType::Structure(Arc::new(fields)))
+}
+
+/// Выделяет из выражения `raise` объявленную ошибку: `Error` или `Error(message)`.
+/// Путь, который не называет ошибку, остаётся выражением сообщения.
+fn raised_error<'a, 'source>(
+    ctx: &SyncRef<FunctionVariableScope>,
+    value: &'a ExpressionAST<'source>,
+) -> Option<(SyncRef<Item>, Option<&'a ExpressionAST<'source>>)> {
+    let (path, message) = match &value.body {
+        ExpressionASTBody::Reference(name) => {
+            let mut path = PathBuf::new("::");
+            path.push(name.text());
+            (path, None)
+        }
+        ExpressionASTBody::ItemReference(path) => (path.path.clone(), None),
+        ExpressionASTBody::FunctionCall(path, arguments) if arguments.len() == 1 => {
+            (path.path.clone(), arguments.first())
+        }
+        _ => return None,
+    };
+    let item = ctx.context().module().get_item(path.as_path(), &mut Vec::new())?;
+    if item.get_type() == SemanticItemType::Error {
+        Some((item, message))
+    } else {
+        None
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum CycleTypeAST<'source> {
     Simple,
     PrePredicated(ExpressionAST<'source>),
     PostPredicated(ExpressionAST<'source>),
+    Iterating {
+        variable: Identifier<'source>,
+        source: StatementSourceAST<'source>,
+    },
 }
 
 impl<'source> Resolve<SyncRef<FunctionVariableScope>> for CycleTypeAST<'source> {
     type Result = CycleType;
     type Error = SemanticError;
+    /// Переменная цикла-перебора объявляется в переданной области видимости, поэтому она должна быть дочерней
     fn resolve(&self, scope: &SyncRef<FunctionVariableScope>) -> Result<Self::Result, Vec<Self::Error>> {
         let result = match self {
             CycleTypeAST::Simple => CycleType::Simple,
             CycleTypeAST::PrePredicated(predicate) => CycleType::PrePredicated(predicate.resolve(scope)?),
             CycleTypeAST::PostPredicated(predicate) => CycleType::PostPredicated(predicate.resolve(scope)?),
+            CycleTypeAST::Iterating { variable, source } => {
+                let source: StatementSource = source.resolve(scope)?;
+                // Запрос курсора не может начинаться с `WITH`
+                if let StatementSource::Selection(query) = &source {
+                    if query.is_recursive() {
+                        return SemanticError::not_allowed_inside(
+                            query.pos,
+                            "recursive common table expression",
+                            "cursor of for-in cycle",
+                        )
+                            .into_err_vec();
+                    }
+                }
+                let item_type = match source.type_of().as_array() {
+                    Some(item_type) => (**item_type).clone(),
+                    None => return SemanticError::not_allowed_here(variable.item_pos(), "iteration over a non-array value")
+                        .into_err_vec(),
+                };
+                let variable = scope.new_variable(variable.item_pos(), variable.to_string(), Some(item_type))?;
+                variable.make_read_only();
+                CycleType::Iterating { variable, source }
+            }
         };
         Ok(result)
     }
@@ -63,6 +155,10 @@ pub enum CycleType {
     Simple,
     PrePredicated(Expression),
     PostPredicated(Expression),
+    Iterating {
+        variable: SyncRef<FunctionVariable>,
+        source: StatementSource,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -75,6 +171,8 @@ pub enum CycleControlOperator {
 pub enum StatementSourceAST<'source> {
     Expression(ExpressionAST<'source>),
     Selection(SelectionAST<'source>),
+    /// Запрос записи, обновления или удаления с `returning`
+    Request(Box<StatementAST<'source>>),
 }
 
 impl<'source> Resolve<SyncRef<FunctionVariableScope>> for StatementSourceAST<'source> {
@@ -83,7 +181,19 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for StatementSourceAST<'so
     fn resolve(&self, scope: &SyncRef<FunctionVariableScope>) -> Result<Self::Result, Vec<Self::Error>> {
         let result = match self {
             StatementSourceAST::Expression(expr) => StatementSource::Expression(expr.resolve(scope)?),
-            StatementSourceAST::Selection(select) => StatementSource::Selection(select.resolve(scope)?)
+            StatementSourceAST::Selection(select) => StatementSource::Selection(select.resolve_statement(scope)?),
+            StatementSourceAST::Request(request) => {
+                let statement = request.resolve(scope)?;
+                let value = match statement.returning() {
+                    Some(returning) => returning.value(),
+                    None => return SemanticError::not_allowed_here(request.pos, "request without RETURNING clause as a value")
+                        .into_err_vec(),
+                };
+                StatementSource::Request {
+                    statement,
+                    value,
+                }
+            }
         };
         Ok(result)
     }
@@ -93,6 +203,11 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for StatementSourceAST<'so
 pub enum StatementSource {
     Expression(Expression),
     Selection(Selection),
+    /// Запрос выполняется до использования значения, а значением служит его табличная переменная
+    Request {
+        statement: Box<Statement>,
+        value: Expression,
+    },
 }
 
 impl StatementSource {
@@ -100,6 +215,18 @@ impl StatementSource {
         match self {
             StatementSource::Expression(expr) => &expr.data_type,
             StatementSource::Selection(query) => &query.result_data_type,
+            StatementSource::Request { statement: _, value } => &value.data_type,
+        }
+    }
+    /// Выводит запрос, который должен выполниться до использования значения
+    pub fn fmt_request(
+        &self,
+        f: BlockFormatter<impl fmt::Write>,
+        context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        match self {
+            StatementSource::Request { statement, value: _ } => statement.fmt(f, context),
+            _ => Ok(()),
         }
     }
 }
@@ -123,6 +250,7 @@ pub enum StatementASTBody<'source> {
     Cycle {
         cycle_type: CycleTypeAST<'source>,
         body: Box<StatementAST<'source>>,
+        label: Option<Identifier<'source>>,
     },
     CycleControl {
         operator: CycleControlOperator,
@@ -131,6 +259,19 @@ pub enum StatementASTBody<'source> {
     Return {
         value: Option<StatementSourceAST<'source>>,
     },
+    /// `raise Error`, `raise Error(message)` и `raise message` разбираются как выражение,
+    /// ошибку из него выделяет `raised_error` при разрешении
+    Raise {
+        value: Option<ExpressionAST<'source>>,
+    },
+    Try {
+        body: Box<StatementAST<'source>>,
+        variable: Option<Identifier<'source>>,
+        catch_body: Box<StatementAST<'source>>,
+    },
+    Transaction {
+        body: Box<StatementAST<'source>>,
+    },
     Block {
         statements: Vec<StatementAST<'source>>,
     },
@@ -210,16 +351,21 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for StatementAST<'source>
                     Some(x) => x,
                     None => return Err(errors),
                 };
+                condition.check_constant_condition(ctx);
                 StatementBody::Condition {
                     condition,
                     then_body,
                     else_body,
                 }
             }
-            StatementASTBody::Cycle { cycle_type, body } => {
+            StatementASTBody::Cycle { cycle_type, body, label } => {
                 let mut errors = Vec::new();
-                let cycle_type = cycle_type.accumulative_resolve(ctx, &mut errors);
-                let body = body.accumulative_resolve(ctx, &mut errors);
+                let scope = match cycle_type {
+                    CycleTypeAST::Iterating { .. } => ctx.child(),
+                    _ => ctx.clone(),
+                };
+                let cycle_type = cycle_type.accumulative_resolve(&scope, &mut errors);
+                let body = body.accumulative_resolve(&scope, &mut errors);
                 let cycle_type = match cycle_type {
                     Some(x) => x,
                     None => return Err(errors),
@@ -227,7 +373,8 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for StatementAST<'source>
                 match &cycle_type {
                     CycleType::PostPredicated(predicate) => predicate.should_cast_to_type(&BOOLEAN_TYPE)?,
                     CycleType::PrePredicated(predicate) => predicate.should_cast_to_type(&BOOLEAN_TYPE)?,
-                    CycleType::Simple => {}
+                    CycleType::Simple |
+                    CycleType::Iterating { .. } => {}
                 }
                 let body = match body {
                     Some(x) => x,
@@ -236,15 +383,13 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for StatementAST<'source>
                 StatementBody::Cycle {
                     cycle_type,
                     body,
+                    label: label.map(|label| label.to_string()),
                 }
             }
             StatementASTBody::CycleControl { operator, name } => {
-                if name.is_some() {
-                    return SemanticError::not_supported_yet(self.pos, "cycle control labels")
-                        .into_err_vec();
-                }
                 StatementBody::CycleControl {
                     operator: *operator,
+                    label: name.map(|name| name.to_string()),
                 }
             }
             StatementASTBody::Return { value } => {
@@ -253,6 +398,63 @@ impl<'source> Resolve<SyncRef<FunctionVariableScope>> for StatementAST<'source>
                     value,
                 }
             }
+            StatementASTBody::Raise { value } => {
+                let (error, message) = match value {
+                    Some(value) => match raised_error(ctx, value) {
+                        Some((error, message)) => (Some(error), message),
+                        None => (None, Some(value)),
+                    },
+                    None => (None, None),
+                };
+                let message: Option<Expression> = match message {
+                    Some(message) => Some(message.resolve(ctx)?),
+                    None => None,
+                };
+                if let Some(message) = &message {
+                    message.should_cast_to_type(&TEXT_TYPE)?;
+                }
+                StatementBody::Raise {
+                    error,
+                    message,
+                }
+            }
+            StatementASTBody::Try { body, variable, catch_body } => {
+                let mut errors = Vec::new();
+                let body = body.accumulative_resolve(ctx, &mut errors);
+                let catch_scope = ctx.child();
+                let variable = match variable {
+                    Some(name) => match catch_scope.new_variable(name.item_pos(), name.to_string(), Some(caught_error_type())) {
+                        Ok(var) => {
+                            var.make_read_only();
+                            Some(var)
+                        }
+                        Err(error) => {
+                            errors.push(error);
+                            None
+                        }
+                    },
+                    None => None,
+                };
+                let catch_body = catch_body.accumulative_resolve(&catch_scope, &mut errors);
+                let (body, catch_body) = match (body, catch_body) {
+                    (Some(body), Some(catch_body)) if errors.is_empty() => (body, catch_body),
+                    _ => return Err(errors),
+                };
+                StatementBody::Try {
+                    body,
+                    variable,
+                    catch_body,
+                }
+            }
+            StatementASTBody::Transaction { body } => {
+                if ctx.is_lite_weight() {
+                    return SemanticError::not_allowed_inside(self.pos, "transaction", "lite-weight function")
+                        .into_err_vec();
+                }
+                StatementBody::Transaction {
+                    body: body.resolve(ctx)?,
+                }
+            }
             StatementASTBody::Block { statements } => {
                 let scope = ctx.child();
                 let mut result = Vec::with_capacity(statements.len());
@@ -349,13 +551,27 @@ pub enum StatementBody {
     Cycle {
         cycle_type: CycleType,
         body: Box<Statement>,
+        label: Option<String>,
     },
     CycleControl {
         operator: CycleControlOperator,
+        label: Option<String>,
     },
     Return {
         value: Option<StatementSource>,
     },
+    Raise {
+        error: Option<SyncRef<Item>>,
+        message: Option<Expression>,
+    },
+    Try {
+        body: Box<Statement>,
+        variable: Option<SyncRef<FunctionVariable>>,
+        catch_body: Box<Statement>,
+    },
+    Transaction {
+        body: Box<Statement>,
+    },
     Block {
         statements: Vec<Statement>,
     },
@@ -380,10 +596,22 @@ pub struct Statement {
 }
 
 impl Statement {
+    /// Возвращаемые строки запроса записи, обновления или удаления
+    pub fn returning(&self) -> Option<&Returning> {
+        match &self.body {
+            StatementBody::DeletingRequest { request } => request.returning.as_ref(),
+            StatementBody::InsertingRequest { request } => request.returning.as_ref(),
+            StatementBody::UpdatingRequest { request } => request.returning.as_ref(),
+            _ => None,
+        }
+    }
     pub fn is_lite_weight(&self) -> bool {
         match &self.body {
             StatementBody::Nothing => true,
-            StatementBody::VariableAssignment { target: _, source: _ } => true,
+            StatementBody::VariableAssignment { target: _, source } => match source {
+                StatementSource::Request { statement, value: _ } => statement.is_lite_weight(),
+                _ => true,
+            },
             StatementBody::Condition { condition, then_body, else_body } => {
                 let is_else_body_lite_weight = match else_body {
                     Some(body) => body.is_lite_weight(),
@@ -393,21 +621,34 @@ impl Statement {
                     && condition.is_lite_weight()
                     && then_body.is_lite_weight()
             }
-            StatementBody::Cycle { cycle_type, body } => {
+            StatementBody::Cycle { cycle_type, body, label: _ } => {
                 let is_predicate_lite_weight = match cycle_type {
                     CycleType::Simple => true,
                     CycleType::PostPredicated(predicate) => predicate.is_lite_weight(),
                     CycleType::PrePredicated(predicate) => predicate.is_lite_weight(),
+                    CycleType::Iterating { variable: _, source } => match source {
+                        StatementSource::Expression(expr) => expr.is_lite_weight(),
+                        StatementSource::Selection(_) => true,
+                        StatementSource::Request { statement, value: _ } => statement.is_lite_weight(),
+                    },
                 };
                 is_predicate_lite_weight
                     && body.is_lite_weight()
             }
-            StatementBody::CycleControl { operator: _ } => true,
+            StatementBody::CycleControl { operator: _, label: _ } => true,
             StatementBody::Return { value } => match value {
                 Some(StatementSource::Expression(expr)) => expr.is_lite_weight(),
-                Some(StatementSource::Selection(_)) => true,
+                // Скалярный запрос функция возвращает прямо в RETURN, а там `WITH` недоступен
+                Some(StatementSource::Selection(query)) => {
+                    !query.is_recursive() || query.result_data_type.as_array().is_some()
+                }
+                Some(StatementSource::Request { statement, value: _ }) => statement.is_lite_weight(),
                 None => true,
             },
+            // THROW и TRY...CATCH недоступны внутри функций T-SQL
+            StatementBody::Raise { .. } |
+            StatementBody::Try { .. } |
+            StatementBody::Transaction { .. } => false,
             StatementBody::Block { statements } => statements.iter()
                 .all(|stmt| stmt.is_lite_weight()),
 //            StatementBody::Expression { expression } => expression.is_lite_weight(),
@@ -417,7 +658,7 @@ impl Statement {
         }
     }
     //TODO Выражения типа, отличного от Void, должны сохранять результат своего выполнения.
-    pub fn jumping_check(&self, pos: StatementFlowControlPosition, return_data_type: &DataType) -> Result<StatementFlowControlJumping, Vec<SemanticError>> {
+    pub fn jumping_check(&self, pos: &StatementFlowControlPosition, return_data_type: &DataType) -> Result<StatementFlowControlJumping, Vec<SemanticError>> {
         match &self.body {
             StatementBody::VariableAssignment { target: _, source: _ } => Ok(StatementFlowControlJumping::Nothing),
             StatementBody::Condition { condition: _, then_body, else_body } => {
@@ -439,26 +680,70 @@ impl Statement {
                     }
                 }
             }
-            StatementBody::Cycle { cycle_type: _, body } => {
-                body.jumping_check(pos.in_cycle(), return_data_type)
+            StatementBody::Cycle { cycle_type: _, body, label } => {
+                if let Some(label) = label {
+                    if pos.has_label(label) {
+                        return SemanticError::duplicate_definition(self.pos, label.clone(), SemanticItemType::Label)
+                            .into_err_vec();
+                    }
+                }
+                body.jumping_check(&pos.in_cycle(label.as_ref().map(|label| label.as_str())), return_data_type)
             }
-            StatementBody::CycleControl { operator } => {
+            StatementBody::CycleControl { operator, label } => {
+                if pos.is_leaving_transaction(label.as_ref().map(|label| label.as_str())) {
+                    return SemanticError::not_allowed_here(self.pos, "cycle control operators leaving transaction")
+                        .into_err_vec();
+                }
                 if !pos.is_in_cycle() {
                     return SemanticError::not_allowed_here(self.pos, "cycle control operators")
                         .into_err_vec();
                 }
+                if let Some(label) = label {
+                    if !pos.has_label(label) {
+                        return SemanticError::not_in_scope(self.pos, format!("'{}", label))
+                            .into_err_vec();
+                    }
+                }
                 match operator {
                     CycleControlOperator::Break => Ok(StatementFlowControlJumping::AlwaysBreaks),
                     CycleControlOperator::Continue => Ok(StatementFlowControlJumping::AlwaysContinues),
                 }
             }
             StatementBody::Return { value } => {
+                // Наборы записей собираются из компонент кортежа, а не из одной выборки
+                if let (Some(StatementSource::Selection(_)), Some(_)) = (value, return_data_type.as_record_sets()) {
+                    return SemanticError::not_allowed_here(self.pos, "returning selection as record sets")
+                        .into_err_vec();
+                }
                 match value {
                     Some(value) => value.type_of().should_cast_to(self.pos, return_data_type)?,
                     None => DataType::Void.should_cast_to(self.pos, return_data_type)?,
                 }
+                if let Some(StatementSource::Selection(query)) = value {
+                    query.result_data_type.should_keep_nulls_in(self.pos, return_data_type)?;
+                }
                 Ok(StatementFlowControlJumping::AlwaysReturns)
             }
+            StatementBody::Raise { error, message } => {
+                if error.is_none() && message.is_none() && !pos.is_in_catch() {
+                    return SemanticError::not_allowed_here(self.pos, "re-raising outside of catch block")
+                        .into_err_vec();
+                }
+                Ok(StatementFlowControlJumping::AlwaysReturns)
+            }
+            StatementBody::Try { body, variable: _, catch_body } => {
+                let body_jumping = body.jumping_check(pos, return_data_type);
+                let catch_body_jumping = catch_body.jumping_check(&pos.in_catch(), return_data_type);
+                match (body_jumping, catch_body_jumping) {
+                    (Ok(body_jumping), Ok(catch_body_jumping)) => Ok(body_jumping + catch_body_jumping),
+                    (Err(mut body_errors), Err(mut catch_body_errors)) => {
+                        body_errors.append(&mut catch_body_errors);
+                        Err(body_errors)
+                    }
+                    (Err(errors), _) | (_, Err(errors)) => Err(errors),
+                }
+            }
+            StatementBody::Transaction { body } => body.jumping_check(&pos.in_transaction(), return_data_type),
             StatementBody::Block { statements } => {
                 let mut result = StatementFlowControlJumping::Nothing;
                 let mut errors = Vec::new();
@@ -581,8 +866,8 @@ impl Statement {
                 while let Some(primitive) = primitives.next() {
                     let mut line = sub_f.line()?;
                     match argument.get_property_or_wrap(primitive.path.as_path()) {
-                        Some(sub_expr) => sub_expr.fmt(&mut line, context)?,
-                        None => argument.fmt(&mut line, context)?,
+                        Some(sub_expr) => sub_expr.fmt_value(&mut line, context)?,
+                        None => argument.fmt_value(&mut line, context)?,
                     }
                     if var_guard.is_some() || arguments.peek().is_some() || primitives.peek().is_some() {
                         line.write_char(',')?;
@@ -684,6 +969,9 @@ impl Statement {
                 None => Err(target_data_type.as_primitive()),
             }
         };
+        if let StatementSource::Selection(query) = source {
+            query.fmt_with_clause(f.clone(), context)?;
+        }
         match target_data_type_as_complex {
             Ok(primitives) => {
                 let select_wrapper = {
@@ -708,7 +996,8 @@ impl Statement {
 
                 let mut source_f = f.sub_block();
                 match source {
-                    StatementSource::Expression(expr) => {
+                    StatementSource::Expression(expr) |
+                    StatementSource::Request { statement: _, value: expr } => {
                         source_f.write_line(select_wrapper)?;
                         let mut sub_f = source_f.sub_block();
                         {
@@ -718,12 +1007,12 @@ impl Statement {
                         source_f.write_line("AS t;")
                     }
                     StatementSource::Selection(query) => {
-                        source_f.write_line("(")?;
                         if query.result_data_type == *target_data_type {
+                            source_f.write_line("(")?;
                             query.fmt(source_f.sub_block(), context)?;
                             source_f.write_line(");")
                         } else {
-                            source_f.write_line(select_wrapper)?;
+                            source_f.write_line(format_args!("{} (", select_wrapper))?;
                             query.fmt(source_f.sub_block(), context)?;
                             source_f.write_line(") as t;")
                         }
@@ -732,14 +1021,20 @@ impl Statement {
             }
             Err(Some(_)) => {
                 match source {
-                    StatementSource::Expression(expr) => {
+                    StatementSource::Expression(expr) |
+                    StatementSource::Request { statement: _, value: expr } => {
                         let mut line = f.line()?;
                         write!(line, "SET @{} = ", target_path)?;
-                        expr.fmt(&mut line, context)?;
+                        expr.fmt_value(&mut line, context)?;
                         line.write_char(';')?;
                     }
                     StatementSource::Selection(query) => {
-                        f.write_line(format_args!("SET @{} = (", target_path))?;
+                        // После `WITH` может идти только запрос, поэтому присваивание делается через SELECT
+                        if query.with_clause.is_empty() {
+                            f.write_line(format_args!("SET @{} = (", target_path))?;
+                        } else {
+                            f.write_line(format_args!("SELECT @{} = (", target_path))?;
+                        }
                         query.fmt(f.sub_block(), context)?;
                         f.write_line(");")?;
                     }
@@ -765,7 +1060,8 @@ impl Statement {
                 }
 
                 match source {
-                    StatementSource::Expression(expr) => {
+                    StatementSource::Expression(expr) |
+                    StatementSource::Request { statement: _, value: expr } => {
                         sub_f.write_line("FROM")?;
                         expr.fmt(&mut sub_sub_f.line()?, context)?;
                         sub_f.write_line(" as t;")
@@ -779,6 +1075,244 @@ impl Statement {
             }
         }
     }
+    /// Выбирает примитивы `item_data_type` из источника, обёрнутого в производную таблицу `t`
+    fn fmt_derived_table_selection(
+        mut f: BlockFormatter<impl fmt::Write>,
+        item_data_type: &DataType,
+        source: &StatementSource,
+        end: &str,
+        context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        {
+            let mut line = f.line()?;
+            item_data_type.fmt_derived_table_selection(&mut line)?;
+        }
+        match source {
+            StatementSource::Expression(expr) |
+            StatementSource::Request { statement: _, value: expr } => {
+                let mut expr_f = f.sub_block();
+                let mut line = expr_f.line()?;
+                expr.fmt(&mut line, context)?;
+            }
+            StatementSource::Selection(query) => {
+                f.write_line("(")?;
+                query.fmt(f.sub_block(), context)?;
+                f.write_line(")")?;
+            }
+        }
+        f.write_line(end)
+    }
+    /// Каждый компонент результата выбирается отдельным набором записей
+    pub fn fmt_record_sets(
+        mut f: BlockFormatter<impl fmt::Write>,
+        record_sets: &[&DataType],
+        source: &StatementSource,
+        context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        let expr = match source {
+            StatementSource::Expression(expr) |
+            StatementSource::Request { statement: _, value: expr } => expr,
+            StatementSource::Selection(_) => unreachable!("Selection returned as record sets should be rejected at semantic-check-time"),
+        };
+        for (i, item_data_type) in record_sets.iter().enumerate() {
+            let mut component_path = PathBuf::new("#");
+            component_path.push_fmt(format_args!("component{}", i))?;
+            let component = expr.get_property_or_wrap(component_path.as_path())
+                .expect("Components of the result should be checked at semantic-check-time");
+            Statement::fmt_derived_table_selection(
+                f.clone(),
+                item_data_type,
+                &StatementSource::Expression(component),
+                "AS t;",
+                context,
+            )?;
+        }
+        Ok(())
+    }
+    /// Массив внутри структуры хранится в переменной JSON-строкой
+    pub fn fmt_json_assignment(
+        mut f: BlockFormatter<impl fmt::Write>,
+        target_path: &str,
+        item_data_type: &DataType,
+        source: &StatementSource,
+        context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        match source {
+            StatementSource::Selection(query) if !query.with_clause.is_empty() => {
+                query.fmt_with_clause(f.clone(), context)?;
+                f.write_line(format_args!("SELECT @{} = (", target_path))?;
+            }
+            _ => f.write_line(format_args!("SET @{} = (", target_path))?,
+        }
+        let mut sub_f = f.sub_block();
+        Statement::fmt_derived_table_selection(sub_f.clone(), item_data_type, source, "AS t", context)?;
+        sub_f.write_line("FOR JSON PATH, INCLUDE_NULL_VALUES")?;
+        f.write_line(");")
+    }
+    /// Метка продолжения помеченного цикла ставится перед проверкой его условия.
+    /// Цикл-перебор выбирает записи курсором по одной.
+    pub fn fmt_cycle(
+        mut f: BlockFormatter<impl fmt::Write>,
+        cycle_type: &CycleType,
+        body: &Statement,
+        labels: Option<&TSQLCycleLabels>,
+        context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        match cycle_type {
+            CycleType::Simple => match labels {
+                Some(labels) => {
+                    f.write_line("WHILE 1 = 1 BEGIN")?;
+                    let mut sub_f = f.sub_block();
+                    sub_f.write_line(format_args!("{}:", labels.continue_label))?;
+                    body.fmt(sub_f, context)?;
+                    f.write_line("END")
+                }
+                None => {
+                    f.write_line("WHILE 1 = 1")?;
+                    body.fmt(f.sub_block(), context)
+                }
+            },
+            CycleType::PrePredicated(predicate) => {
+                f.write_line("WHILE 1 = 1 BEGIN")?;
+                let mut buffer = String::new();
+                let mut sub_f = f.sub_block();
+                if let Some(labels) = labels {
+                    sub_f.write_line(format_args!("{}:", labels.continue_label))?;
+                }
+                Statement::fmt_something_with_pre_calls(
+                    sub_f.clone(),
+                    &mut buffer,
+                    context,
+                    |mut buffer_f, context| {
+                        let mut predicate_line = buffer_f.line()?;
+                        predicate_line.write_str("IF ")?;
+                        predicate.fmt_condition(&mut predicate_line, context)
+                    },
+                )?;
+                body.fmt(sub_f.clone(), context)?;
+                sub_f.write_line("ELSE BREAK;")?;
+                f.write_line("END")
+            }
+            CycleType::PostPredicated(predicate) => {
+                f.write_line("WHILE 1 = 1 BEGIN")?;
+                let mut sub_f = f.sub_block();
+                body.fmt(sub_f.clone(), context)?;
+                if let Some(labels) = labels {
+                    sub_f.write_line(format_args!("{}:", labels.continue_label))?;
+                }
+                let mut buffer = String::new();
+                Statement::fmt_something_with_pre_calls(
+                    sub_f.clone(),
+                    &mut buffer,
+                    context,
+                    |mut buffer_f, context| {
+                        let mut predicate_line = buffer_f.line()?;
+                        predicate_line.write_str("IF NOT ")?;
+                        predicate.fmt_condition(&mut predicate_line, context)?;
+                        predicate_line.write_str(" BREAK;")
+                    },
+                )?;
+                f.write_line("END")
+            }
+            CycleType::Iterating { variable, source } => {
+                source.fmt_request(f.clone(), context)?;
+                let var_guard = variable.read();
+                let item_data_type = var_guard.data_type()
+                    .expect("Variable cannot have undefined data-type at generate-time");
+                let cursor_name = context.names.add_name(format!("{}_cursor", var_guard.name()));
+                context.set_cycle_cursor(cursor_name.clone());
+                f.write_line(format_args!("DECLARE @{} CURSOR;", cursor_name))?;
+                let mut buffer = String::new();
+                Statement::fmt_something_with_pre_calls(
+                    f.clone(),
+                    &mut buffer,
+                    context,
+                    |mut buffer_f, context| {
+                        buffer_f.write_line(format_args!("SET @{} = CURSOR FAST_FORWARD FOR", cursor_name))?;
+                        Statement::fmt_derived_table_selection(
+                            buffer_f.sub_block(),
+                            item_data_type,
+                            source,
+                            "AS t;",
+                            context,
+                        )
+                    },
+                )?;
+                f.write_line(format_args!("OPEN @{};", cursor_name))?;
+                f.write_line("WHILE 1 = 1 BEGIN")?;
+                {
+                    let mut sub_f = f.sub_block();
+                    if let Some(labels) = labels {
+                        sub_f.write_line(format_args!("{}:", labels.continue_label))?;
+                    }
+                    {
+                        let mut line = sub_f.line()?;
+                        write!(line, "FETCH NEXT FROM @{} INTO ", cursor_name)?;
+                        let mut prefix = PathBuf::new("#");
+                        prefix.push(var_guard.name());
+                        let mut primitives = item_data_type.primitives(prefix)
+                            .into_iter()
+                            .peekable();
+                        while let Some(primitive) = primitives.next() {
+                            write!(line, "@{}", primitive.path)?;
+                            if primitives.peek().is_some() {
+                                line.write_str(", ")?;
+                            }
+                        }
+                        line.write_char(';')?;
+                    }
+                    sub_f.write_line("IF @@FETCH_STATUS <> 0 BREAK;")?;
+                    body.fmt(sub_f, context)?;
+                }
+                f.write_line("END")?;
+                f.write_line(format_args!("CLOSE @{};", cursor_name))?;
+                f.write_line(format_args!("DEALLOCATE @{};", cursor_name))
+            }
+        }
+    }
+    /// Вложенная транзакция становится точкой сохранения внешней
+    pub fn fmt_transaction(
+        mut f: BlockFormatter<impl fmt::Write>,
+        body: &Statement,
+        context: &mut TSQLFunctionContext,
+    ) -> fmt::Result {
+        let count_var = context.names.add_name("tran_count".to_string());
+        let savepoint = context.names.add_name("savepoint".to_string());
+        f.write_line(format_args!("DECLARE @{} int = @@TRANCOUNT;", count_var))?;
+        f.write_line(format_args!("IF @{} = 0 BEGIN TRANSACTION;", count_var))?;
+        f.write_line(format_args!("ELSE SAVE TRANSACTION {};", savepoint))?;
+        f.write_line("BEGIN TRY")?;
+        context.transactions.push(count_var.clone());
+        {
+            let mut sub_f = f.sub_block();
+            Statement::fmt_block_without_parens(
+                sub_f.clone(),
+                context,
+                body.as_block().unwrap_or(::std::slice::from_ref(body)),
+            )?;
+            sub_f.write_line(format_args!("IF @{} = 0 AND @@TRANCOUNT > 0 COMMIT TRANSACTION;", count_var))?;
+        }
+        context.transactions.pop();
+        f.write_line("END TRY")?;
+        f.write_line("BEGIN CATCH")?;
+        {
+            let mut sub_f = f.sub_block();
+            sub_f.write_line(format_args!("IF @{} = 0 AND @@TRANCOUNT > 0 ROLLBACK TRANSACTION;", count_var))?;
+            sub_f.write_line(format_args!("ELSE IF XACT_STATE() = 1 ROLLBACK TRANSACTION {};", savepoint))?;
+            sub_f.write_line("THROW;")?;
+        }
+        f.write_line("END CATCH")
+    }
+    /// Выход из процедуры внутри транзакции должен её зафиксировать
+    pub fn fmt_transactions_commit(
+        mut f: BlockFormatter<impl fmt::Write>,
+        context: &TSQLFunctionContext,
+    ) -> fmt::Result {
+        for count_var in context.transactions.iter().rev() {
+            f.write_line(format_args!("IF @{} = 0 AND @@TRANCOUNT > 0 COMMIT TRANSACTION;", count_var))?;
+        }
+        Ok(())
+    }
     pub fn fmt(
         &self,
         mut f: BlockFormatter<impl fmt::Write>,
@@ -787,6 +1321,7 @@ impl Statement {
         match &self.body {
             StatementBody::Nothing => Ok(()),
             StatementBody::VariableAssignment { target, source } => {
+                source.fmt_request(f.clone(), context)?;
                 let var_guard = target.var.read();
                 let data_type = var_guard.data_type()
                     .expect("Variable cannot have undefined data-type at generate-time");
@@ -794,6 +1329,17 @@ impl Statement {
                     .expect("Property path should be checked at semantic-check-time");
                 let mut var_path = target.property.as_path().into_new_buf("#");
                 var_path.push_front(var_guard.name());
+                if !target.property.as_path().is_empty() {
+                    if let Some(sub_type) = data_type.as_array() {
+                        return Statement::fmt_json_assignment(
+                            f,
+                            &var_path.data,
+                            sub_type,
+                            source,
+                            context,
+                        );
+                    }
+                }
                 Statement::fmt_assignment(
                     f,
                     &var_path.data,
@@ -804,6 +1350,13 @@ impl Statement {
                 )
             }
             StatementBody::Condition { condition, then_body, else_body } => {
+                // Ветвь, которая никогда не выполнится, не попадает в результат
+                match (condition.as_boolean(), else_body) {
+                    (Some(true), _) => return then_body.fmt(f, context),
+                    (Some(false), Some(else_body)) => return else_body.fmt(f, context),
+                    (Some(false), None) => return Ok(()),
+                    (None, _) => {}
+                }
                 Statement::fmt_something_with_pre_calls(
                     f.clone(),
                     &mut String::new(),
@@ -811,7 +1364,7 @@ impl Statement {
                     |mut f, context| {
                         let mut cond_line = f.line()?;
                         cond_line.write_str("IF ")?;
-                        condition.fmt(&mut cond_line, context)
+                        condition.fmt_condition(&mut cond_line, context)
                     },
                 )?;
                 then_body.fmt(f.sub_block(), context)?;
@@ -821,60 +1374,56 @@ impl Statement {
                 }
                 Ok(())
             }
-            StatementBody::Cycle { cycle_type: CycleType::Simple, body } => {
-                f.write_line("WHILE 1 = 1")?;
-                body.fmt(f.sub_block(), context)
-            }
-            StatementBody::Cycle { cycle_type: CycleType::PrePredicated(predicate), body } => {
-                f.write_line("WHILE 1 = 1 BEGIN")?;
-                let mut buffer = String::new();
-                let mut sub_f = f.sub_block();
-                Statement::fmt_something_with_pre_calls(
-                    sub_f.clone(),
-                    &mut buffer,
-                    context,
-                    |mut buffer_f, context| {
-                        let mut predicate_line = buffer_f.line()?;
-                        predicate_line.write_str("IF ")?;
-                        predicate.fmt(&mut predicate_line, context)
-                    },
-                )?;
-                body.fmt(sub_f.clone(), context)?;
-                sub_f.write_line("ELSE BREAK;")?;
-                f.write_line("END")
-            }
-            StatementBody::Cycle { cycle_type: CycleType::PostPredicated(predicate), body } => {
-                f.write_line("WHILE 1 = 1 BEGIN")?;
-                let mut sub_f = f.sub_block();
-                body.fmt(sub_f.clone(), context)?;
-                let mut buffer = String::new();
-                Statement::fmt_something_with_pre_calls(
-                    sub_f.clone(),
-                    &mut buffer,
-                    context,
-                    |mut buffer_f, context| {
-                        let mut predicate_line = buffer_f.line()?;
-                        predicate_line.write_str("IF NOT ")?;
-                        predicate.fmt(&mut predicate_line, context)?;
-                        predicate_line.write_str(" BREAK;")
-                    },
-                )?;
-                f.write_line("END")
+            StatementBody::Cycle { cycle_type, body, label } => {
+                let labels = context.enter_cycle(label.as_ref().map(|label| label.as_str()));
+                Statement::fmt_cycle(f.clone(), cycle_type, body, labels.as_ref(), context)?;
+                context.leave_cycle();
+                if let Some(labels) = labels {
+                    f.write_line(format_args!("{}:", labels.break_label))?;
+                }
+                Ok(())
             }
-            StatementBody::CycleControl { operator } => {
-                f.write_line(match operator {
-                    CycleControlOperator::Break => "BREAK;",
-                    CycleControlOperator::Continue => "CONTINUE;",
-                })
+            StatementBody::CycleControl { operator, label } => {
+                let outer_labels = label.as_ref()
+                    .and_then(|label| context.get_outer_cycle_labels(label));
+                match outer_labels {
+                    Some(labels) => {
+                        // GOTO не проходит мимо CLOSE покидаемых циклов-переборов, поэтому их курсоры закрываются заранее
+                        let is_break = *operator == CycleControlOperator::Break;
+                        for cursor in context.get_left_cycle_cursors(&labels.name, is_break) {
+                            f.write_line(format_args!("CLOSE @{};", cursor))?;
+                            f.write_line(format_args!("DEALLOCATE @{};", cursor))?;
+                        }
+                        f.write_line(format_args!("GOTO {};", if is_break {
+                            &labels.break_label
+                        } else {
+                            &labels.continue_label
+                        }))
+                    }
+                    None => f.write_line(match operator {
+                        CycleControlOperator::Break => "BREAK;",
+                        CycleControlOperator::Continue => "CONTINUE;",
+                    }),
+                }
             }
             StatementBody::Return { value } => {
                 if let Some(value) = value {
-                    if context.function.result.as_primitive().is_some() {
+                    value.fmt_request(f.clone(), context)?;
+                }
+                Statement::fmt_transactions_commit(f.clone(), context)?;
+                if let (Some(value), Some(record_sets)) = (value, context.function.result.as_record_sets()) {
+                    Statement::fmt_record_sets(f.clone(), &record_sets, value, context)?;
+                    return f.write_line("RETURN;");
+                }
+                if let Some(value) = value {
+                    // Процедура возвращает значение через OUTPUT-параметр, а RETURN в ней только завершает выполнение
+                    if context.function.is_lite_weight && context.function.result.as_primitive().is_some() {
                         match value {
-                            StatementSource::Expression(expr) => {
+                            StatementSource::Expression(expr) |
+                            StatementSource::Request { statement: _, value: expr } => {
                                 let mut line = f.line()?;
                                 line.write_str("RETURN ")?;
-                                expr.fmt(&mut line, context)?;
+                                expr.fmt_value(&mut line, context)?;
                                 line.write_char(';')?;
                             }
                             StatementSource::Selection(selection) => {
@@ -895,6 +1444,7 @@ impl Statement {
                             value,
                             context,
                         )?;
+                        f.write_line("RETURN;")?;
                     }
                 } else {
                     if context.function.is_lite_weight {
@@ -905,6 +1455,67 @@ impl Statement {
                 }
                 Ok(())
             }
+            StatementBody::Raise { error, message } => {
+                if error.is_none() && message.is_none() {
+                    return f.write_line("THROW;");
+                }
+                let (number, default_message) = match error {
+                    Some(error) => {
+                        let error_guard = error.read();
+                        let def = error_guard.get_error()
+                            .expect("Raised item should be an error at generate-time");
+                        // Текст подставляется в строковый литерал T-SQL, где апостроф удваивается
+                        (def.number, def.path.data.replace('\'', "''"))
+                    }
+                    None => (DEFAULT_RAISE_ERROR_NUMBER, String::new()),
+                };
+                match message {
+                    Some(message) => {
+                        // THROW принимает только литералы и переменные
+                        let message_var = context.names.add_name("message".to_string());
+                        Statement::fmt_something_with_pre_calls(
+                            f.clone(),
+                            &mut String::new(),
+                            context,
+                            |mut buffer_f, context| {
+                                let mut line = buffer_f.line()?;
+                                write!(line, "DECLARE @{} nvarchar(2048) = ", message_var)?;
+                                message.fmt(&mut line, context)?;
+                                line.write_char(';')
+                            },
+                        )?;
+                        f.write_line(format_args!("THROW {}, @{}, 1;", number, message_var))
+                    }
+                    None => f.write_line(format_args!("THROW {}, N'{}', 1;", number, default_message)),
+                }
+            }
+            StatementBody::Try { body, variable, catch_body } => {
+                f.write_line("BEGIN TRY")?;
+                Statement::fmt_block_without_parens(
+                    f.sub_block(),
+                    context,
+                    body.as_block().unwrap_or(::std::slice::from_ref(&**body)),
+                )?;
+                f.write_line("END TRY")?;
+                f.write_line("BEGIN CATCH")?;
+                {
+                    let mut sub_f = f.sub_block();
+                    if let Some(variable) = variable {
+                        let var_guard = variable.read();
+                        sub_f.write_line(format_args!("SET @{}#number = ERROR_NUMBER();", var_guard.name()))?;
+                        sub_f.write_line(format_args!("SET @{}#message = ERROR_MESSAGE();", var_guard.name()))?;
+                    }
+                    Statement::fmt_block_without_parens(
+                        sub_f,
+                        context,
+                        catch_body.as_block().unwrap_or(::std::slice::from_ref(&**catch_body)),
+                    )?;
+                }
+                f.write_line("END CATCH")
+            }
+            StatementBody::Transaction { body } => {
+                Statement::fmt_transaction(f, body, context)
+            }
             StatementBody::Block { statements } => {
                 Statement::fmt_block(f, context, statements)
             }
@@ -16,28 +16,44 @@
 
     post_predicated_cycle = "do" block "while" expression
 
+    iterating_cycle = "for" identifier "in" (selection | expression) block
+
+    cycle = [label ":"] (simple_cycle | pre_predicated_cycle | post_predicated_cycle | iterating_cycle)
+
     cycle_control =
-        | "break" [identifier]
-        | "continue" [identifier]
+        | "break" [label]
+        | "continue" [label]
 
     return_stmt = "return" [expression]
 
+    raise_stmt =
+        | "raise" module_path ["(" expression ")"]
+        | "raise" expression
+        | "raise"
+
+    try_stmt = "try" block "catch" [identifier] block
+
+    transaction_stmt = "transaction" block
+
     block = "{" list(statement, ";") "}"
 
     statement =
         | variable_definition
         | variable_assignment
         | condition
-        | simple_cycle
-        | pre_predicated_cycle
-        | post_predicated_cycle
+        | cycle
         | cycle_control
         | return_stmt
+        | raise_stmt
+        | try_stmt
+        | transaction_stmt
         | block
         | expression
     ```
 
-    Правила `list` и `identifier` определены в модуле `parser_basics`.
+    Правила `list`, `identifier` и `label` определены в модуле `parser_basics`.
+
+    Правило `module_path` определено в модуле `language::others`.
 
     Правило `expression` определено в модуле `language::expressions`.
 */
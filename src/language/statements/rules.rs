@@ -12,6 +12,7 @@ use parser_basics::{
     identifier,
     item_position,
     keyword,
+    label,
     list,
     ParserResult,
     symbol_position,
@@ -21,7 +22,13 @@ use super::*;
 
 parser_rule!(stmt_source(i) -> StatementSourceAST<'source> {
     alt!(i,
-        selection => { |x| StatementSourceAST::Selection(x) }
+        do_parse!(
+            begin: symbol_position >>
+            body: request >>
+            pos: apply!(item_position, begin) >>
+            (StatementSourceAST::Request(Box::new(StatementAST { body, pos })))
+        )
+        | selection => { |x| StatementSourceAST::Selection(x) }
         | expression => { |x| StatementSourceAST::Expression(x) }
     )
 });
@@ -78,38 +85,61 @@ parser_rule!(condition(i) -> StatementASTBody<'source> {
     )
 });
 
-parser_rule!(simple_cycle(i) -> StatementASTBody<'source> {
+parser_rule!(simple_cycle(i) -> (CycleTypeAST<'source>, Box<StatementAST<'source>>) {
     do_parse!(i,
         apply!(keyword, "loop") >>
         body: map!(block, |stmt| Box::new(stmt)) >>
-        (StatementASTBody::Cycle {
-            cycle_type: CycleTypeAST::Simple,
-            body,
-        })
+        ((CycleTypeAST::Simple, body))
     )
 });
 
-parser_rule!(pre_predicated_cycle(i) -> StatementASTBody<'source> {
+parser_rule!(pre_predicated_cycle(i) -> (CycleTypeAST<'source>, Box<StatementAST<'source>>) {
     do_parse!(i,
         apply!(keyword, "while") >>
         predicate: expression >>
         body: map!(block, |stmt| Box::new(stmt)) >>
-        (StatementASTBody::Cycle {
-            cycle_type: CycleTypeAST::PrePredicated(predicate),
-            body,
-        })
+        ((CycleTypeAST::PrePredicated(predicate), body))
     )
 });
 
-parser_rule!(post_predicated_cycle(i) -> StatementASTBody<'source> {
+parser_rule!(post_predicated_cycle(i) -> (CycleTypeAST<'source>, Box<StatementAST<'source>>) {
     do_parse!(i,
         apply!(keyword, "do") >>
         body: map!(block, |stmt| Box::new(stmt)) >>
         apply!(keyword, "while") >>
         predicate: expression >>
+        ((CycleTypeAST::PostPredicated(predicate), body))
+    )
+});
+
+parser_rule!(iterating_cycle(i) -> (CycleTypeAST<'source>, Box<StatementAST<'source>>) {
+    do_parse!(i,
+        apply!(keyword, "for") >>
+        variable: identifier >>
+        apply!(keyword, "in") >>
+        source: stmt_source >>
+        body: map!(block, |stmt| Box::new(stmt)) >>
+        ((CycleTypeAST::Iterating { variable, source }, body))
+    )
+});
+
+parser_rule!(cycle(i) -> StatementASTBody<'source> {
+    do_parse!(i,
+        label: opt!(do_parse!(
+            label: label >>
+            apply!(symbols, ":") >>
+            (label)
+        )) >>
+        cycle: alt!(
+            simple_cycle
+            | pre_predicated_cycle
+            | post_predicated_cycle
+            | iterating_cycle
+        ) >>
         (StatementASTBody::Cycle {
-            cycle_type: CycleTypeAST::PostPredicated(predicate),
-            body,
+            cycle_type: cycle.0,
+            body: cycle.1,
+            label,
         })
     )
 });
@@ -120,7 +150,7 @@ parser_rule!(cycle_control(i) -> StatementASTBody<'source> {
             apply!(keyword, "break") => { |_| CycleControlOperator::Break }
             | apply!(keyword, "continue") => { |_| CycleControlOperator::Continue }
         ) >>
-        name: opt!(identifier) >>
+        name: opt!(label) >>
         (StatementASTBody::CycleControl {
             operator,
             name,
@@ -138,6 +168,41 @@ parser_rule!(return_stmt(i) -> StatementASTBody<'source> {
     )
 });
 
+parser_rule!(raise_stmt(i) -> StatementASTBody<'source> {
+    do_parse!(i,
+        apply!(keyword, "raise") >>
+        value: opt!(expression) >>
+        (StatementASTBody::Raise {
+            value,
+        })
+    )
+});
+
+parser_rule!(try_stmt(i) -> StatementASTBody<'source> {
+    do_parse!(i,
+        apply!(keyword, "try") >>
+        body: map!(block, |stmt| Box::new(stmt)) >>
+        apply!(keyword, "catch") >>
+        variable: opt!(identifier) >>
+        catch_body: map!(block, |stmt| Box::new(stmt)) >>
+        (StatementASTBody::Try {
+            body,
+            variable,
+            catch_body,
+        })
+    )
+});
+
+parser_rule!(transaction_stmt(i) -> StatementASTBody<'source> {
+    do_parse!(i,
+        apply!(keyword, "transaction") >>
+        body: map!(block, |stmt| Box::new(stmt)) >>
+        (StatementASTBody::Transaction {
+            body,
+        })
+    )
+});
+
 parser_rule!(pub block(i) -> StatementAST<'source> {
     do_parse!(i,
         begin: symbol_position >>
@@ -175,11 +240,12 @@ pub fn statement<'token, 'source>(input: &'token [Token<'source>]) -> ParserResu
             | variable_definition
             | variable_assignment
             | condition
-            | simple_cycle
-            | pre_predicated_cycle
-            | post_predicated_cycle
+            | cycle
             | cycle_control
             | return_stmt
+            | raise_stmt
+            | try_stmt
+            | transaction_stmt
             | block => { |x: StatementAST<'source>| x.body }
             | expr
         ) >>
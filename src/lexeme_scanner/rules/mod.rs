@@ -9,8 +9,9 @@ pub mod symbol_group;
 pub mod whitespace;
 pub mod word;
 
-/// Композитор всех прочих "правил", расположенных в этом модуле
-pub fn scan(input: &[u8]) -> BatcherResult {
+/// Композитор всех прочих "правил", расположенных в этом модуле.
+/// `previous` - текст предыдущего значимого токена, нужен для распознавания меток циклов.
+pub fn scan(input: &[u8], previous: Option<&str>) -> BatcherResult {
     if input.len() == 0 {
         #[cfg(feature = "parser_trace")] trace!("Scanner found end of the input");
         return Ok((TokenKind::EndOfInput, 0));
@@ -22,6 +23,9 @@ pub fn scan(input: &[u8]) -> BatcherResult {
     } else if peek == '"' {
         #[cfg(feature = "parser_trace")] trace!("Scanner found a string literal");
         string::string(input, '"', TokenKindLess::StringLiteral)
+    } else if word::is_label_begin(input, previous) {
+        #[cfg(feature = "parser_trace")] trace!("Scanner found a label");
+        word::label(input)
     } else if peek == '\'' {
         #[cfg(feature = "parser_trace")] trace!("Scanner found a braced literal");
         string::string(input, '\'', TokenKindLess::BracedExpressionLiteral)
@@ -33,3 +33,62 @@ pub fn word(input: &[u8]) -> BatcherResult {
     }
     Ok((TokenKind::Word, result))
 }
+
+/// Ключевые слова, которыми начинаются циклы
+const CYCLE_KEYWORDS: [&str; 4] = ["loop", "while", "do", "for"];
+
+fn skip_whitespace(input: &[u8], mut i: usize) -> usize {
+    while (i < input.len()) && (input[i] as char).is_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+fn skip_identifier(input: &[u8], mut i: usize) -> usize {
+    while (i < input.len()) && (is_letter(input[i] as char) || is_identifier_symbol(input[i] as char)) {
+        i += 1;
+    }
+    i
+}
+
+/**
+    Функция-тест, проверяющая является ли ввод меткой цикла, т.е. апострофом со словом.
+    Решение принимается по предыдущему токену `previous`:
+    метка используется сразу после `break` или `continue`,
+    а объявляется в начале оператора (после `{`, `}`, `;` или в начале ввода)
+    и обязательно сопровождается `:` и ключевым словом цикла.
+    Во всех прочих случаях апостроф открывает литерал выражения.
+*/
+pub fn is_label_begin(input: &[u8], previous: Option<&str>) -> bool {
+    if input.len() < 2 || input[0] as char != '\'' || !is_letter(input[1] as char) {
+        return false;
+    }
+    match previous {
+        Some("break") | Some("continue") => return true,
+        None | Some("{") | Some("}") | Some(";") => {}
+        _ => return false,
+    }
+    let i = skip_whitespace(input, skip_identifier(input, 2));
+    if (i >= input.len()) || (input[i] as char != ':') {
+        return false;
+    }
+    let begin = skip_whitespace(input, i + 1);
+    if (begin >= input.len()) || !is_letter(input[begin] as char) {
+        return false;
+    }
+    let end = skip_identifier(input, begin);
+    CYCLE_KEYWORDS.iter().any(|keyword| keyword.as_bytes() == &input[begin..end])
+}
+
+/**
+    Правило "Метка".
+
+    Обрабатывает метку цикла вида `'name`, где `name` - слово.
+    Возвращает ошибку `MustBeGot` в случае, если в начале ввода не апостроф со следующей за ним буквой.
+*/
+pub fn label(input: &[u8]) -> BatcherResult {
+    assert_eq(input, 0, '\'')?;
+    let (_, length) = word(&input[1..])
+        .map_err(|(kind, length)| (kind, length + 1))?;
+    Ok((TokenKind::Label, length + 1))
+}
@@ -8,6 +8,7 @@ use super::rules::scan;
 pub struct Scanner<'a> {
     source: &'a str,
     position: SymbolPosition,
+    previous: Option<&'a str>,
     finished: bool,
 }
 
@@ -19,7 +20,7 @@ impl<'a> Iterator for Scanner<'a>
             return None;
         }
         loop {
-            let (scan_result, len) = match scan(self.source.as_bytes()) {
+            let (scan_result, len) = match scan(self.source.as_bytes(), self.previous) {
                 Ok((kind, len)) => (Ok(kind), len),
                 Err((kind, len)) => (Err(kind), len),
             };
@@ -38,6 +39,7 @@ impl<'a> Iterator for Scanner<'a>
                 self.finished = true;
             }
             if kind.is_must_not_be_ignored() {
+                self.previous = Some(text);
                 return Some(Ok(Token { kind, pos: begin, text }));
             }
         }
@@ -51,6 +53,7 @@ impl<'a> Scanner<'a> {
         Self {
             source,
             position: SymbolPosition::default(),
+            previous: None,
             finished: false,
         }
     }
@@ -49,6 +49,11 @@ pub enum TokenKind {
         Не может начинаться с чего-то, кроме буквы.
     */
     Word,
+    /**
+        Метка цикла. Генерируется сканером при нахождении апострофа (`'`) со словом (например, `'outer`)
+        после `break`/`continue` или в объявлении метки цикла (`'outer: loop`).
+    */
+    Label,
     /// Группа символов. Генерируется сканером при нахождении специального символа.
     SymbolGroup,
 }
@@ -62,6 +67,7 @@ pub enum TokenKindLess {
     StringLiteral,
     BracedExpressionLiteral,
     Word,
+    Label,
     SymbolGroup,
 }
 
@@ -74,6 +80,7 @@ impl fmt::Display for TokenKindLess {
             &TokenKindLess::StringLiteral => "string literal",
             &TokenKindLess::BracedExpressionLiteral => "braced expression literal",
             &TokenKindLess::Word => "word",
+            &TokenKindLess::Label => "label",
             &TokenKindLess::SymbolGroup => "symbol group",
         };
         write!(f, "{}", text)
@@ -125,6 +132,7 @@ impl TokenKind {
                 length: _,
             } => TokenKindLess::BracedExpressionLiteral,
             &TokenKind::Word => TokenKindLess::Word,
+            &TokenKind::Label => TokenKindLess::Label,
             &TokenKind::SymbolGroup => TokenKindLess::SymbolGroup,
         }
     }
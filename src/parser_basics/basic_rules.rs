@@ -116,6 +116,15 @@ pub fn identifier_raw<'token, 'source>(input: &'token [Token<'source>]) -> Parse
     token(input, TokenKindLess::Word)
 }
 
+/**
+    Правило "Метка".
+    Ищет токен типа `Label` и возвращает его текст без апострофа в случае успеха.
+*/
+pub fn label<'token, 'source>(input: &'token [Token<'source>]) -> ParserResult<'token, 'source, Identifier<'source>> {
+    token(input, TokenKindLess::Label)
+        .map(|token| Identifier::new(&token.text[1..], token.pos))
+}
+
 array!(pub const KEYWORD_LIST: &'static str =
     "as",
     "using",
@@ -131,6 +140,10 @@ array!(pub const KEYWORD_LIST: &'static str =
     "having",
     "order",
     "limit",
+    "union",
+    "intersect",
+    "except",
+    "returning",
     "set",
     "join",
     "value",
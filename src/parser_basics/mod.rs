@@ -22,6 +22,7 @@ pub use self::basic_rules::{
     identifier_raw,
     item_position,
     keyword,
+    label,
     none,
     not_keyword_identifier,
     number_literal,
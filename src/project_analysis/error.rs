@@ -114,6 +114,24 @@ pub enum SemanticErrorKind {
         expected: usize,
         got: usize,
     },
+    ConstantCondition {
+        value: bool,
+    },
+    AmbiguousColumn {
+        name: String,
+    },
+    PrimaryKeyIsNotInserted {
+        name: String,
+    },
+    ArrayOfPrimitivesField {
+        name: String,
+    },
+    DuplicateErrorNumber {
+        number: u32,
+        path: PathBuf,
+        other_path: PathBuf,
+        is_hash_clash: bool,
+    },
 }
 
 impl Default for SemanticErrorKind {
@@ -152,6 +170,17 @@ impl fmt::Display for SemanticErrorKind {
             SemanticErrorKind::CannotDoWithDataSource { action } => write!(f, "can't {} this data-source", action),
             SemanticErrorKind::ValueListWithWrongLength { expected, got } => write!(f, "expected value list of {} elements, got {}", expected, got),
             SemanticErrorKind::SelectWithWrongColumnCount { expected, got } => write!(f, "expected selection with {} columns, got with {}", expected, got),
+            SemanticErrorKind::ConstantCondition { value } => write!(f, "condition is always {}", value),
+            SemanticErrorKind::AmbiguousColumn { name } => write!(f, "column {} is ambiguous", name),
+            SemanticErrorKind::PrimaryKeyIsNotInserted { name } => write!(f, "primary key column {} should be inserted", name),
+            SemanticErrorKind::DuplicateErrorNumber { number, path, other_path, is_hash_clash } => {
+                write!(f, "error {} has the same number {} as error {}", path.data, number, other_path.data)?;
+                if *is_hash_clash {
+                    write!(f, ": default numbers are hashes of error paths and these ones clash, specify the number explicitly")?;
+                }
+                Ok(())
+            }
+            SemanticErrorKind::ArrayOfPrimitivesField { name } => write!(f, "field {} cannot be an array of primitives, elements of nested arrays should have named fields", name),
         }
     }
 }
@@ -290,6 +319,34 @@ impl SemanticError {
         SemanticError { pos, kind: SemanticErrorKind::SelectWithWrongColumnCount { expected, got }, text: None }
     }
     #[inline]
+    pub fn constant_condition(pos: ItemPosition, value: bool) -> Self {
+        SemanticError { pos, kind: SemanticErrorKind::ConstantCondition { value }, text: None }
+    }
+    #[inline]
+    pub fn ambiguous_column(pos: ItemPosition, name: String) -> Self {
+        SemanticError { pos, kind: SemanticErrorKind::AmbiguousColumn { name }, text: None }
+    }
+    #[inline]
+    pub fn primary_key_is_not_inserted(pos: ItemPosition, name: String) -> Self {
+        SemanticError { pos, kind: SemanticErrorKind::PrimaryKeyIsNotInserted { name }, text: None }
+    }
+    #[inline]
+    pub fn array_of_primitives_field(pos: ItemPosition, name: String) -> Self {
+        SemanticError { pos, kind: SemanticErrorKind::ArrayOfPrimitivesField { name }, text: None }
+    }
+    #[inline]
+    pub fn duplicate_error_number(pos: ItemPosition, number: u32, path: PathBuf, other_path: PathBuf, is_hash_clash: bool) -> Self {
+        SemanticError { pos, kind: SemanticErrorKind::DuplicateErrorNumber { number, path, other_path, is_hash_clash }, text: None }
+    }
+    /// Предупреждения не прерывают компиляцию, а только выводятся пользователю
+    #[inline]
+    pub fn is_warning(&self) -> bool {
+        match &self.kind {
+            SemanticErrorKind::ConstantCondition { .. } => true,
+            _ => false,
+        }
+    }
+    #[inline]
     pub fn set_text(&mut self, text: Arc<Text>) {
         self.text = Some(text);
     }
@@ -305,7 +362,7 @@ impl fmt::Display for SemanticError {
             Some(arc) => writeln!(f, "  in {} on {}", &arc.name, self.pos.begin)?,
             None => writeln!(f, "  on {}", self.pos.begin)?,
         }
-        writeln!(f, "  error: {}", self.kind)?;
+        writeln!(f, "  {}: {}", if self.is_warning() { "warning" } else { "error" }, self.kind)?;
         let text = match &self.text {
             Some(arc) => arc,
             None => return writeln!(f, "   | text is unspecified."),
@@ -5,7 +5,10 @@ use helpers::{
     Path,
 };
 use helpers::SyncRef;
-use language::DataType;
+use language::{
+    CommonTable,
+    DataType,
+};
 use lexeme_scanner::ItemPosition;
 use project_analysis::{
     Module,
@@ -26,6 +29,8 @@ pub struct FunctionVariable {
     is_read_only: bool,
     is_argument: bool,
     is_automatic: bool,
+    is_nullable: bool,
+    is_temporary: bool,
 }
 
 impl FunctionVariable {
@@ -38,6 +43,8 @@ impl FunctionVariable {
             is_read_only: false,
             is_argument: false,
             is_automatic: false,
+            is_nullable: false,
+            is_temporary: false,
         })
     }
     #[inline]
@@ -45,6 +52,10 @@ impl FunctionVariable {
         self.name.as_str()
     }
     #[inline]
+    pub fn pos(&self) -> ItemPosition {
+        self.pos
+    }
+    #[inline]
     pub fn set_name(&mut self, new_name: String) -> String {
         replace(&mut self.name, new_name)
     }
@@ -79,6 +90,20 @@ impl FunctionVariable {
     pub fn mark_as_automatic(&mut self) {
         self.is_automatic = true
     }
+    /// Строка источника может отсутствовать целиком, если он стоит на внешней стороне соединения
+    #[inline]
+    pub fn is_nullable(&self) -> bool {
+        self.is_nullable
+    }
+    #[inline]
+    pub fn mark_as_nullable(&mut self) {
+        self.is_nullable = true
+    }
+    /// Временную переменную заводит сам компилятор, в исходном тексте её имени нет
+    #[inline]
+    pub fn is_temporary(&self) -> bool {
+        self.is_temporary
+    }
 }
 
 impl SyncRef<FunctionVariable> {
@@ -122,6 +147,14 @@ impl SyncRef<FunctionVariable> {
     pub fn mark_as_automatic(&self) {
         self.write().mark_as_automatic()
     }
+    #[inline]
+    pub fn is_nullable(&self) -> bool {
+        self.read().is_nullable()
+    }
+    #[inline]
+    pub fn mark_as_nullable(&self) {
+        self.write().mark_as_nullable()
+    }
 }
 
 #[derive(Clone, PartialEq)]
@@ -130,7 +163,10 @@ pub struct FunctionVariableScope {
     parent: Option<ID>,
     context: SyncRef<FunctionContext>,
     variables: Vec<SyncRef<FunctionVariable>>,
+    common_tables: Vec<SyncRef<CommonTable>>,
     is_aggregate: bool,
+    is_window: bool,
+    is_grouped: bool,
     is_lite_weight: bool,
 }
 
@@ -142,7 +178,10 @@ impl FunctionVariableScope {
             parent,
             context,
             variables: Vec::new(),
+            common_tables: Vec::new(),
             is_aggregate: false,
+            is_window: false,
+            is_grouped: false,
             is_lite_weight: false,
         })
     }
@@ -153,6 +192,10 @@ impl FunctionVariableScope {
         self.variables.iter()
             .find(|v| v.read().name() == name)
     }
+    pub fn find_common_table(&self, name: &str) -> Option<&SyncRef<CommonTable>> {
+        self.common_tables.iter()
+            .find(|table| table.read().name == name)
+    }
 }
 
 impl SyncRef<FunctionVariableScope> {
@@ -178,6 +221,22 @@ impl SyncRef<FunctionVariableScope> {
     pub fn lite_weight_child(&self) -> Self {
         self._child(false, true)
     }
+    /// Оконные функции допустимы только в самом списке результатов выборки, поэтому этот признак не наследуется
+    pub fn window_child(&self, is_grouped: bool) -> Self {
+        let result = self.aggregate_child();
+        {
+            let mut child = result.write();
+            child.is_window = true;
+            child.is_grouped = is_grouped;
+        }
+        result
+    }
+    /// Аргументы и окно оконной функции не группирующего запроса не могут содержать агрегатные функции
+    pub fn not_aggregate_child(&self) -> Self {
+        let result = self.child();
+        result.write().is_aggregate = false;
+        result
+    }
     #[inline]
     pub fn parent(&self) -> Option<SyncRef<FunctionVariableScope>> {
         let scope = self.read();
@@ -211,9 +270,26 @@ impl SyncRef<FunctionVariableScope> {
             "t".to_string(),
         );
         let var = FunctionVariable::new(pos, name, Some(data_type));
+        var.write().is_temporary = true;
         self.write().variables.push(var.clone());
         var
     }
+    /// Общие табличные выражения (`with name as (...)`) видны запросу и всем вложенным в него
+    pub fn get_common_table(&self, name: &str) -> Option<SyncRef<CommonTable>> {
+        if let Some(table) = self.read().find_common_table(name) {
+            return Some(table.clone());
+        }
+        self.parent()?
+            .get_common_table(name)
+    }
+    pub fn new_common_table(&self, pos: ItemPosition, table: SyncRef<CommonTable>) -> Result<(), SemanticError> {
+        let name = table.read().name.clone();
+        if self.read().find_common_table(name.as_str()).is_some() {
+            return Err(SemanticError::duplicate_definition(pos, name, SemanticItemType::Table));
+        }
+        self.write().common_tables.push(table);
+        Ok(())
+    }
     #[inline]
     pub fn context(&self) -> SyncRef<FunctionContext> {
         self.read().context.clone()
@@ -227,6 +303,14 @@ impl SyncRef<FunctionVariableScope> {
         self.read().is_aggregate
     }
     #[inline]
+    pub fn is_window(&self) -> bool {
+        self.read().is_window
+    }
+    #[inline]
+    pub fn is_grouped(&self) -> bool {
+        self.read().is_grouped
+    }
+    #[inline]
     pub fn is_lite_weight(&self) -> bool {
         self.read().is_lite_weight
     }
@@ -238,7 +322,9 @@ impl fmt::Debug for FunctionVariableScope {
             .field("id", &self.id)
             .field("parent", &self.parent)
             .field("variables", &self.variables)
+            .field("common_tables", &self.common_tables)
             .field("is_aggregate", &self.is_aggregate)
+            .field("is_window", &self.is_window)
             .field("is_lite_weight", &self.is_lite_weight)
             .finish()
     }
@@ -4,7 +4,9 @@ use helpers::{
     SyncRef,
 };
 use language::{
+    ConstDefinition,
     DataTypeDefinition,
+    ErrorDefinition,
     FunctionDefinition,
     TableDefinition,
 };
@@ -33,6 +35,12 @@ pub enum ItemBody {
     Function {
         def: FunctionDefinition,
     },
+    Error {
+        def: ErrorDefinition,
+    },
+    Const {
+        def: ConstDefinition,
+    },
 }
 
 impl Item {
@@ -58,6 +66,20 @@ impl Item {
         }
     }
     #[inline]
+    pub fn error(parent: SyncRef<Module>, def: ErrorDefinition) -> Self {
+        Item {
+            parent,
+            body: ItemBody::Error { def },
+        }
+    }
+    #[inline]
+    pub fn constant(parent: SyncRef<Module>, def: ConstDefinition) -> Self {
+        Item {
+            parent,
+            body: ItemBody::Const { def },
+        }
+    }
+    #[inline]
     pub fn table(parent: SyncRef<Module>, def: TableDefinition) -> Self {
         let entity = SyncRef::new(Item::data_type(parent.clone(), DataTypeDefinition {
             name: format!("{}::entity", def.name),
@@ -79,6 +101,8 @@ impl Item {
             ItemBody::ModuleReference { module: _ } => SemanticItemType::Module,
             ItemBody::Table { def: _, entity: _, primary_key: _ } => SemanticItemType::Table,
             ItemBody::Function { def: _ } => SemanticItemType::Function,
+            ItemBody::Error { def: _ } => SemanticItemType::Error,
+            ItemBody::Const { def: _ } => SemanticItemType::Const,
         }
     }
     #[inline]
@@ -103,6 +127,20 @@ impl Item {
         }
     }
     #[inline]
+    pub fn get_error(&self) -> Option<&ErrorDefinition> {
+        match &self.body {
+            ItemBody::Error { def } => Some(def),
+            _ => None,
+        }
+    }
+    #[inline]
+    pub fn get_constant(&self) -> Option<&ConstDefinition> {
+        match &self.body {
+            ItemBody::Const { def } => Some(def),
+            _ => None,
+        }
+    }
+    #[inline]
     pub fn get_table(&self) -> Option<&TableDefinition> {
         match &self.body {
             ItemBody::Table { def, entity: _, primary_key: _ } => Some(def),
@@ -119,6 +157,8 @@ impl Item {
             }
             ItemBody::Table { def, .. } => def.name.as_str(),
             ItemBody::Function { def } => def.name.as_str(),
+            ItemBody::Error { def } => def.name.as_str(),
+            ItemBody::Const { def } => def.name.as_str(),
         };
         let parent = self.parent.read();
         let path = parent.path().read();
@@ -152,6 +192,8 @@ impl SyncRef<Item> {
                 return module.get_item(path, search_route);
             }
             ItemBody::Function { def: _ } => {}
+            ItemBody::Error { def: _ } => {}
+            ItemBody::Const { def: _ } => {}
             ItemBody::Table { def: _, entity, primary_key } => if let Some(name) = path.the_only() {
                 match name {
                     "entity" => return Some(entity.clone()),
@@ -195,6 +237,10 @@ pub enum SemanticItemType {
     Table,
     Variable,
     Function,
+    Label,
+    Error,
+    Variant,
+    Const,
 }
 
 impl SemanticItemType {
@@ -207,6 +253,10 @@ impl SemanticItemType {
             &SemanticItemType::Table => "table",
             &SemanticItemType::Variable => "variable",
             &SemanticItemType::Function => "function",
+            &SemanticItemType::Label => "label",
+            &SemanticItemType::Error => "error",
+            &SemanticItemType::Variant => "enum variant",
+            &SemanticItemType::Const => "constant",
         }
     }
 }
@@ -104,10 +104,38 @@ impl Resolve<(SyncRef<PathBuf>, SyncRef<ProjectContext>)> for UnresolvedModule {
                     None => item_names.push(borrowed_name),
                 }
             }
+            // Переменные функций перекрывали бы одноимённые константы модуля
+            for (_, item) in context.items.iter() {
+                let item_guard = item.value.read();
+                let function = match item_guard.get_function() {
+                    Some(function) => function,
+                    None => continue,
+                };
+                for var in function.context.get_all_variables() {
+                    let var_guard = var.read();
+                    if var_guard.is_temporary() {
+                        continue;
+                    }
+                    let is_constant = context.get_item(Path::new(var_guard.name(), "::"), &mut Vec::new())
+                        .map_or(false, |item| item.read().get_type() == SemanticItemType::Const);
+                    if is_constant {
+                        let mut error = SemanticError::duplicate_definition(
+                            var_guard.pos(),
+                            var_guard.name().to_string(),
+                            SemanticItemType::Const,
+                        );
+                        error.set_text(self.text.clone());
+                        errors.push(error);
+                    }
+                }
+            }
             if !errors.is_empty() {
                 return Err(errors);
             }
         }
+        for warning in context.write().warnings.iter_mut() {
+            warning.set_text(self.text.clone());
+        }
         Ok(context)
     }
 }
@@ -118,6 +146,7 @@ pub struct Module {
     path: SyncRef<PathBuf>,
     project: SyncRef<ProjectContext>,
     imported: Vec<SyncRef<Module>>,
+    warnings: Vec<SemanticError>,
 }
 
 impl Module {
@@ -128,6 +157,7 @@ impl Module {
             path,
             project,
             imported: Vec::new(),
+            warnings: Vec::new(),
         }
     }
     #[inline]
@@ -170,6 +200,10 @@ impl Module {
     pub fn path(&self) -> &SyncRef<PathBuf> {
         &self.path
     }
+    #[inline]
+    pub fn warnings(&self) -> &Vec<SemanticError> {
+        &self.warnings
+    }
 }
 
 impl SyncRef<Module> {
@@ -196,6 +230,10 @@ impl SyncRef<Module> {
         self.write().inject_import_module(module)
     }
     #[inline]
+    pub fn warn(&self, warning: SemanticError) {
+        self.write().warnings.push(warning)
+    }
+    #[inline]
     pub fn project(&self) -> SyncRef<ProjectContext> { self.read().project.clone() }
 }
 
@@ -226,9 +226,37 @@ impl<S: TextSource> Resolve<S> for SyncRef<ProjectContext> {
                     _ => {}
                 }
             }
-            Ok(result)
-        } else {
-            Err(errors)
+            errors = check_error_numbers(&result);
+            if errors.is_empty() {
+                return Ok(result);
+            }
+        }
+        Err(errors)
+    }
+}
+
+/// Номера ошибок уходят в `THROW` и клиентские `case`, поэтому должны быть уникальны в пределах всего проекта
+fn check_error_numbers(modules: &IndexMap<SyncRef<PathBuf>, SyncRef<Module>>) -> Vec<SemanticError> {
+    let mut numbers: IndexMap<u32, (PathBuf, bool)> = IndexMap::new();
+    let mut errors = Vec::new();
+    for (_, module) in modules.iter() {
+        for (_, item) in module.read().items() {
+            let item_guard = item.value.read();
+            if !item_guard.is_belongs_to(module) { continue; }
+            if let Some(error) = item_guard.get_error() {
+                if let Some((other_path, other_is_default)) = numbers.get(&error.number) {
+                    errors.push(SemanticError::duplicate_error_number(
+                        error.pos,
+                        error.number,
+                        error.path.clone(),
+                        other_path.clone(),
+                        error.is_default_number && *other_is_default,
+                    ));
+                    continue;
+                }
+                numbers.insert(error.number, (error.path.clone(), error.is_default_number));
+            }
         }
     }
+    errors
 }
@@ -3,9 +3,13 @@ use std::ops::{
     AddAssign,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StatementFlowControlPosition {
     in_cycle: bool,
+    in_catch: bool,
+    in_transaction: bool,
+    labels: Vec<String>,
+    outer_labels: Vec<String>,
 }
 
 impl StatementFlowControlPosition {
@@ -13,17 +17,57 @@ impl StatementFlowControlPosition {
     pub fn new() -> Self {
         StatementFlowControlPosition {
             in_cycle: false,
+            in_catch: false,
+            in_transaction: false,
+            labels: Vec::new(),
+            outer_labels: Vec::new(),
         }
     }
     #[inline]
-    pub fn in_cycle(mut self) -> Self {
-        self.in_cycle = true;
-        self
+    pub fn in_cycle(&self, label: Option<&str>) -> Self {
+        let mut result = self.clone();
+        result.in_cycle = true;
+        if let Some(label) = label {
+            result.labels.push(label.to_string());
+        }
+        result
     }
     #[inline]
     pub fn is_in_cycle(&self) -> bool {
         self.in_cycle
     }
+    #[inline]
+    pub fn in_catch(&self) -> Self {
+        let mut result = self.clone();
+        result.in_catch = true;
+        result
+    }
+    #[inline]
+    pub fn is_in_catch(&self) -> bool {
+        self.in_catch
+    }
+    /// Проверяет, находится ли позиция внутри цикла с данной меткой
+    #[inline]
+    pub fn has_label(&self, label: &str) -> bool {
+        self.labels.iter().any(|item| item == label)
+    }
+    /// Циклы снаружи транзакции становятся недоступны операторам управления циклом
+    #[inline]
+    pub fn in_transaction(&self) -> Self {
+        let mut result = self.clone();
+        result.in_transaction = true;
+        result.in_cycle = false;
+        result.outer_labels.append(&mut result.labels);
+        result
+    }
+    /// Проверяет, покидает ли оператор управления циклом (с меткой `label`) транзакцию
+    #[inline]
+    pub fn is_leaving_transaction(&self, label: Option<&str>) -> bool {
+        match label {
+            Some(label) => !self.has_label(label) && self.outer_labels.iter().any(|item| item == label),
+            None => self.in_transaction && !self.in_cycle,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
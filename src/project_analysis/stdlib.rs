@@ -1,6 +1,7 @@
 use helpers::SyncRef;
 use language::{
     BinaryOperator,
+    BOOLEAN_TYPE,
     DataType,
     PostfixUnaryOperator,
     PrefixUnaryOperator,
@@ -60,16 +61,29 @@ impl StdLib {
         }
         None
     }
-    pub fn resolve_binary_operation(&self, operator: BinaryOperator, left: &DataType, right: &DataType) -> Option<&Arc<StdLibBinaryOperation>> {
+    pub fn resolve_binary_operation(&self, operator: BinaryOperator, left: &DataType, right: &DataType) -> Option<Arc<StdLibBinaryOperation>> {
+        if let Some(op) = StdLib::resolve_enum_operation(operator, left, right) {
+            return Some(op);
+        }
         for element in self.elements.iter() {
             if let StdLibElement::BinaryOperation(op) = element {
                 if (op.operator == operator) && left.can_cast(&op.left) && right.can_cast(&op.right) {
-                    return Some(op);
+                    return Some(op.clone());
                 }
             }
         }
         None
     }
+    /// Перечисления сравниваются только на равенство и только со значениями того же перечисления
+    fn resolve_enum_operation(operator: BinaryOperator, left: &DataType, right: &DataType) -> Option<Arc<StdLibBinaryOperation>> {
+        if operator != BinaryOperator::Equals {
+            return None;
+        }
+        if left.as_enum()?.path != right.as_enum()?.path {
+            return None;
+        }
+        Some(Arc::new(StdLibBinaryOperation::new(operator, left.clone(), right.clone(), BOOLEAN_TYPE)))
+    }
     pub fn resolve_function(&self, name: &str) -> Option<&Arc<StdLibFunction>> {
         for element in self.elements.iter() {
             if let StdLibElement::Function(function) = element {
@@ -99,7 +113,6 @@ impl SyncRef<StdLib> {
     pub fn resolve_binary_operation(&self, operator: BinaryOperator, left: &DataType, right: &DataType) -> Option<Arc<StdLibBinaryOperation>> {
         self.read()
             .resolve_binary_operation(operator, left, right)
-            .cloned()
     }
     #[inline]
     pub fn resolve_function(&self, name: &str) -> Option<Arc<StdLibFunction>> {
@@ -173,12 +186,24 @@ impl StdLibBinaryOperation {
     }
 }
 
+/// Тип аргумента функции стандартной библиотеки
+#[derive(Debug, Clone, PartialEq)]
+pub enum StdLibArgument {
+    Typed(DataType),
+    /// Значение любого типа, общего для всех обобщённых аргументов вызова
+    Generic,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct StdLibFunction {
     pub name: String,
-    pub arguments: Vec<DataType>,
+    pub arguments: Vec<StdLibArgument>,
+    pub required_arguments: usize,
     pub output: DataType,
+    pub is_generic_output: bool,
     pub is_aggregate: bool,
+    pub is_window: bool,
+    pub is_ordered: bool,
     pub is_lite_weight: bool,
 }
 
@@ -188,21 +213,42 @@ impl StdLibFunction {
         StdLibFunction {
             name,
             arguments: Vec::new(),
+            required_arguments: 0,
             output: DataType::Void,
+            is_generic_output: false,
             is_aggregate: false,
+            is_window: false,
+            is_ordered: false,
             is_lite_weight: false,
         }
     }
     #[inline]
-    pub fn gets(mut self, arguments: Vec<DataType>) -> Self {
+    pub fn gets(self, arguments: Vec<DataType>) -> Self {
+        self.gets_arguments(arguments.into_iter().map(StdLibArgument::Typed).collect())
+    }
+    #[inline]
+    pub fn gets_arguments(mut self, arguments: Vec<StdLibArgument>) -> Self {
+        self.required_arguments = arguments.len();
         self.arguments = arguments;
         self
     }
+    /// Аргументы, начиная с указанного, можно не передавать
+    #[inline]
+    pub fn optional_from(mut self, index: usize) -> Self {
+        self.required_arguments = index;
+        self
+    }
     #[inline]
     pub fn returns(mut self, output: DataType) -> Self {
         self.output = output;
         self
     }
+    /// Результат имеет тип обобщённых аргументов (`lag`)
+    #[inline]
+    pub fn returns_generic(mut self) -> Self {
+        self.is_generic_output = true;
+        self
+    }
     #[inline]
     pub fn lite_weight(mut self) -> Self {
         self.is_lite_weight = true;
@@ -213,4 +259,16 @@ impl StdLibFunction {
         self.is_aggregate = true;
         self.lite_weight()
     }
+    /// Оконная функция (`row_number`, `lag`) вызывается только с окном `over (...)`
+    #[inline]
+    pub fn window(mut self) -> Self {
+        self.is_window = true;
+        self.lite_weight()
+    }
+    /// Ранжирующая функция и функция смещения (`row_number`, `lag`) требуют сортировки в окне
+    #[inline]
+    pub fn ordered(mut self) -> Self {
+        self.is_ordered = true;
+        self.window()
+    }
 }
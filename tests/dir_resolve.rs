@@ -6,8 +6,10 @@ use n_lang::{
     code_generation::{
         DatabaseProject,
         RPCModule,
+        python_client::PythonParamStyle,
     },
     helpers::{
+        Path as ModulePath,
         PathBuf,
         Resolve,
         SyncRef,
@@ -23,12 +25,27 @@ use n_lang::{
         Module,
         ProjectContext,
         SemanticError,
+        SemanticErrorKind,
+        SemanticItemType,
         StdLib,
+        StdLibArgument,
         StdLibBinaryOperation,
         StdLibFunction,
     },
 };
-use std::path::Path;
+use std::{
+    env,
+    fs,
+    path::Path,
+    process::{
+        self,
+        Command,
+    },
+    sync::atomic::{
+        AtomicUsize,
+        Ordering,
+    },
+};
 
 fn get_test_stdlib() -> StdLib {
     let mut stdlib = StdLib::new();
@@ -67,6 +84,13 @@ fn get_test_stdlib() -> StdLib {
         output: small_integer.clone(),
     });
 
+    stdlib.reg_binary_operation(StdLibBinaryOperation {
+        operator: BinaryOperator::Divide,
+        left: small_integer.clone(),
+        right: small_integer.clone(),
+        output: small_integer.clone(),
+    });
+
     stdlib.reg_binary_operation(StdLibBinaryOperation {
         operator: BinaryOperator::MoreThan,
         left: small_integer.clone(),
@@ -95,6 +119,24 @@ fn get_test_stdlib() -> StdLib {
             .aggregate()
     );
 
+    stdlib.reg_function(
+        StdLibFunction::new("row_number".to_string())
+            .returns(small_integer.clone())
+            .ordered()
+    );
+
+    stdlib.reg_function(
+        StdLibFunction::new("lag".to_string())
+            .gets_arguments(vec![
+                StdLibArgument::Generic,
+                StdLibArgument::Typed(small_integer.clone()),
+                StdLibArgument::Generic,
+            ])
+            .optional_from(1)
+            .returns_generic()
+            .ordered()
+    );
+
     stdlib.reg_function(
         StdLibFunction::new("abs".to_string())
             .gets(vec![small_integer.clone()])
@@ -114,7 +156,10 @@ fn get_sources(directory: &str) -> HashMapSource {
 }
 
 fn resolve_directory(stdlib: StdLib, directory: &str) -> Result<IndexMap<SyncRef<PathBuf>, SyncRef<Module>>, Vec<SemanticError>> {
-    let source = get_sources(directory);
+    resolve_sources(stdlib, get_sources(directory))
+}
+
+fn resolve_sources(stdlib: StdLib, source: HashMapSource) -> Result<IndexMap<SyncRef<PathBuf>, SyncRef<Module>>, Vec<SemanticError>> {
     let project = ProjectContext::new(SyncRef::new(stdlib));
     for (module_path, _) in source.texts() {
         project.request_resolving_module(module_path.as_path());
@@ -139,6 +184,29 @@ fn resolve_project() -> (DatabaseProject, RPCModule) {
     )
 }
 
+/// Возвращает текст от строки `header` до ближайшего за ней `end` включительно
+fn code_block<'a>(code: &'a str, header: &str, end: &str) -> &'a str {
+    let begin = code.find(header)
+        .unwrap_or_else(|| panic!("There is no {:?} in generated code", header));
+    let length = code[begin + header.len()..].find(end)
+        .unwrap_or_else(|| panic!("Block {:?} is not finished with {:?}", header, end));
+    &code[begin..begin + header.len() + length + end.len()]
+}
+
+/// Возвращает текст экспортируемой функции `name` из сгенерированного TypeScript-клиента
+fn ts_function<'a>(code: &'a str, name: &str) -> &'a str {
+    code_block(code, &format!("    export async function {}(\n", name), "\n    }\n")
+}
+
+/// Возвращает текст объявления функции или процедуры `name` из сгенерированного T-SQL
+fn sql_definition<'a>(sql: &'a str, name: &str) -> &'a str {
+    let header = format!(" dbo.[{}]\n", name);
+    let begin = sql.find(&header)
+        .unwrap_or_else(|| panic!("There is no definition of {} in generated T-SQL", name));
+    let end = sql[begin..].find("\nGO\n").expect("Definition must be finished with GO");
+    &sql[begin..begin + end]
+}
+
 #[test]
 fn dir_resolve() {
     let (db, rpc) = resolve_project();
@@ -147,6 +215,1363 @@ fn dir_resolve() {
     println!("{}", rpc.generate_string().expect("Cannot generate output for RPC"));
 }
 
+#[test]
+fn typescript_client_should_be_generated() {
+    let (_, rpc) = resolve_project();
+    let code = rpc.generate_string().expect("Cannot generate output for RPC");
+
+    let class = code_block(&code, "export class NLangError extends Error {\n", "\n}\n");
+    assert!(class.contains("            case 2627:\n                return new UniqueViolationError(message, error)\n"), "{}", class);
+    assert!(class.contains("            case 797831:\n                return new users.DuplicateUser(message, error)\n"), "{}", class);
+    let module = code_block(&code, "export module users {\n", "\n}\n");
+    assert!(module.contains("\n    export class DuplicateUser extends NLangError { static readonly number = 797831 }\n"), "{}", module);
+
+    let function = ts_function(&code, "fibo");
+    assert!(function.contains("        n: number,\n    ): Promise<number> {\n"), "{}", function);
+    assert!(function.contains("        _req.input('n', _mssql.SmallInt, n)\n"), "{}", function);
+    assert!(function.contains("            const _result = await _req.query('SELECT dbo.[users::fibo](@n) as result')\n            return _result.recordset[0].result\n"), "{}", function);
+    assert!(function.contains("        } catch (_error) {\n            throw NLangError.wrap(_error)\n        }\n"), "{}", function);
+
+    let function = ts_function(&code, "add_order_returning_id");
+    assert!(function.contains("        _req.output('return_value', _mssql.Int)\n"), "{}", function);
+    assert!(function.contains("            const _result = await _req.execute('dbo.[users::add_order_returning_id]')\n            return _result.output['return_value']\n"), "{}", function);
+}
+
+#[test]
+fn typescript_client_should_accept_pool_or_transaction() {
+    let (_, rpc) = resolve_project();
+    let code = rpc.generate_string().expect("Cannot generate output for RPC");
+
+    assert!(code.contains("\nexport type Connection = _mssql.ConnectionPool | _mssql.Transaction\n"), "{}", code);
+    let function = code_block(&code, "export async function withTransaction<T>(\n", "\n}\n");
+    assert!(function.contains("    _pool: _mssql.ConnectionPool,\n    _body: (tx: _mssql.Transaction) => Promise<T>,\n"), "{}", function);
+    assert!(function.contains("    const _tx = new _mssql.Transaction(_pool)\n    await _tx.begin()\n"), "{}", function);
+    assert!(function.contains("        await _tx.rollback().catch(() => undefined)\n        throw NLangError.wrap(_error)\n"), "{}", function);
+
+    // Исключения из тела транзакции, не пришедшие от сервера, не подменяются
+    let class = code_block(&code, "export class NLangError extends Error {\n", "\n}\n");
+    assert!(class.contains("        if (error instanceof NLangError || !(error && typeof error.number === 'number')) {\n            return error\n        }\n"), "{}", class);
+    assert!(class.contains("        switch (error.number) {\n"), "{}", class);
+}
+
+#[test]
+fn rust_client_should_be_generated() {
+    let (_, rpc) = resolve_project();
+    let code = rpc.generate_rust_string().expect("Cannot generate Rust client");
+
+    let executor = code_block(&code, "pub trait Executor {\n", "\n}\n");
+    assert!(executor.contains("    async fn query(&mut self, query: &str, inputs: Row) -> Result<Vec<Row>, Self::Error>;\n"), "{}", executor);
+
+    let function = code_block(&code, "    pub async fn fibo<E: Executor>(\n", "\n    }\n");
+    assert!(function.contains("        n: i16,\n    ) -> Result<i16, ClientError<E::Error>> {\n"), "{}", function);
+    assert!(function.contains("        let _rows = _executor.query(\"SELECT dbo.[users::fibo](@n) as result\", _inputs).await.map_err(ClientError::Executor)?;\n"), "{}", function);
+
+    let function = code_block(&code, "    pub async fn add_order_returning_id<E: Executor>(\n", "\n    }\n");
+    assert!(function.contains("    ) -> Result<u32, ClientError<E::Error>> {\n"), "{}", function);
+    assert!(function.contains("        let _output = _executor.execute(\"dbo.[users::add_order_returning_id]\", _inputs, &[\n            (\"return_value\", \"int\"),\n        ])"), "{}", function);
+    assert!(function.contains("        Ok(FromRow::from_row(&_output, \"return_value\")?)\n"), "{}", function);
+}
+
+/// `Waker::noop` в проверке клиента появился в Rust 1.85
+const RUST_CLIENT_CHECK_MIN_VERSION: (u32, u32) = (1, 85);
+
+/// Возвращает версию из вывода `cargo --version`, например `cargo 1.85.0 (...)`
+fn cargo_version(cargo: &str) -> Option<(u32, u32)> {
+    let output = Command::new(cargo).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut numbers = text.split_whitespace().nth(1)?.split('.');
+    let major = numbers.next()?.parse().ok()?;
+    let minor = numbers.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+const RUST_CLIENT_CHECK: &str = r##"
+mod client;
+
+use client::{Executor, FromRow, Row, ToRow, Value};
+use std::{
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+struct MockExecutor {
+    queries: Vec<String>,
+}
+
+impl Executor for MockExecutor {
+    type Error = ();
+    async fn query(&mut self, query: &str, inputs: Row) -> Result<Vec<Row>, ()> {
+        assert_eq!(inputs, vec![("n".to_string(), Value::Int(5))]);
+        self.queries.push(query.to_string());
+        Ok(vec![vec![("result".to_string(), Value::Int(8))]])
+    }
+    async fn execute(&mut self, procedure: &str, _inputs: Row, _outputs: &[(&str, &str)]) -> Result<Row, ()> {
+        self.queries.push(procedure.to_string());
+        Ok(Row::new())
+    }
+    async fn execute_sets(&mut self, procedure: &str, _inputs: Row) -> Result<Vec<Vec<Row>>, ()> {
+        self.queries.push(procedure.to_string());
+        Ok(Vec::new())
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut context = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(result) = future.as_mut().poll(&mut context) {
+            return result;
+        }
+    }
+}
+
+fn main() {
+    let mut executor = MockExecutor { queries: Vec::new() };
+    let result = block_on(client::users::fibo(&mut executor, 5));
+    assert_eq!(result.ok(), Some(8));
+    assert_eq!(executor.queries, vec!["SELECT dbo.[users::fibo](@n) as result".to_string()]);
+
+    let row = vec![("n".to_string(), Value::Int(300))];
+    assert!(u8::from_row(&row, "n").is_err());
+    assert!(u64::MAX.to_row("n", &mut Row::new()).is_err());
+
+    let mut row = Row::new();
+    vec![(1i32, "a\"b".to_string())].to_row("items", &mut row).unwrap();
+    assert_eq!(Vec::<(i32, String)>::from_row(&row, "items"), Ok(vec![(1, "a\"b".to_string())]));
+}
+"##;
+
+const RUST_CLIENT_CHECK_MANIFEST: &str = r#"[package]
+name = "client_check"
+version = "0.0.0"
+edition = "2021"
+
+[dependencies]
+serde_json = "1"
+"#;
+
+/// Нужны Rust не старше 1.85 и крейт serde_json, поэтому проверка запускается явно: `cargo test -- --ignored`
+#[test]
+#[ignore]
+fn generated_rust_client_compiles_against_executor() {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    match cargo_version(&cargo) {
+        Some(version) if version >= RUST_CLIENT_CHECK_MIN_VERSION => {}
+        Some((major, minor)) => panic!("The check needs Rust 1.85 or newer, found {}.{}", major, minor),
+        None => panic!("Cannot run {}", cargo),
+    }
+
+    let (_, rpc) = resolve_project();
+    static CHECK_NUMBER: AtomicUsize = AtomicUsize::new(0);
+    let dir = env::temp_dir().join(format!(
+        "n_lang_rust_client_check_{}_{}",
+        process::id(),
+        CHECK_NUMBER.fetch_add(1, Ordering::SeqCst),
+    ));
+    fs::create_dir_all(dir.join("src")).expect("Cannot create directory for Rust client");
+    fs::write(dir.join("Cargo.toml"), RUST_CLIENT_CHECK_MANIFEST).expect("Cannot write Rust client manifest");
+    fs::write(
+        dir.join("src").join("client.rs"),
+        rpc.generate_rust_string().expect("Cannot generate Rust client"),
+    ).expect("Cannot write Rust client");
+    fs::write(dir.join("src").join("main.rs"), RUST_CLIENT_CHECK).expect("Cannot write Rust client check");
+
+    let output = Command::new(&cargo)
+        .current_dir(&dir)
+        .env("CARGO_TARGET_DIR", dir.join("target"))
+        .args(&["run", "--quiet"])
+        .output()
+        .expect("Cannot run cargo");
+    let _ = fs::remove_dir_all(&dir);
+    assert!(
+        output.status.success(),
+        "Generated Rust client check failed:\n{}",
+        String::from_utf8_lossy(&output.stderr),
+    );
+}
+
+#[test]
+fn python_client_should_be_generated() {
+    let (_, rpc) = resolve_project();
+    let code = rpc.generate_python_string().expect("Cannot generate Python client");
+
+    assert!(!code.contains(".replace("), "SQL should not be rewritten at runtime");
+    assert!(code.contains("\nparamstyle = 'qmark'\n"));
+    assert!(!code.contains("\n@staticmethod\n"), "Module-level functions are not static methods");
+    assert!(!code.contains("DECLARE ;"));
+
+    let function = code_block(&code, "    @staticmethod\n    def fibo(connection, n: int) -> int:\n", "\n\n");
+    assert!(function.contains("        records = _query(connection, 'SELECT dbo.[users::fibo](?) AS result', [\n            n,\n        ])\n"), "{}", function);
+    assert!(function.contains("        return records[0]['result']\n"), "{}", function);
+
+    let function = code_block(&code, "    def add_order_returning_id(connection, currency: 'users.Currency') -> int:\n", "\n\n");
+    assert!(function.contains("'SET NOCOUNT ON; DECLARE @_0 int; EXECUTE dbo.[users::add_order_returning_id] @currency = ?, @return_value = @_0 OUTPUT; SELECT @_0 AS [return_value]'"), "{}", function);
+    assert!(function.contains("        return record['return_value']\n"), "{}", function);
+}
+
+#[test]
+fn json_schema_should_be_generated() {
+    let (_, rpc) = resolve_project();
+    let schema = rpc.generate_json_schema_string().expect("Cannot generate JSON Schema");
+
+    assert!(schema.starts_with("{\n  \"$schema\": \"https://json-schema.org/draft/2020-12/schema\",\n  \"$defs\": {\n"), "{}", schema);
+    let definition = code_block(&schema, "    \"users.Users.primary_key\": {\n", "\n    }");
+    assert_eq!(definition, r#"    "users.Users.primary_key": {
+      "type": "object",
+      "properties": {
+        "id": {
+          "type": "integer",
+          "minimum": 0,
+          "maximum": 4294967295
+        }
+      },
+      "required": [
+        "id"
+      ],
+      "additionalProperties": false
+    }"#);
+    let definition = code_block(&schema, "    \"users.Orders.entity\": {\n", "\n    }");
+    assert!(definition.contains("        \"status\": {\n          \"$ref\": \"#/$defs/users.OrderStatus\"\n        },\n"), "{}", definition);
+    assert!(definition.contains("        \"currency\": {\n          \"$ref\": \"#/$defs/users.Currency\"\n        }\n"), "{}", definition);
+    let definition = code_block(&schema, "    \"users.Orders.primary_key\": {\n", "\n    }");
+    assert_eq!(definition, r#"    "users.Orders.primary_key": {
+      "type": "object",
+      "properties": {
+        "id": {
+          "type": "integer",
+          "minimum": 0,
+          "maximum": 4294967295
+        }
+      },
+      "required": [
+        "id"
+      ],
+      "additionalProperties": false
+    }"#);
+}
+
+#[test]
+fn open_api_should_be_generated() {
+    let (_, rpc) = resolve_project();
+    let document = rpc.generate_open_api_string("dir_resolve", "0.1.0").expect("Cannot generate OpenAPI document");
+
+    assert!(document.starts_with("{\n  \"openapi\": \"3.1.0\",\n  \"info\": {\n    \"title\": \"dir_resolve\",\n    \"version\": \"0.1.0\"\n  },\n"), "{}", document);
+    let operation = code_block(&document, "    \"/users/fibo\": {\n", "\n    }");
+    assert!(operation.contains("        \"operationId\": \"users.fibo\",\n"), "{}", operation);
+    assert!(operation.contains("                \"properties\": {\n                  \"n\": {\n                    \"type\": \"integer\",\n                    \"minimum\": -32768,\n                    \"maximum\": 32767\n"), "{}", operation);
+    assert!(operation.contains("            \"description\": \"Result of users::fibo\",\n"), "{}", operation);
+    assert!(document.contains("\n      \"users.PersonInfo\": {\n        \"type\": \"object\",\n"), "Named types should be placed in components");
+}
+
+#[test]
+fn python_client_should_use_format_param_style() {
+    let (_, rpc) = resolve_project();
+    let code = rpc.generate_python_string_with_param_style(PythonParamStyle::Format)
+        .expect("Cannot generate Python client");
+
+    assert!(code.contains("\nparamstyle = 'format'\n"));
+    let function = code_block(&code, "    def add_user(connection, ", "\n\n");
+    assert!(function.contains("@person_info#age = %s, @person_info#sex#is_male = %s, @return_value#age = @_0 OUTPUT"), "{}", function);
+    assert!(!function.contains("?"), "{}", function);
+}
+
+#[test]
+fn python_client_should_call_transactional_procedures() {
+    let (_, rpc) = resolve_project();
+    let code = rpc.generate_python_string_with_param_style(PythonParamStyle::Format)
+        .expect("Cannot generate Python client");
+
+    let function = code_block(&code, "    def add_users_atomically(connection, ", "\n\n");
+    assert!(function.contains("@first#age = %s, @first#sex#is_male = %s, @second#age = %s, @second#sex#is_male = %s, @return_value#age = @_0 OUTPUT"), "{}", function);
+    assert!(!function.contains("?"), "{}", function);
+}
+
+#[test]
+fn error_numbers_should_be_unique_in_project() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("first", "::"), "first.n", "error NotFound = 60000;");
+    source.simple_insert(ModulePath::new("second", "::"), "second.n", "error Missing = 60000;\nerror Other;");
+    let errors = match resolve_sources(get_test_stdlib(), source) {
+        Ok(_) => panic!("Duplicate error numbers must not be resolved"),
+        Err(errors) => errors,
+    };
+    assert_eq!(errors.len(), 1);
+    // Какая из ошибок объявлена раньше, зависит от порядка разрешения модулей
+    match &errors[0].kind {
+        SemanticErrorKind::DuplicateErrorNumber { number: 60000, path, other_path, is_hash_clash: false } => {
+            let mut paths = vec![path.data.as_str(), other_path.data.as_str()];
+            paths.sort();
+            assert_eq!(paths, vec!["first::NotFound", "second::Missing"]);
+        }
+        kind => panic!("Unexpected error {:?}", kind),
+    }
+}
+
+#[test]
+fn errors_should_be_raised_and_caught() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+    let definition = sql_definition(&sql, "users::add_user_checked");
+    assert!(definition.contains("    DECLARE @e#number int;\n    DECLARE @e#message nvarchar(4000);\n    BEGIN TRY\n"), "{}", definition);
+    assert!(definition.contains(r#"
+    BEGIN CATCH
+        SET @e#number = ERROR_NUMBER();
+        SET @e#message = ERROR_MESSAGE();
+        DECLARE @message nvarchar(2048) = @e#message;
+        THROW 797831, @message, 1;
+    END CATCH
+"#), "{}", definition);
+
+    let definition = sql_definition(&sql, "users::add_user_or_raise");
+    assert!(definition.contains("    BEGIN CATCH\n        THROW;\n    END CATCH\n    THROW 50100, N'users::UserNotAdded', 1;\n"), "{}", definition);
+}
+
+#[test]
+fn raised_messages_should_not_be_taken_for_errors() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("access", "::"), "access.n", r#"
+        error Denied;
+
+        fn deny(reason: varchar(64)) {
+            raise reason;
+        }
+
+        fn deny_as_error(reason: varchar(64)) {
+            raise Denied(reason);
+        }
+    "#);
+    let project = resolve_sources(get_test_stdlib(), source)
+        .unwrap_or_else(|errors| panic!("Raised messages must be resolved: {:?}", errors));
+    let sql = DatabaseProject::new(&project).generate_string().expect("Cannot generate output for database");
+    let definition = sql_definition(&sql, "access::deny");
+    assert!(definition.contains("    DECLARE @message nvarchar(2048) = @reason;\n    THROW 50000, @message, 1;\n"), "{}", definition);
+    let definition = sql_definition(&sql, "access::deny_as_error");
+    assert!(!definition.contains("THROW 50000"), "{}", definition);
+    assert!(definition.contains("    DECLARE @message nvarchar(2048) = @reason;\n    THROW "), "{}", definition);
+}
+
+#[test]
+fn default_error_numbers_clash_should_name_both_errors() {
+    let mut source = HashMapSource::new();
+    // Хэши путей этих ошибок совпадают по модулю диапазона номеров по умолчанию
+    source.simple_insert(ModulePath::new("first", "::"), "first.n", "error E978;\nerror E2622;");
+    let errors = match resolve_sources(get_test_stdlib(), source) {
+        Ok(_) => panic!("Clashing default error numbers must not be resolved"),
+        Err(errors) => errors,
+    };
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].kind.to_string(),
+        "error first::E2622 has the same number 492048 as error first::E978: \
+        default numbers are hashes of error paths and these ones clash, specify the number explicitly",
+    );
+}
+
+#[test]
+fn nested_arrays_should_be_passed_as_json() {
+    let (db, rpc) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+    let definition = sql_definition(&sql, "utils::get_user_pair_report");
+    assert!(definition.contains("    ) RETURNS @return_value TABLE (\n        [count] int, \n        [users] nvarchar(max)\n    )\n"), "{}", definition);
+    assert!(definition.contains("    DECLARE @report#users nvarchar(max);\n"), "{}", definition);
+    assert!(definition.contains("    SET @report#users = (\n        SELECT t.[user#id], t.[user#person_info#age], t.[user#person_info#sex#is_male] FROM\n"), "{}", definition);
+    assert!(definition.contains("        AS t\n        FOR JSON PATH, INCLUDE_NULL_VALUES\n    );\n"), "{}", definition);
+
+    let code = rpc.generate_string().expect("Cannot generate TypeScript client");
+    assert!(code.contains("function _jsonArray(value: any): any[] {\n"));
+    let function = ts_function(&code, "get_user_pair_report");
+    assert!(function.contains("                    users: _jsonArray(record['users']).map(item => (\n"), "{}", function);
+    assert!(function.contains("                                id: item['user#id'],\n"), "{}", function);
+
+    let code = rpc.generate_python_string().expect("Cannot generate Python client");
+    let function = code_block(&code, "    def get_user_pair_report(connection, id0: int, id1: int) -> Dict[str, Any]:\n", "\n\n");
+    assert!(function.contains("                        id=item['user#id'],\n"), "{}", function);
+    assert!(function.contains("                for item in _json_array(record['users'])\n"), "{}", function);
+}
+
+#[test]
+fn arrays_of_primitives_should_not_be_fields() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("reports", "::"), "reports.n", r#"
+        struct Report {
+            count: unsigned integer,
+            ages: unsigned tiny integer[],
+        }
+
+        struct Pair (unsigned integer, small integer[])
+    "#);
+    let errors = match resolve_sources(get_test_stdlib(), source) {
+        Ok(_) => panic!("Arrays of primitives must not be resolved as fields"),
+        Err(errors) => errors,
+    };
+    let kinds: Vec<_> = errors.into_iter().map(|error| error.kind).collect();
+    assert_eq!(kinds, vec![
+        SemanticErrorKind::ArrayOfPrimitivesField { name: "ages".to_string() },
+        SemanticErrorKind::ArrayOfPrimitivesField { name: "component1".to_string() },
+    ]);
+}
+
+#[test]
+fn record_sets_should_be_returned_by_procedures() {
+    let (db, rpc) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+    let definition = sql_definition(&sql, "utils::add_user_pair");
+    assert!(definition.starts_with(" dbo.[utils::add_user_pair]\n        @person_info#age tinyint, \n        @person_info#sex#is_male bit\nAS BEGIN\n"), "{}", definition);
+    assert!(definition.ends_with(r#"
+    SELECT t.[user#id], t.[user#person_info#age], t.[user#person_info#sex#is_male] FROM
+        (SELECT [t].user#id AS user#id, [t].user#person_info#age AS user#person_info#age, [t].user#person_info#sex#is_male AS user#person_info#sex#is_male FROM @users as t)
+    AS t;
+    SELECT t.[id] FROM
+        (SELECT [t].id AS id FROM @ids as t)
+    AS t;
+    RETURN;
+END"#), "{}", definition);
+
+    let code = rpc.generate_string().expect("Cannot generate TypeScript client");
+    let function = ts_function(&code, "add_user_pair");
+    assert!(function.contains("            const _result = await _req.execute('dbo.[utils::add_user_pair]')\n"), "{}", function);
+    assert!(function.contains("                _result.recordsets[0].map(record => (\n"), "{}", function);
+    assert!(function.contains("                _result.recordsets[1].map(record => (\n"), "{}", function);
+
+    let code = rpc.generate_rust_string().expect("Cannot generate Rust client");
+    let function = code_block(&code, "    pub async fn add_user_pair<E: Executor>(\n", "\n    }\n");
+    assert!(function.contains("        let mut _sets = _executor.execute_sets(\"dbo.[utils::add_user_pair]\", _inputs).await.map_err(ClientError::Executor)?.into_iter();\n"), "{}", function);
+    assert_eq!(function.matches("            _sets.next().ok_or(ClientError::EmptyResult)?.iter()\n").count(), 2, "{}", function);
+
+    let code = rpc.generate_python_string().expect("Cannot generate Python client");
+    let function = code_block(&code, "    def add_user_pair(connection, ", "\n\n");
+    assert!(function.contains("        sets = _query_sets(connection, 'SET NOCOUNT ON; EXECUTE dbo.[utils::add_user_pair] @person_info#age = ?, @person_info#sex#is_male = ?', [\n"), "{}", function);
+    assert!(function.contains("                for record in sets[1]\n"), "{}", function);
+}
+
+#[test]
+fn selection_should_not_be_returned_as_record_sets() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("orders", "::"), "orders.n", r#"
+        table Orders {
+            #[primary_key]
+            id: unsigned integer,
+        }
+
+        fn get_orders(): ({id: unsigned integer}[], {id: unsigned integer}[]) {
+            update Orders o set o.id = o.id;
+            return select o.id from Orders o;
+        }
+    "#);
+    let errors = match resolve_sources(get_test_stdlib(), source) {
+        Ok(_) => panic!("Selection returned as record sets must not be resolved"),
+        Err(errors) => errors,
+    };
+    let kinds: Vec<_> = errors.into_iter().map(|error| error.kind).collect();
+    assert_eq!(kinds, vec![SemanticErrorKind::NotAllowedHere {
+        feature: "returning selection as record sets",
+    }]);
+}
+
+#[test]
+fn iterating_cycle_should_fetch_records_with_cursor() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+    let definition = sql_definition(&sql, "utils::total_age");
+    assert!(definition.contains(r#"
+    DECLARE @user_cursor CURSOR;
+    SET @user_cursor = CURSOR FAST_FORWARD FOR
+        SELECT t.[info#age], t.[info#sex#is_male] FROM
+        (
+            SELECT
+                [u].person_info#age AS info#age,
+                [u].person_info#sex#is_male AS info#sex#is_male
+            FROM
+                [users::Users] AS [u]
+        )
+        AS t;
+    OPEN @user_cursor;
+    WHILE 1 = 1 BEGIN
+        FETCH NEXT FROM @user_cursor INTO @user#info#age, @user#info#sex#is_male;
+        IF @@FETCH_STATUS <> 0 BREAK;
+        BEGIN
+            SET @total = @total + @user#info#age;
+        END
+    END
+    CLOSE @user_cursor;
+    DEALLOCATE @user_cursor;
+"#), "{}", definition);
+}
+
+#[test]
+fn labeled_cycle_control_should_jump_to_labels() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+    let definition = sql_definition(&sql, "users::first_product_over");
+    assert!(definition.contains("    WHILE 1 = 1 BEGIN\n        outer_continue:\n        BEGIN\n"), "{}", definition);
+    assert!(definition.contains("                            SET @result = @i + @j;\n                            GOTO outer_break;\n"), "{}", definition);
+    assert!(definition.contains("                    IF @j > 2\n                        BEGIN\n                            GOTO outer_continue;\n"), "{}", definition);
+    assert!(definition.ends_with("    END\n    outer_break:\n    RETURN @result;\nEND"), "{}", definition);
+}
+
+#[test]
+fn cycle_control_should_close_cursors_of_left_cycles() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("cycles", "::"), "cycles.n", r#"
+        table Items {
+            #[primary_key]
+            id: small integer,
+        }
+
+        fn find_pair(limit: small integer): small integer {
+            let result: small integer := 0;
+            'outer: for a in select i.id from Items i {
+                for b in select i.id from Items i {
+                    if a.id + b.id > limit {
+                        result := a.id + b.id;
+                        break 'outer;
+                    };
+                    if b.id > a.id {
+                        continue 'outer;
+                    };
+                };
+            };
+            return result;
+        }
+    "#);
+    let project = match resolve_sources(get_test_stdlib(), source) {
+        Ok(project) => project,
+        Err(errors) => panic!("Got errors: {:?}", errors),
+    };
+    let sql = DatabaseProject::new(&project).generate_string()
+        .expect("Cannot generate output for database");
+    let definition = sql_definition(&sql, "cycles::find_pair");
+    assert!(definition.contains(r#"
+                            CLOSE @b_cursor;
+                            DEALLOCATE @b_cursor;
+                            CLOSE @a_cursor;
+                            DEALLOCATE @a_cursor;
+                            GOTO outer_break;
+"#), "{}", definition);
+    assert!(definition.contains(r#"
+                            CLOSE @b_cursor;
+                            DEALLOCATE @b_cursor;
+                            GOTO outer_continue;
+"#), "{}", definition);
+    assert!(definition.contains("    CLOSE @a_cursor;\n    DEALLOCATE @a_cursor;\n    outer_break:\n"), "{}", definition);
+}
+
+#[test]
+fn enums_should_be_generated() {
+    let (db, rpc) = resolve_project();
+
+    let sql = db.generate_string().expect("Cannot generate output for database");
+    let table = code_block(&sql, "CREATE TABLE [users::Orders] (\n", "\n)\n");
+    assert!(table.contains("    [status] tinyint,\n    [currency] nvarchar(3),\n"), "{}", table);
+    assert!(table.contains("    CHECK ([status] < 3),\n    CHECK ([currency] IN (N'Rub', N'Usd')),\n"), "{}", table);
+
+    let code = rpc.generate_string().expect("Cannot generate output for RPC");
+    let module = code_block(&code, "export module users {\n", "\n}\n");
+    assert!(module.contains("\n    export type Currency = 'Rub' | 'Usd';\n"), "{}", module);
+    assert!(module.contains("\n    export enum OrderStatus {\n        Pending = 0,\n        Paid = 1,\n        Shipped = 2,\n    }\n"), "{}", module);
+
+    let code = rpc.generate_rust_string().expect("Cannot generate Rust client");
+    let enumeration = code_block(&code, "    pub enum OrderStatus {\n", "\n    }\n");
+    assert_eq!(enumeration, "    pub enum OrderStatus {\n        Pending,\n        Paid,\n        Shipped,\n    }\n");
+    let implementation = code_block(&code, "    impl FromRow for Currency {\n", "\n    }\n");
+    assert!(implementation.contains("                \"Rub\" => Ok(Currency::Rub),\n                \"Usd\" => Ok(Currency::Usd),\n"), "{}", implementation);
+    let implementation = code_block(&code, "    impl ToRow for OrderStatus {\n", "\n    }\n");
+    assert!(implementation.contains("                OrderStatus::Shipped => Value::Int(2),\n"), "{}", implementation);
+
+    let code = rpc.generate_python_string().expect("Cannot generate Python client");
+    let class = code_block(&code, "    class OrderStatus(IntEnum):\n", "\n\n");
+    assert_eq!(class, "    class OrderStatus(IntEnum):\n        Pending = 0\n        Paid = 1\n        Shipped = 2\n\n");
+    let class = code_block(&code, "    class Currency(str, Enum):\n", "\n\n");
+    assert_eq!(class, "    class Currency(str, Enum):\n        Rub = 'Rub'\n        Usd = 'Usd'\n\n");
+
+    let schema = rpc.generate_json_schema_string().expect("Cannot generate JSON Schema");
+    let definition = code_block(&schema, "    \"users.OrderStatus\": {\n", "\n    }");
+    assert_eq!(definition, "    \"users.OrderStatus\": {\n      \"type\": \"integer\",\n      \"enum\": [\n        0,\n        1,\n        2\n      ]\n    }");
+    let definition = code_block(&schema, "    \"users.Currency\": {\n", "\n    }");
+    assert!(definition.contains("      \"type\": \"string\",\n      \"enum\": [\n        \"Rub\",\n        \"Usd\"\n      ]\n"), "{}", definition);
+}
+
+#[test]
+fn constants_should_be_substituted_by_values() {
+    let (db, rpc) = resolve_project();
+
+    let sql = db.generate_string().expect("Cannot generate output for database");
+    let definition = sql_definition(&sql, "users::add_order");
+    assert!(definition.contains("        (status, currency)\n        VALUES\n        (\n            0,\n            @currency\n        )\n"), "{}", definition);
+    assert!(sql_definition(&sql, "users::next_age").ends_with("AS BEGIN\n    RETURN @age + 2;\nEND"));
+
+    let code = rpc.generate_string().expect("Cannot generate output for RPC");
+    let module = code_block(&code, "export module users {\n", "\n}\n");
+    assert!(module.contains("\n    export const AGE_STEP: number = 2;\n    export const DEFAULT_STATUS: users.OrderStatus = 0;\n"), "{}", module);
+}
+
+#[test]
+fn constants_should_keep_their_values_in_typescript() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("prices", "::"), "prices.n", r#"
+        #[as_text]
+        enum Currency {
+            Rub,
+            Usd
+        }
+
+        const BASE_CURRENCY: Currency = Currency::Rub;
+
+        const HALF: small integer = 7 / 2;
+
+        fn is_base(currency: Currency): boolean {
+            return currency = BASE_CURRENCY;
+        }
+    "#);
+    let project = resolve_sources(get_test_stdlib(), source)
+        .unwrap_or_else(|errors| panic!("Constants must be resolved: {:?}", errors));
+
+    let sql = DatabaseProject::new(&project).generate_string().expect("Cannot generate output for database");
+    assert!(sql_definition(&sql, "prices::is_base").contains("@currency = N'Rub'"), "{}", sql);
+
+    let code = RPCModule::top(&project).generate_string().expect("Cannot generate output for RPC");
+    let module = code_block(&code, "export module prices {\n", "\n}\n");
+    assert!(module.contains("\n    export const BASE_CURRENCY: prices.Currency = 'Rub';\n"), "{}", module);
+    assert!(module.contains("\n    export const HALF: number = Math.trunc(7 / 2);\n"), "{}", module);
+}
+
+#[test]
+fn variables_should_not_shadow_constants() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("ages", "::"), "ages.n", r#"
+        fn next_age(age: unsigned tiny integer): unsigned tiny integer {
+            let STEP := age;
+            return age + STEP;
+        }
+
+        const STEP: unsigned tiny integer = 1;
+    "#);
+    let errors = match resolve_sources(get_test_stdlib(), source) {
+        Ok(_) => panic!("Variables must not shadow constants"),
+        Err(errors) => errors,
+    };
+    let kinds: Vec<_> = errors.into_iter().map(|error| error.kind).collect();
+    assert_eq!(kinds, vec![SemanticErrorKind::DuplicateDefinition {
+        name: "STEP".to_string(),
+        item_type: SemanticItemType::Const,
+    }]);
+}
+
+#[test]
+fn transactions_should_be_nested_with_savepoints() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+    let definition = sql_definition(&sql, "users::add_users_atomically");
+    assert!(definition.contains("AS BEGIN\n    DECLARE @tran_count int = @@TRANCOUNT;\n    IF @tran_count = 0 BEGIN TRANSACTION;\n    ELSE SAVE TRANSACTION savepoint;\n    BEGIN TRY\n"), "{}", definition);
+    assert!(definition.contains(r#"
+        DECLARE @tran_count_0 int = @@TRANCOUNT;
+        IF @tran_count_0 = 0 BEGIN TRANSACTION;
+        ELSE SAVE TRANSACTION savepoint_0;
+        BEGIN TRY
+"#), "{}", definition);
+    assert!(definition.contains(r#"
+            IF @tran_count_0 = 0 AND @@TRANCOUNT > 0 COMMIT TRANSACTION;
+        END TRY
+        BEGIN CATCH
+            IF @tran_count_0 = 0 AND @@TRANCOUNT > 0 ROLLBACK TRANSACTION;
+            ELSE IF XACT_STATE() = 1 ROLLBACK TRANSACTION savepoint_0;
+            THROW;
+        END CATCH
+"#), "{}", definition);
+    assert!(definition.ends_with(r#"
+    BEGIN CATCH
+        IF @tran_count = 0 AND @@TRANCOUNT > 0 ROLLBACK TRANSACTION;
+        ELSE IF XACT_STATE() = 1 ROLLBACK TRANSACTION savepoint;
+        THROW;
+    END CATCH
+END"#), "{}", definition);
+}
+
+#[test]
+fn cycle_control_should_not_leave_transaction() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("cycles", "::"), "cycles.n", r#"
+        table Items {
+            #[primary_key]
+            id: small integer,
+        }
+
+        fn break_in_transaction() {
+            loop {
+                transaction {
+                    insert into Items i (i.id) values (1);
+                    break;
+                }
+            }
+        }
+
+        fn continue_outer_in_transaction() {
+            'outer: while true {
+                transaction {
+                    while true {
+                        continue 'outer;
+                    }
+                }
+            }
+        }
+
+        fn break_inner_in_transaction() {
+            transaction {
+                'inner: loop {
+                    insert into Items i (i.id) values (1);
+                    break 'inner;
+                }
+            }
+        }
+    "#);
+    let errors = match resolve_sources(get_test_stdlib(), source) {
+        Ok(_) => panic!("Cycle control leaving transaction must not be resolved"),
+        Err(errors) => errors,
+    };
+    let kinds: Vec<_> = errors.into_iter().map(|error| error.kind).collect();
+    let expected_kind = SemanticErrorKind::NotAllowedHere {
+        feature: "cycle control operators leaving transaction",
+    };
+    assert_eq!(kinds, vec![expected_kind.clone(), expected_kind]);
+}
+
+#[test]
+fn comparison_of_variable_with_itself_is_not_folded() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+    let definition = sql_definition(&sql, "users::is_status_known");
+    assert!(definition.contains("IF @status = @status"), "{}", definition);
+    assert!(sql_definition(&sql, "users::next_age").ends_with("RETURN @age + 2;\nEND"));
+}
+
+#[test]
+fn constant_conditions_should_be_reported_as_warnings() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("items", "::"), "items.n", r#"
+        table Items {
+            #[primary_key]
+            id: unsigned integer,
+            #[nullable]
+            parent: unsigned integer,
+        }
+
+        fn has_item(id: unsigned integer): boolean {
+            if 1 = 1 {
+                return true;
+            };
+            return false;
+        }
+
+        fn get_items(): {id: unsigned integer}[] {
+            return select i.id from Items i where true;
+        }
+
+        fn get_children(): {id: unsigned integer}[] {
+            return select i.id from Items i where i.parent = i.parent;
+        }
+
+        fn get_joined_items(): {id: unsigned integer}[] {
+            return select i.id from Items i left join Items j using (id) where j.id = j.id;
+        }
+    "#);
+    let project = resolve_sources(get_test_stdlib(), source)
+        .unwrap_or_else(|errors| panic!("Constant conditions must be resolved: {:?}", errors));
+    let kinds: Vec<_> = project.values()
+        .flat_map(|module| module.read().warnings().clone())
+        .map(|warning| warning.kind)
+        .collect();
+    let expected_kind = SemanticErrorKind::ConstantCondition { value: true };
+    assert_eq!(kinds, vec![expected_kind.clone(), expected_kind]);
+
+    let sql = DatabaseProject::new(&project).generate_string().expect("Cannot generate output for database");
+    assert!(!sql_definition(&sql, "items::has_item").contains("IF "), "{}", sql);
+    assert!(!sql_definition(&sql, "items::get_items").contains("WHERE"), "{}", sql);
+    // Сворачиваются только сравнения литералов
+    assert!(sql_definition(&sql, "items::get_children").contains("WHERE [i].parent = [i].parent\n"), "{}", sql);
+    assert!(sql_definition(&sql, "items::get_joined_items").contains("WHERE [j].id = [j].id\n"), "{}", sql);
+}
+
+#[test]
+fn nullable_columns_should_not_be_returned_into_not_nullable_fields() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("orders", "::"), "orders.n", r#"
+        table Users {
+            #[primary_key]
+            id: unsigned integer,
+        }
+
+        table Orders {
+            #[primary_key]
+            id: unsigned integer,
+            price: small integer,
+        }
+
+        fn get_nullable_prices(): {id: unsigned integer, #[nullable] price: small integer}[] {
+            return select u.id, o.price from Users u left join Orders o using (id);
+        }
+
+        fn get_prices(): {id: unsigned integer, price: small integer}[] {
+            return select u.id, o.price from Users u left join Orders o using (id);
+        }
+    "#);
+    let errors = match resolve_sources(get_test_stdlib(), source) {
+        Ok(_) => panic!("Nullable column must not be returned into not nullable field"),
+        Err(errors) => errors,
+    };
+    assert_eq!(errors.len(), 1);
+    match &errors[0].kind {
+        SemanticErrorKind::CannotCastType { .. } => {}
+        kind => panic!("Unexpected error {:?}", kind),
+    }
+}
+
+#[test]
+fn nullable_fields_should_be_cast_outside_selections() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("prices", "::"), "prices.n", r#"
+        fn get_price(price: {#[nullable] value: small integer}): {value: small integer} {
+            return price;
+        }
+    "#);
+    if let Err(errors) = resolve_sources(get_test_stdlib(), source) {
+        panic!("Nullable field of argument must be cast: {:?}", errors);
+    }
+}
+
+#[test]
+fn joins_should_be_generated_with_using_conditions() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+
+    let definition = sql_definition(&sql, "users::get_ordered_users");
+    assert!(definition.contains(r#"
+    INSERT INTO @users ([id])
+        (
+            SELECT
+                [u].id AS id
+            FROM
+                [users::Users] AS [u]
+                INNER JOIN
+                [users::Orders] AS [o]
+                ON [u].id = [o].id
+        );
+"#), "{}", definition);
+    assert!(definition.contains(r#"
+    INSERT INTO @orders ([id])
+        (
+            SELECT
+                [o].id AS id
+            FROM
+                [users::Users] AS [u]
+                FULL JOIN
+                [users::Orders] AS [o]
+                ON [u].id = [o].id
+        );
+"#), "{}", definition);
+}
+
+#[test]
+fn computed_columns_are_selected_from_derived_table() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+
+    let definition = sql_definition(&sql, "users::get_order_payments");
+    assert!(definition.contains("SELECT t.[id], t.[paid] FROM (\n"), "{}", definition);
+    assert!(definition.contains("CASE WHEN [o].status = 1 THEN 1 ELSE 0 END AS paid\n"), "{}", definition);
+    assert!(definition.contains(") as t;\n"), "{}", definition);
+
+    let definition = sql_definition(&sql, "users::get_users_by_age");
+    assert!(definition.contains("SELECT t.[id], t.[position], t.[previous_age], t.[total_age] FROM (\n"), "{}", definition);
+    assert!(definition.contains("sum([u].person_info#age) OVER () AS total_age\n"), "{}", definition);
+    assert!(definition.contains(") as t;\n"), "{}", definition);
+}
+
+#[test]
+fn case_expressions_should_be_generated() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+
+    let definition = sql_definition(&sql, "users::order_priority");
+    assert!(definition.ends_with("AS BEGIN\n    RETURN CASE WHEN @status = 0 THEN 1 WHEN @status = 1 THEN 2 ELSE 3 END;\nEND"), "{}", definition);
+}
+
+#[test]
+fn casts_should_be_generated() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+
+    let definition = sql_definition(&sql, "users::get_order_labels");
+    assert!(definition.contains(r#"
+            SELECT
+                CAST([o].id AS tinyint) AS id,
+                CAST([o].status AS nvarchar(16)) AS label,
+                TRY_CAST(CAST([o].currency AS nvarchar(8)) AS int) AS code
+            FROM
+"#), "{}", definition);
+}
+
+#[test]
+fn casts_to_compound_types_should_be_rejected() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("prices", "::"), "prices.n", r#"
+        fn get_price(price: {value: small integer}): {value: small integer} {
+            return cast(price, {value: small integer});
+        }
+    "#);
+    let errors = match resolve_sources(get_test_stdlib(), source) {
+        Ok(_) => panic!("Structure must not be cast explicitly"),
+        Err(errors) => errors,
+    };
+    let kinds: Vec<_> = errors.into_iter()
+        .map(|error| error.kind)
+        .collect();
+    assert_eq!(kinds, vec![SemanticErrorKind::NotAllowedHere {
+        feature: "cast to a type that is neither primitive nor enum",
+    }]);
+}
+
+#[test]
+fn predicates_are_returned_as_bit_values() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+
+    let definition = sql_definition(&sql, "users::has_paid_orders");
+    assert!(definition.contains("RETURN IIF(exists (SELECT [b].id AS id FROM ("), "{}", definition);
+    assert!(sql_definition(&sql, "users::is_paid").contains("RETURN IIF(@status = 1, 1, 0);"));
+    let definition = sql_definition(&sql, "users::has_users");
+    assert!(definition.contains("SET @found = IIF(exists (SELECT [u].id AS id FROM [users::Users] AS [u]), 1, 0);"), "{}", definition);
+}
+
+#[test]
+fn scalar_selections_should_return_one_row() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("items", "::"), "items.n", r#"
+        table Items {
+            #[primary_key]
+            id: unsigned integer,
+        }
+
+        fn get_first_id(): unsigned integer {
+            return (select i.id from Items i order by i.id limit 1);
+        }
+
+        fn get_any_id(): unsigned integer {
+            return (select i.id from Items i);
+        }
+    "#);
+    let errors = match resolve_sources(get_test_stdlib(), source) {
+        Ok(_) => panic!("Selection of several rows must not be used as value"),
+        Err(errors) => errors,
+    };
+    let kinds: Vec<_> = errors.into_iter().map(|error| error.kind).collect();
+    assert_eq!(kinds, vec![
+        SemanticErrorKind::NotAllowedInside {
+            feature: "selection of several rows without limit 1",
+            output_feature: "expression",
+        },
+    ]);
+}
+
+#[test]
+fn common_tables_are_declared_in_with_clause() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+
+    let definition = sql_definition(&sql, "users::get_age_ladder");
+    assert!(definition.contains("AS BEGIN\n    WITH [ages] ([age]) AS (\n"), "{}", definition);
+    assert!(definition.contains("    )\n    INSERT INTO @return_value ([age])\n"), "{}", definition);
+    assert!(definition.contains("[ages] AS [a]"), "{}", definition);
+    assert!(!definition.contains("RECURSIVE") && !definition.contains("DECLARE @ages"), "{}", definition);
+
+    let definition = sql_definition(&sql, "users::get_paid_ids");
+    assert!(definition.contains("    WITH [paid] ([id]) AS (\n"), "{}", definition);
+    assert!(definition.contains("    )\n    INSERT INTO @ids ([id])\n"), "{}", definition);
+    assert!(definition.contains("[paid] AS [p]"), "{}", definition);
+
+    // Во вложенном запросе выражение подставляется производной таблицей и видит внешние столбцы
+    let definition = sql_definition(&sql, "users::get_buyers");
+    assert!(definition.contains("WHERE exists (SELECT [w].id AS id FROM ( SELECT [o].id AS id FROM [users::Orders] AS [o] WHERE [o].id = [u].id ) AS [w] ([id]))\n"), "{}", definition);
+    let definition = sql_definition(&sql, "users::has_paid_orders");
+    assert!(definition.contains("INNER JOIN ( SELECT [o].id AS id FROM [users::Orders] AS [o] WHERE [o].status = 1 ) AS [p] ([id]) ON [u].id = [p].id ) AS [b] ([id])"), "{}", definition);
+    assert!(!definition.contains("DECLARE"), "{}", definition);
+}
+
+#[test]
+fn recursive_common_tables_should_not_be_nested() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("items", "::"), "items.n", r#"
+        table Items {
+            #[primary_key]
+            id: unsigned integer,
+            parent: unsigned integer,
+        }
+
+        fn get_trees(): {id: unsigned integer}[] {
+            return select o.id from Items o where exists(with recursive tree as (select i.id from Items i where i.id = o.id union all select c.id from Items c inner join tree t on c.parent = t.id) select t.id from tree t);
+        }
+
+        fn count_tree(): unsigned integer {
+            let total: unsigned integer := 0;
+            for item in with recursive tree as (select i.id from Items i union all select c.id from Items c inner join tree t on c.parent = t.id) select t.id from tree t {
+                total := 1;
+            };
+            return total;
+        }
+    "#);
+    let errors = match resolve_sources(get_test_stdlib(), source) {
+        Ok(_) => panic!("Nested recursive common tables must not be resolved"),
+        Err(errors) => errors,
+    };
+    let kinds: Vec<_> = errors.into_iter().map(|error| error.kind).collect();
+    assert_eq!(kinds, vec![
+        SemanticErrorKind::NotAllowedInside {
+            feature: "recursive common table expression",
+            output_feature: "nested selection",
+        },
+        SemanticErrorKind::NotAllowedInside {
+            feature: "recursive common table expression",
+            output_feature: "cursor of for-in cycle",
+        },
+    ]);
+}
+
+#[test]
+fn window_functions_should_be_generated_with_over_clause() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+
+    let definition = sql_definition(&sql, "users::get_users_by_age");
+    assert!(definition.contains("    ) RETURNS @return_value TABLE (\n        [id] int, \n        [position] bigint, \n        [previous_age] smallint, \n        [total_age] smallint\n    )\n"), "{}", definition);
+    assert!(definition.contains(r#"
+                [u].id AS id,
+                row_number() OVER (PARTITION BY [u].person_info#sex#is_male ORDER BY [u].person_info#age DESC) AS position,
+                lag([u].person_info#age) OVER (ORDER BY [u].person_info#age ASC) AS previous_age,
+                sum([u].person_info#age) OVER () AS total_age
+            FROM
+"#), "{}", definition);
+}
+
+#[test]
+fn lag_should_return_type_of_its_argument() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("history", "::"), "history.n", r#"
+        table Events {
+            #[primary_key]
+            id: unsigned integer,
+            level: unsigned tiny integer,
+            label: varchar(16),
+        }
+
+        fn get_previous_events(): {id: unsigned integer, #[nullable] previous_level: unsigned tiny integer, #[nullable] previous_label: varchar(16)}[] {
+            return select e.id, lag(e.level) over (order by e.id) as previous_level, lag(e.label) over (order by e.id) as previous_label from Events e;
+        }
+
+        fn get_earlier_levels(): {id: unsigned integer, earlier_level: unsigned tiny integer}[] {
+            return select e.id, lag(e.level, 2, 0) over (order by e.id) as earlier_level from Events e;
+        }
+    "#);
+    let project = match resolve_sources(get_test_stdlib(), source) {
+        Ok(project) => project,
+        Err(errors) => panic!("Got errors: {:?}", errors),
+    };
+    let sql = DatabaseProject::new(&project).generate_string()
+        .expect("Cannot generate output for database");
+
+    let definition = sql_definition(&sql, "history::get_previous_events");
+    assert!(definition.contains("lag([e].level) OVER (ORDER BY [e].id ASC) AS previous_level"), "{}", definition);
+    assert!(definition.contains("lag([e].label) OVER (ORDER BY [e].id ASC) AS previous_label"), "{}", definition);
+
+    let definition = sql_definition(&sql, "history::get_earlier_levels");
+    assert!(definition.contains("lag([e].level, 2, 0) OVER (ORDER BY [e].id ASC) AS earlier_level"), "{}", definition);
+}
+
+#[test]
+fn window_functions_should_be_checked() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("history", "::"), "history.n", r#"
+        table Events {
+            #[primary_key]
+            id: unsigned integer,
+            level: small integer,
+            label: varchar(16),
+        }
+
+        fn get_numbers(): {id: unsigned integer, position: small integer}[] {
+            return select e.id, row_number() over (partition by e.level) as position from Events e;
+        }
+
+        fn get_previous_levels(): {#[nullable] previous_level: small integer}[] {
+            return select lag(e.level) over () as previous_level from Events e;
+        }
+
+        fn get_previous_ids(): {previous_id: unsigned integer}[] {
+            return select lag(e.id, 1, e.level) over (order by e.id) as previous_id from Events e;
+        }
+
+        fn get_too_far_levels(): {#[nullable] previous_level: small integer}[] {
+            return select lag(e.level, 1, 0, 0) over (order by e.id) as previous_level from Events e;
+        }
+
+        fn get_ungrouped_positions(): {id: unsigned integer, position: small integer}[] {
+            return select e.id, row_number() over (order by max(e.level)) as position from Events e;
+        }
+
+        fn get_grouped_positions(): {label: varchar(16), top_level: small integer, position: small integer}[] {
+            return select e.label, max(e.level) as top_level, row_number() over (order by max(e.level)) as position from Events e group by e.label;
+        }
+    "#);
+    let errors = resolve_sources(get_test_stdlib(), source)
+        .expect_err("Window functions should be checked");
+    let errors: Vec<SemanticErrorKind> = errors.into_iter().map(|e| e.kind).collect();
+    assert_eq!(errors, vec![
+        SemanticErrorKind::NotAllowedInside {
+            feature: "ordered window function",
+            output_feature: "window without sorting",
+        },
+        SemanticErrorKind::NotAllowedInside {
+            feature: "ordered window function",
+            output_feature: "window without sorting",
+        },
+        SemanticErrorKind::CannotCastType {
+            source: DataType::Primitive(PrimitiveDataType::Number(NumberType::Integer {
+                unsigned: false,
+                zerofill: false,
+                size: 16,
+            })),
+            target: DataType::Primitive(PrimitiveDataType::Number(NumberType::Integer {
+                unsigned: true,
+                zerofill: false,
+                size: 32,
+            })),
+        },
+        SemanticErrorKind::WrongArgumentsCount {
+            expected: 3,
+            got: 4,
+        },
+        SemanticErrorKind::NotAllowedHere {
+            feature: "aggregate functions",
+        },
+    ]);
+}
+
+#[test]
+fn set_operations_are_applied_from_left_to_right() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+
+    let definition = sql_definition(&sql, "users::get_ids_without_orders");
+    assert!(definition.contains("(\n            (\n            SELECT\n                [u].id AS id\n"), "{}", definition);
+    assert!(definition.contains("            EXCEPT\n            SELECT\n                [o].id AS id\n            FROM\n                [users::Orders] AS [o]\n            )\n            INTERSECT\n"), "{}", definition);
+    assert!(definition.contains("[users::Users] AS [v]\n            UNION ALL\n"), "{}", definition);
+    assert_eq!(definition.matches('(').count(), definition.matches(')').count(), "{}", definition);
+
+    let definition = sql_definition(&sql, "users::get_known_ids");
+    assert!(!definition.contains("(\n            (\n"), "{}", definition);
+}
+
+#[test]
+fn set_operations_should_be_checked() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("history", "::"), "history.n", r#"
+        struct EventInfo {
+            level: small integer,
+            is_active: boolean,
+        }
+
+        table Events {
+            #[primary_key]
+            id: unsigned integer,
+            info: EventInfo,
+        }
+
+        fn get_wrong_count(): {id: unsigned integer}[] {
+            return select e.id from Events e union select e.id, e.info.level from Events e;
+        }
+
+        fn get_wrong_types(): {id: unsigned integer, level: small integer}[] {
+            return select e.id, e.info.level from Events e union select e.id, e.info.is_active from Events e;
+        }
+
+        fn get_sorted_infos(): {id: unsigned integer, info: EventInfo}[] {
+            return select e.id, e.info from Events e union select e.id, e.info from Events e order by e.info;
+        }
+    "#);
+    let errors = resolve_sources(get_test_stdlib(), source)
+        .expect_err("Set operations should be checked");
+    let errors: Vec<SemanticErrorKind> = errors.into_iter().map(|e| e.kind).collect();
+    assert_eq!(errors, vec![
+        SemanticErrorKind::SelectWithWrongColumnCount {
+            expected: 1,
+            got: 2,
+        },
+        SemanticErrorKind::CannotCastType {
+            source: DataType::Primitive(PrimitiveDataType::Number(NumberType::Boolean)),
+            target: DataType::Primitive(PrimitiveDataType::Number(NumberType::Integer {
+                unsigned: false,
+                zerofill: false,
+                size: 16,
+            })),
+        },
+        SemanticErrorKind::NotAllowedInside {
+            feature: "sorting by compound value",
+            output_feature: "compound query",
+        },
+    ]);
+}
+
+#[test]
+fn inserts_on_conflict_should_be_merged() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+
+    let definition = sql_definition(&sql, "users::set_user_setting");
+    assert!(definition.contains(r#"
+    MERGE INTO
+        [users::UserSettings] WITH (HOLDLOCK) AS [s]
+    USING (
+        VALUES
+        (
+            @user_id,
+            @name,
+            @value
+        )
+    ) AS [source] ([user_id], [name], [value])
+    ON [s].user_id = [source].[user_id] AND [s].name = [source].[name]
+    WHEN MATCHED THEN
+        UPDATE SET
+            [s].value = @value
+    WHEN NOT MATCHED THEN
+        INSERT ([user_id], [name], [value])
+        VALUES ([source].[user_id], [source].[name], [source].[value])
+    ;
+"#), "{}", definition);
+
+    let definition = sql_definition(&sql, "users::reset_user_settings");
+    assert!(definition.contains("    USING (\n        SELECT\n            t.[user_id] as [user_id],\n            t.[name] as [name],\n            t.[value] as [value]\n        FROM (\n"), "{}", definition);
+    assert!(definition.contains("        ) as t\n    ) AS [source] ([user_id], [name], [value])\n"), "{}", definition);
+}
+
+#[test]
+fn inserts_on_conflict_should_be_checked() {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("settings", "::"), "settings.n", r#"
+        table Settings {
+            #[primary_key]
+            user_id: unsigned integer,
+            #[primary_key]
+            name: varchar(32),
+            value: varchar(256),
+        }
+
+        table Counters {
+            #[primary_key]
+            #[auto_increment]
+            id: unsigned integer,
+            value: unsigned integer,
+        }
+
+        fn set_value(name: varchar(32), value: varchar(256)) {
+            insert into Settings s (s.name, s.value) values (name, value) on conflict update set s.value = value;
+        }
+
+        fn set_counter(id: unsigned integer, value: unsigned integer) {
+            insert into Counters c (c.id, c.value) values (id, value) on conflict update set c.value = value;
+        }
+
+        fn set_joined_value(user_id: unsigned integer, name: varchar(32), value: varchar(256)) {
+            insert into Settings s inner join Counters c on s.user_id = c.id (s.user_id, s.name, s.value) values (user_id, name, value) on conflict update set s.value = value;
+        }
+    "#);
+    let errors = resolve_sources(get_test_stdlib(), source)
+        .expect_err("Inserts on conflict should be checked");
+    let errors: Vec<SemanticErrorKind> = errors.into_iter().map(|e| e.kind).collect();
+    assert_eq!(errors, vec![
+        SemanticErrorKind::PrimaryKeyIsNotInserted {
+            name: "user_id".to_string(),
+        },
+        SemanticErrorKind::NotAllowedInside {
+            feature: "ON CONFLICT clause",
+            output_feature: "insert into table with auto increment primary key",
+        },
+        SemanticErrorKind::CannotDoWithDataSource {
+            action: "upsert into",
+        },
+    ]);
+}
+
+#[test]
+fn procedures_return_values_through_output_parameters() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+
+    let definition = sql_definition(&sql, "users::add_order_returning_id");
+    assert!(definition.contains("@return_value int OUTPUT\n"), "{}", definition);
+    assert!(definition.ends_with("    SET @return_value = @result;\n    RETURN;\nEND"), "{}", definition);
+    assert!(!definition.contains("RETURN @"), "{}", definition);
+
+    let definition = sql_definition(&sql, "users::add_user");
+    assert!(definition.ends_with("         as t;\n    RETURN;\nEND"), "{}", definition);
+}
+
+#[test]
+fn returned_rows_should_be_output_into_table_variables() {
+    let (db, _) = resolve_project();
+    let sql = db.generate_string().expect("Cannot generate output for database");
+
+    let definition = sql_definition(&sql, "users::add_order_returning_id");
+    assert!(definition.contains("    DELETE FROM @t;\n    INSERT INTO\n        [users::Orders]\n        (status, currency)\n        OUTPUT\n            [inserted].id\n        INTO @t ([id])\n        VALUES\n"), "{}", definition);
+    assert!(definition.contains("    INSERT INTO @ids ([id])\n        SELECT t.[id] FROM\n            (SELECT [t].id AS id FROM @t as t)\n        AS t;\n"), "{}", definition);
+
+    let definition = sql_definition(&sql, "users::close_orders");
+    assert!(definition.contains(r#"
+        SET
+            status = 1
+        OUTPUT
+            [inserted].id,
+            [inserted].status,
+            [inserted].currency
+        INTO @t ([o#id], [o#status], [o#currency])
+        WHERE currency = @currency
+"#), "{}", definition);
+    assert!(definition.contains(r#"
+    DELETE FROM @t_0;
+    DELETE FROM
+            [users::Orders]
+        OUTPUT
+            [deleted].id,
+            [deleted].status
+        INTO @t_0 ([id], [status])
+        WHERE status = 0
+"#), "{}", definition);
+    assert!(definition.contains("        WHERE currency = @currency\n        ;\n    INSERT INTO @paid"), "{}", definition);
+    assert!(definition.contains("        WHERE status = 0\n        ;\n    INSERT INTO @deleted"), "{}", definition);
+
+    assert!(definition.ends_with(r#"
+    SELECT t.[o#id], t.[o#status], t.[o#currency] FROM
+        (SELECT [t].o#id AS o#id, [t].o#status AS o#status, [t].o#currency AS o#currency FROM @paid as t)
+    AS t;
+    SELECT t.[id], t.[status] FROM
+        (SELECT [t].id AS id, [t].status AS status FROM @deleted as t)
+    AS t;
+    RETURN;
+END"#), "{}", definition);
+
+    let definition = sql_definition(&sql, "users::add_order_returning_id");
+    assert!(definition.contains(r#"
+    SET @item_cursor = CURSOR FAST_FORWARD FOR
+        SELECT t.[id] FROM
+            (SELECT [t].id AS id FROM @ids as t)
+        AS t;
+"#), "{}", definition);
+
+    let definition = sql_definition(&sql, "users::set_user_setting_returning");
+    assert!(definition.contains("        VALUES ([source].[user_id], [source].[name], [source].[value])\n    OUTPUT\n        [inserted].value\n    INTO @t ([value])\n    ;\n"), "{}", definition);
+}
+
 #[test]
 fn item_order_should_always_be_the_same() {
     let (db_code, rpc_code) = {
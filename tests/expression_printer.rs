@@ -0,0 +1,194 @@
+extern crate n_lang;
+
+use n_lang::{
+    code_generation::{
+        DatabaseProject,
+        RPCModule,
+    },
+    helpers::{
+        Path as ModulePath,
+        Resolve,
+        SyncRef,
+    },
+    language::{
+        BinaryOperator,
+        DataType,
+        NumberType,
+        PostfixUnaryOperator,
+        PrefixUnaryOperator,
+        PrimitiveDataType,
+    },
+    project_analysis::{
+        HashMapSource,
+        ProjectContext,
+        StdLib,
+        StdLibBinaryOperation,
+        StdLibPostfixUnaryOperation,
+        StdLibPrefixUnaryOperation,
+    },
+};
+
+const ARITHMETIC_OPERATORS: [BinaryOperator; 11] = [
+    BinaryOperator::BitOr,
+    BinaryOperator::BitXOr,
+    BinaryOperator::BitAnd,
+    BinaryOperator::ShiftLeft,
+    BinaryOperator::ShiftRight,
+    BinaryOperator::Plus,
+    BinaryOperator::Minus,
+    BinaryOperator::Times,
+    BinaryOperator::Divide,
+    BinaryOperator::Mod,
+    BinaryOperator::Pow,
+];
+
+const COMPARISON_OPERATORS: [BinaryOperator; 5] = [
+    BinaryOperator::Equals,
+    BinaryOperator::MoreThanOrEquals,
+    BinaryOperator::MoreThan,
+    BinaryOperator::LessThanOrEquals,
+    BinaryOperator::LessThan,
+];
+
+const LOGICAL_OPERATORS: [BinaryOperator; 4] = [
+    BinaryOperator::Or,
+    BinaryOperator::XOr,
+    BinaryOperator::And,
+    BinaryOperator::Equals,
+];
+
+fn get_test_stdlib() -> StdLib {
+    let mut stdlib = StdLib::new();
+
+    let integer = DataType::Primitive(PrimitiveDataType::Number(NumberType::Integer {
+        unsigned: false,
+        zerofill: false,
+        size: 32,
+    }));
+
+    let boolean = DataType::Primitive(PrimitiveDataType::Number(NumberType::Boolean));
+
+    for &operator in ARITHMETIC_OPERATORS.iter() {
+        stdlib.reg_binary_operation(StdLibBinaryOperation::new(operator, integer.clone(), integer.clone(), integer.clone()));
+    }
+    for &operator in LOGICAL_OPERATORS.iter() {
+        stdlib.reg_binary_operation(StdLibBinaryOperation::new(operator, boolean.clone(), boolean.clone(), boolean.clone()));
+    }
+    for &operator in COMPARISON_OPERATORS.iter() {
+        stdlib.reg_binary_operation(StdLibBinaryOperation::new(operator, integer.clone(), integer.clone(), boolean.clone()));
+    }
+    for &operator in [PrefixUnaryOperator::Plus, PrefixUnaryOperator::Minus, PrefixUnaryOperator::Tilde].iter() {
+        stdlib.reg_prefix_unary_operation(StdLibPrefixUnaryOperation::new(operator, integer.clone(), integer.clone()));
+    }
+    stdlib.reg_prefix_unary_operation(StdLibPrefixUnaryOperation::new(PrefixUnaryOperator::Not, boolean.clone(), boolean.clone()));
+    for &operator in [PostfixUnaryOperator::IsTrue, PostfixUnaryOperator::IsFalse, PostfixUnaryOperator::IsNull].iter() {
+        stdlib.reg_postfix_unary_operation(StdLibPostfixUnaryOperation::new(operator, boolean.clone(), boolean.clone()));
+    }
+
+    stdlib
+}
+
+fn resolve(text: &str) -> (DatabaseProject, RPCModule) {
+    let mut source = HashMapSource::new();
+    source.simple_insert(ModulePath::new("printer", "::"), "printer.n", text);
+    let project = ProjectContext::new(SyncRef::new(get_test_stdlib()));
+    for (module_path, _) in source.texts() {
+        project.request_resolving_module(module_path.as_path());
+    }
+    let project = project.resolve(&source)
+        .unwrap_or_else(|errors| panic!("Expressions must be resolved: {:?}", errors));
+    (DatabaseProject::new(&project), RPCModule::top(&project))
+}
+
+/// Каждое выражение возвращается своей функцией, а в T-SQL проверяется выражение её `RETURN`
+fn print_tsql(cases: &[(&str, &str, &str)]) -> Vec<String> {
+    let mut text = String::new();
+    for (i, &(result_type, source, _)) in cases.iter().enumerate() {
+        text += &format!(
+            "fn e{}(a: integer, b: integer, c: integer, p: boolean, q: boolean, r: boolean): {} {{ return {}; }}\n",
+            i,
+            result_type,
+            source,
+        );
+    }
+    let (db, _) = resolve(text.as_str());
+    let sql = db.generate_string().expect("Cannot generate output for database");
+    (0..cases.len())
+        .map(|i| {
+            let header = format!(" dbo.[printer::e{}]\n", i);
+            let definition = &sql[sql.find(&header).expect("Function must be generated")..];
+            let begin = definition.find("RETURN ").expect("Function must return expression") + "RETURN ".len();
+            let end = definition[begin..].find(";\n").expect("Return must be finished");
+            definition[begin..begin + end].to_string()
+        })
+        .collect()
+}
+
+/// Ожидаемый вывод выписан вручную по таблицам приоритетов SQL Server
+#[test]
+fn tsql_expressions_print_with_sql_server_precedence() {
+    let cases = [
+        ("integer", "a + b * c", "@a + @b * @c"),
+        ("integer", "(a + b) * c", "(@a + @b) * @c"),
+        ("integer", "a - (b - c)", "@a - (@b - @c)"),
+        ("integer", "a * (b % c)", "@a * (@b % @c)"),
+        ("integer", "a % b * c", "@a % @b * @c"),
+        // Побитовые операции стоят наравне со сложением
+        ("integer", "(a & b) + c", "@a & @b + @c"),
+        ("integer", "a & (b + c)", "@a & (@b + @c)"),
+        ("boolean", "(a & b) = c", "@a & @b = @c"),
+        // Унарный минус стоит наравне со сложением, т.е. слабее умножения
+        ("integer", "-a * b", "(- @a) * @b"),
+        ("integer", "-(a * b)", "- @a * @b"),
+        ("integer", "~a + b", "~ @a + @b"),
+        ("boolean", "a < b || b < c && a = c", "@a < @b OR @b < @c AND @a = @c"),
+        ("boolean", "(a < b || b < c) && a = c", "(@a < @b OR @b < @c) AND @a = @c"),
+        // NOT слабее сравнений, поэтому там, где нужно значение, он становится им
+        ("boolean", "!(a = b)", "NOT @a = @b"),
+        ("boolean", "!(a < b) = p", "IIF(NOT @a < @b, 1, 0) = @p"),
+        // Исключающее или сравнивает битовые значения операндов
+        ("boolean", "p xor q", "@p <> @q"),
+        ("boolean", "(a < b) xor !(b < c)", "IIF(@a < @b, 1, 0) <> IIF(NOT @b < @c, 1, 0)"),
+        // Возведение в степень выводится вызовом функции и в скобках не нуждается
+        ("integer", "a * (b + c) ** 2", "@a * POWER(@b + @c, 2)"),
+        ("integer", "a ** b ** c", "POWER(@a, POWER(@b, @c))"),
+        ("boolean", "p is true", "@p = 1"),
+        ("boolean", "(a = b) is false", "IIF(@a = @b, 1, 0) = 0"),
+    ];
+    let printed = print_tsql(&cases);
+    for (&(result_type, source, expected), printed) in cases.iter().zip(printed.iter()) {
+        // Логическое значение функция возвращает битом
+        if result_type == "boolean" {
+            assert_eq!(printed, &format!("IIF({}, 1, 0)", expected), "{}", source);
+        } else {
+            assert_eq!(printed, expected, "{}", source);
+        }
+    }
+}
+
+/// Ожидаемый вывод выписан вручную по таблице приоритетов операторов JavaScript.
+/// Константы сворачиваются, поэтому в них остаются только операции, которые не вычисляются при компиляции.
+#[test]
+fn typescript_constants_print_with_javascript_precedence() {
+    let cases = [
+        ("integer", "(1 << 2) + 3", "(1 << 2) + 3"),
+        ("integer", "1 << (2 | 3)", "1 << (2 | 3)"),
+        ("boolean", "(1 & 2) = 3", "(1 & 2) === 3"),
+        ("integer", "2 ** 3 ** 2", "2 ** 3 ** 2"),
+        ("integer", "(2 ** 3) ** 2", "(2 ** 3) ** 2"),
+        ("integer", "(-2) ** 2", "(-2) ** 2"),
+        ("integer", "(1 | 2) * 3", "(1 | 2) * 3"),
+        ("integer", "7 / (1 << 1)", "Math.trunc(7 / (1 << 1))"),
+    ];
+    let mut text = String::new();
+    for (i, &(data_type, source, _)) in cases.iter().enumerate() {
+        text += &format!("const E{}: {} = {};\n", i, data_type, source);
+    }
+    let (_, rpc) = resolve(text.as_str());
+    let code = rpc.generate_string().expect("Cannot generate output for RPC");
+    for (i, &(data_type, source, expected)) in cases.iter().enumerate() {
+        let ts_type = if data_type == "boolean" { "boolean" } else { "number" };
+        let line = format!("export const E{}: {} = {};\n", i, ts_type, expected);
+        assert!(code.contains(&line), "{}\n{}", source, code);
+    }
+}
@@ -45,6 +45,71 @@ fn scans_alone_string_correctly() {
     assert_eq!(scanner.next(), None);
 }
 
+fn scan_kinds(text: &str) -> Vec<(TokenKind, &str)> {
+    Scanner::scan(text)
+        .expect("Scanner result must be ok")
+        .into_iter()
+        .map(|token| (token.kind, token.text))
+        .collect()
+}
+
+#[test]
+fn scans_label_correctly() {
+    let _ = try_init();
+    assert_eq!(
+        scan_kinds("'outer: loop { break 'outer }"),
+        vec![
+            (TokenKind::Label, "'outer"),
+            (TokenKind::SymbolGroup, ":"),
+            (TokenKind::Word, "loop"),
+            (TokenKind::SymbolGroup, "{"),
+            (TokenKind::Word, "break"),
+            (TokenKind::Label, "'outer"),
+            (TokenKind::SymbolGroup, "}"),
+            (TokenKind::EndOfInput, ""),
+        ]
+    );
+    assert_eq!(
+        scan_kinds("{ 'a : while true { continue 'a; } }"),
+        vec![
+            (TokenKind::SymbolGroup, "{"),
+            (TokenKind::Label, "'a"),
+            (TokenKind::SymbolGroup, ":"),
+            (TokenKind::Word, "while"),
+            (TokenKind::Word, "true"),
+            (TokenKind::SymbolGroup, "{"),
+            (TokenKind::Word, "continue"),
+            (TokenKind::Label, "'a"),
+            (TokenKind::SymbolGroup, ";"),
+            (TokenKind::SymbolGroup, "}"),
+            (TokenKind::SymbolGroup, "}"),
+            (TokenKind::EndOfInput, ""),
+        ]
+    );
+}
+
+#[test]
+fn scans_braced_literal_looking_like_label_correctly() {
+    let _ = try_init();
+    assert_eq!(
+        scan_kinds("x = 'a;b'"),
+        vec![
+            (TokenKind::Word, "x"),
+            (TokenKind::SymbolGroup, "="),
+            (TokenKind::BracedExpressionLiteral { length: 3 }, "'a;b'"),
+            (TokenKind::EndOfInput, ""),
+        ]
+    );
+    assert_eq!(
+        scan_kinds("return 'a: loop'"),
+        vec![
+            (TokenKind::Word, "return"),
+            (TokenKind::BracedExpressionLiteral { length: 7 }, "'a: loop'"),
+            (TokenKind::EndOfInput, ""),
+        ]
+    );
+}
+
 #[test]
 fn scans_alone_binary_number_correctly() {
     let _ = try_init();
@@ -53,7 +118,7 @@ fn scans_alone_binary_number_correctly() {
     assert_eq!(
         scanner.next(),
         Token::new_wrapped(
-            TokenKind::NumberLiteral { negative: false, radix: 2, fractional: false },
+            TokenKind::NumberLiteral { negative: false, radix: 2, fractional: false, approx_value: 13.0 },
             text,
             SymbolPosition::default(),
         )
@@ -81,7 +146,7 @@ fn scans_alone_octal_number_correctly() {
     assert_eq!(
         scanner.next(),
         Token::new_wrapped(
-            TokenKind::NumberLiteral { negative: false, radix: 8, fractional: false },
+            TokenKind::NumberLiteral { negative: false, radix: 8, fractional: false, approx_value: 74.0 },
             text,
             SymbolPosition::default(),
         )
@@ -109,7 +174,7 @@ fn scans_alone_decimal_number_correctly() {
     assert_eq!(
         scanner.next(),
         Token::new_wrapped(
-            TokenKind::NumberLiteral { negative: false, radix: 10, fractional: false },
+            TokenKind::NumberLiteral { negative: false, radix: 10, fractional: false, approx_value: 1.0 },
             text,
             SymbolPosition::default(),
         )
@@ -137,7 +202,7 @@ fn scans_alone_hexadecimal_number_correctly() {
     assert_eq!(
         scanner.next(),
         Token::new_wrapped(
-            TokenKind::NumberLiteral { negative: false, radix: 16, fractional: false },
+            TokenKind::NumberLiteral { negative: false, radix: 16, fractional: false, approx_value: 7980.0 },
             text,
             SymbolPosition::default(),
         )
@@ -165,7 +230,7 @@ fn scans_alone_negative_number_correctly() {
     assert_eq!(
         scanner.next(),
         Token::new_wrapped(
-            TokenKind::NumberLiteral { negative: true, radix: 10, fractional: false },
+            TokenKind::NumberLiteral { negative: true, radix: 10, fractional: false, approx_value: -12.0 },
             text,
             SymbolPosition::default(),
         )
@@ -193,7 +258,7 @@ fn scans_alone_fractional_number_correctly() {
     assert_eq!(
         scanner.next(),
         Token::new_wrapped(
-            TokenKind::NumberLiteral { negative: false, radix: 10, fractional: true },
+            TokenKind::NumberLiteral { negative: false, radix: 10, fractional: true, approx_value: 0.30000000000000004 },
             text,
             SymbolPosition::default(),
         )
@@ -245,17 +310,12 @@ fn scans_complex_text_correctly() {
     pos.step_str("{ ");
     assert_eq!(
         scanner.next(),
-        Token::new_wrapped(TokenKind::SymbolGroup, "[", pos.clone())
-    );
-    pos.step_str("[");
-    assert_eq!(
-        scanner.next(),
-        Token::new_wrapped(TokenKind::SymbolGroup, "]", pos.clone())
+        Token::new_wrapped(TokenKind::SymbolGroup, "[]", pos.clone())
     );
-    pos.step_str("] ");
+    pos.step_str("[] ");
     assert_eq!(
         scanner.next(),
-        Token::new_wrapped(TokenKind::NumberLiteral { negative: false, radix: 10, fractional: false }, "1", pos.clone())
+        Token::new_wrapped(TokenKind::NumberLiteral { negative: false, radix: 10, fractional: false, approx_value: 1.0 }, "1", pos.clone())
     );
     pos.step_str("1");
     assert_eq!(
@@ -265,7 +325,7 @@ fn scans_complex_text_correctly() {
     pos.step_str(", ");
     assert_eq!(
         scanner.next(),
-        Token::new_wrapped(TokenKind::NumberLiteral { negative: false, radix: 10, fractional: false }, "2", pos.clone())
+        Token::new_wrapped(TokenKind::NumberLiteral { negative: false, radix: 10, fractional: false, approx_value: 2.0 }, "2", pos.clone())
     );
     pos.step_str("2");
     assert_eq!(
@@ -389,6 +449,7 @@ fn coverts_into_token_iterator_correctly() {
                 negative: false,
                 radix: 10,
                 fractional: false,
+                approx_value: 2.0,
             },
             "2",
             SymbolPosition {
@@ -41,7 +41,7 @@ fn string_and_braced_literals_parses_correctly() {
     {
         assert_eq!(
             extract_literal(parse!("\"azaz\"", expression)),
-            LiteralType::StringLiteral { length: 4 }
+            LiteralType::StringLiteral { length: 4, national: false }
         );
     }
     {
@@ -227,9 +227,9 @@ fn property_access_and_set_and_function_call_expression_parses_correctly() {
 #[test]
 fn simple_join_parses_correctly() {
     let result = parse!("foo f INNER JOIN bar b ON f.foo_id = b.bar_id", data_source);
-    let (left, right) = match_it!(result, DataSource::Join { join_type, condition, left, right } => {
-        assert_eq!(join_type, JoinType::Cross);
-        match_it!(condition, Some(JoinCondition::Expression(condition)) => {
+    let (left, right) = match_it!(result, DataSourceAST::Join { join_type, condition, left, right } => {
+        assert_eq!(join_type, JoinType::Inner);
+        match_it!(condition, Some(JoinConditionAST::Expression(condition)) => {
             condition.assert("f.foo_id = b.bar_id");
         });
         (*left, *right)
@@ -387,19 +387,22 @@ fn complex_query_parses_correctly() {
         offset: None,
         count: 100,
     }));
-    let subquery_0 = match_it!(&query.source, &DataSource::Join {
+    let subquery_0 = match_it!(&query.source, &DataSourceAST::Join {
             join_type: JoinType::Left,
-            condition: Some(JoinCondition::Using(ref cond)),
+            condition: Some(JoinConditionAST::Using(ref columns)),
             ref left,
             ref right,
         } => {
-            assert_eq!(*cond, vec![vec!["item_id"]]);
+            let columns: Vec<_> = columns.iter()
+                .map(|column| column.path.to_string())
+                .collect();
+            assert_eq!(columns, vec!["item_id"]);
             assert_table(&**right, "Items", Some("i"));
             (**left).clone()
         });
-    let subquery_1 = match_it!(subquery_0, DataSource::Join {
+    let subquery_1 = match_it!(subquery_0, DataSourceAST::Join {
             join_type: JoinType::Left,
-            condition: Some(JoinCondition::Expression(ref cond)),
+            condition: Some(JoinConditionAST::Expression(ref cond)),
             ref left,
             ref right,
         } => {
@@ -407,9 +410,9 @@ fn complex_query_parses_correctly() {
             assert_table(&**right, "Mans", Some("m"));
             (**left).clone()
         });
-    let subquery_2 = match_it!(subquery_1, DataSource::Join {
-            join_type: JoinType::Cross,
-            condition: Some(JoinCondition::Expression(ref cond)),
+    let subquery_2 = match_it!(subquery_1, DataSourceAST::Join {
+            join_type: JoinType::Inner,
+            condition: Some(JoinConditionAST::Expression(ref cond)),
             ref left,
             ref right,
         } => {